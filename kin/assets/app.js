@@ -0,0 +1,126 @@
+// A small force-directed viewer for the node-link JSON served by `kin serve`,
+// or embedded directly in the pages written by `kin site`.
+
+const COLOURS = {
+  Same: "green",
+  Second: "greenyellow",
+  Voicing: "gold",
+  Rendaku: "gold",
+  Rhyme: "yellow",
+  Clipped: "cyan",
+  Consonant: "orange",
+  Differ: "red",
+  None: "gray",
+};
+
+const svg = document.getElementById("graph");
+const NS = "http://www.w3.org/2000/svg";
+
+async function show(kanji) {
+  const res = await fetch("/family?k=" + encodeURIComponent(kanji));
+  if (!res.ok) {
+    svg.innerHTML = "";
+    return;
+  }
+  draw(await res.json(), kanji);
+}
+
+function draw(graph, chosen) {
+  const { width, height } = svg.getBoundingClientRect();
+  const byId = new Map();
+  graph.nodes.forEach((n, i) => {
+    n.x = width / 2 + Math.cos(i) * 100;
+    n.y = height / 2 + Math.sin(i) * 100;
+    byId.set(n.id, n);
+  });
+  const links = graph.links.map((l) => ({
+    source: byId.get(l.source),
+    target: byId.get(l.target),
+    inherit: l.inherit,
+  }));
+
+  // Spring forces along edges, repulsion between all nodes.
+  for (let step = 0; step < 300; step++) {
+    for (const a of graph.nodes) {
+      for (const b of graph.nodes) {
+        if (a === b) continue;
+        const dx = a.x - b.x || 0.01;
+        const dy = a.y - b.y || 0.01;
+        const d2 = dx * dx + dy * dy;
+        a.x += (dx / d2) * 200;
+        a.y += (dy / d2) * 200;
+      }
+    }
+    for (const l of links) {
+      const dx = l.target.x - l.source.x;
+      const dy = l.target.y - l.source.y;
+      const d = Math.sqrt(dx * dx + dy * dy) || 1;
+      const f = (d - 80) / d / 10;
+      l.source.x += dx * f;
+      l.source.y += dy * f;
+      l.target.x -= dx * f;
+      l.target.y -= dy * f;
+    }
+    // Parents drift upwards, children downwards.
+    for (const l of links) {
+      l.source.y -= 1;
+      l.target.y += 1;
+    }
+    for (const n of graph.nodes) {
+      n.x += (width / 2 - n.x) * 0.01;
+      n.y += (height / 2 - n.y) * 0.01;
+    }
+  }
+
+  svg.innerHTML = "";
+  for (const l of links) {
+    const line = document.createElementNS(NS, "line");
+    line.setAttribute("x1", l.source.x);
+    line.setAttribute("y1", l.source.y);
+    line.setAttribute("x2", l.target.x);
+    line.setAttribute("y2", l.target.y);
+    line.setAttribute("stroke", COLOURS[l.inherit] || "black");
+    line.setAttribute("stroke-width", 2);
+    const title = document.createElementNS(NS, "title");
+    title.textContent = l.inherit;
+    line.appendChild(title);
+    svg.appendChild(line);
+  }
+  for (const n of graph.nodes) {
+    const g = document.createElementNS(NS, "g");
+    g.setAttribute("class", n.kanji === chosen ? "node chosen" : "node");
+    g.setAttribute("transform", `translate(${n.x},${n.y})`);
+    const circle = document.createElementNS(NS, "circle");
+    circle.setAttribute("r", 16);
+    const text = document.createElementNS(NS, "text");
+    text.textContent = n.kanji;
+    const title = document.createElementNS(NS, "title");
+    title.textContent = [n.onyomi.join(" "), n.level, ...(n.notes || [])]
+      .filter(Boolean)
+      .join(" / ");
+    g.append(circle, text, title);
+    g.addEventListener("click", () => visit(n.kanji));
+    svg.appendChild(g);
+  }
+}
+
+// On a static site, every Kanji has its own page to move to instead.
+function visit(kanji) {
+  if (window.FAMILY) {
+    location.href = encodeURIComponent(kanji) + ".html";
+  } else {
+    show(kanji);
+  }
+}
+
+const search = document.getElementById("search");
+if (search) {
+  search.addEventListener("submit", (e) => {
+    e.preventDefault();
+    show(document.getElementById("kanji").value.trim());
+  });
+}
+
+if (window.FAMILY) {
+  draw(window.FAMILY, window.CHOSEN);
+}
@@ -0,0 +1,140 @@
+//! Copies of the data file, and how long to keep them.
+//!
+//! Backups live in a `backups/` directory next to the data file, named after
+//! the data file and the Unix time they were taken at, like
+//! `backups/data-1700000000.json`. One is taken before every write to the data
+//! file, and `kin undo` puts the latest one back. After every command that
+//! writes to the data file, older backups are thinned out according to a
+//! `Retention` policy so that the directory doesn't grow without bound.
+
+use crate::Error;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The name of the directory backups are kept in, next to the data file.
+pub const BACKUP_DIR: &str = "backups";
+
+const DAY: u64 = 60 * 60 * 24;
+const WEEK: u64 = DAY * 7;
+
+/// Which backups survive pruning. A backup is kept if any rule wants it.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Retention {
+    /// Always keep this many of the most recent backups.
+    pub keep_last: usize,
+    /// Keep the newest backup of each of this many recent days.
+    pub keep_daily: u64,
+    /// Keep the newest backup of each of this many recent weeks.
+    pub keep_weekly: u64,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            keep_last: 10,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+/// The directory that holds the backups of a data file.
+pub fn backup_dir(data: &Path) -> PathBuf {
+    data.with_file_name(BACKUP_DIR)
+}
+
+/// All backups of a data file with the times they were taken, newest first.
+pub fn backups(data: &Path) -> Result<Vec<(u64, PathBuf)>, Error> {
+    let prefix = format!("{}-", stem(data));
+    let dir = match std::fs::read_dir(backup_dir(data)) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(Error::Io(e)),
+    };
+
+    let mut found = Vec::new();
+    for entry in dir {
+        let path = entry.map_err(Error::Io)?.path();
+        let time = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.strip_prefix(&prefix))
+            .and_then(|t| t.parse::<u64>().ok());
+
+        if let Some(t) = time {
+            found.push((t, path));
+        }
+    }
+
+    found.sort_by(|a, b| b.cmp(a));
+    Ok(found)
+}
+
+/// Copy the data file into the backup directory, stamped with the given time.
+/// Nothing is taken if there's no data file yet, if the data is a directory of
+/// shards, or if a backup was already taken within the same second.
+pub fn take(data: &Path, now: u64) -> Result<Option<PathBuf>, Error> {
+    if !data.is_file() {
+        return Ok(None);
+    }
+
+    let ext = data
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let path = backup_dir(data).join(format!("{}-{}{}", stem(data), now, ext));
+
+    if path.exists() {
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(backup_dir(data)).map_err(Error::Io)?;
+    std::fs::copy(data, &path).map_err(Error::Io)?;
+    Ok(Some(path))
+}
+
+/// Replace the data file with its most recent backup, which is used up in the
+/// process so that repeated restores go further back in time. Yields the time
+/// the restored backup was taken, if there was one.
+pub fn restore_latest(data: &Path) -> Result<Option<u64>, Error> {
+    match backups(data)?.into_iter().next() {
+        None => Ok(None),
+        Some((t, path)) => {
+            std::fs::rename(&path, data).map_err(Error::Io)?;
+            Ok(Some(t))
+        }
+    }
+}
+
+/// Delete the backups that the policy doesn't want anymore, yielding how many
+/// were removed.
+pub fn prune(data: &Path, policy: &Retention, now: u64) -> Result<usize, Error> {
+    let all = backups(data)?;
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = 0;
+
+    for (i, (t, path)) in all.iter().enumerate() {
+        let age = now.saturating_sub(*t);
+        // Backups are visited newest first, so the first one seen in a given
+        // day or week is the one to keep.
+        let daily = age < policy.keep_daily * DAY && days.insert(t / DAY);
+        let weekly = age < policy.keep_weekly * WEEK && weeks.insert(t / WEEK);
+
+        if i >= policy.keep_last && !daily && !weekly {
+            std::fs::remove_file(path).map_err(Error::Io)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// The name of the data file, without its extension.
+fn stem(data: &Path) -> String {
+    data.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "data".to_string())
+}
@@ -0,0 +1,119 @@
+//! User settings, read once at startup.
+//!
+//! Settings live in `~/.config/kanji-net/config.toml` (or under
+//! `$XDG_CONFIG_HOME`, if set). Every setting is optional:
+//!
+//! ```toml
+//! data = "/home/me/kanji/data.json"
+//! history = "/home/me/.local/share/kanji-net/history.txt"
+//! usage = true
+//! commit = true
+//!
+//! [graph]
+//! format = "svg"
+//!
+//! [rules]
+//! rendaku = true
+//!
+//! [colours]
+//! differ = "#ff8080"
+//!
+//! [backups]
+//! keep_last = 10
+//! keep_daily = 7
+//! keep_weekly = 4
+//! ```
+//!
+//! The `KANJI_NET_DATA` environment variable overrides `data`, and the
+//! `--data` flag overrides both.
+
+use crate::backup::Retention;
+use crate::Error;
+use kn_core::{Palette, Rules};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// The environment variable that overrides the data path.
+const DATA_VAR: &str = "KANJI_NET_DATA";
+
+/// Settings that apply across commands.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Path to the Kanji data file.
+    pub data: PathBuf,
+    /// Path to the history file of interactive prompts.
+    pub history: PathBuf,
+    /// Keep a local ledger of the commands run and the Kanji consulted.
+    pub usage: bool,
+    /// Commit changes to the data file with git after every command.
+    pub commit: bool,
+    pub graph: GraphConfig,
+    /// Edge colours for `kin graph`.
+    pub colours: Palette,
+    /// The optional rules to classify edges with, as if always given to `kin
+    /// graph` as flags. Entries added by `kin new` and `kin repl` are
+    /// classified with these too.
+    pub rules: Rules,
+    /// How many backups of the data file to keep.
+    pub backups: Retention,
+}
+
+/// Settings for `kin graph`.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphConfig {
+    /// The image format to have `dot` produce (e.g. png, svg).
+    pub format: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data: PathBuf::from("data.json"),
+            history: PathBuf::from("history.txt"),
+            usage: false,
+            commit: false,
+            graph: GraphConfig::default(),
+            colours: Palette::default(),
+            rules: Rules::default(),
+            backups: Retention::default(),
+        }
+    }
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        GraphConfig {
+            format: "png".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Read the config file, if there is one, and apply environment overrides.
+    pub fn load() -> Result<Config, Error> {
+        let mut config = match config_path() {
+            None => Config::default(),
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(raw) => toml::from_str(&raw).map_err(Error::Toml)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+                Err(e) => Err(Error::Io(e))?,
+            },
+        };
+
+        if let Some(data) = std::env::var_os(DATA_VAR) {
+            config.data = PathBuf::from(data);
+        }
+
+        Ok(config)
+    }
+}
+
+/// The expected location of the config file.
+fn config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|dir| dir.join("kanji-net").join("config.toml"))
+}
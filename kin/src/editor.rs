@@ -0,0 +1,99 @@
+//! Editing entries as a whole in `$EDITOR`.
+//!
+//! The entry is written out as JSON to a temporary file, and read back once the
+//! editor exits. If what comes back can't be understood or doesn't make sense,
+//! the problem is reported and the file can be opened again with the user's
+//! changes intact.
+
+use crate::prompt::Prompt;
+use crate::Error;
+use kn_core::{utils, Entry};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+/// Let the user edit an entry in their editor until it passes `validate`.
+/// Yields `None` if they give up.
+pub fn edit<F>(rl: &mut Prompt, template: &Entry, validate: F) -> Result<Option<Entry>, Error>
+where
+    F: Fn(&Entry) -> Result<(), Error>,
+{
+    // Empty fields are normally left out, but here they're what's to be filled in.
+    let mut value = serde_json::to_value(template).map_err(Error::Json)?;
+    if let Some(fields) = value.as_object_mut() {
+        for field in [
+            "oya",
+            "kakushi_oya",
+            "onyomi",
+            "daihyou",
+            "notes",
+            "tags",
+            "variants",
+            "nite",
+        ] {
+            fields.entry(field).or_insert_with(|| serde_json::json!([]));
+        }
+    }
+    let raw = serde_json::to_string_pretty(&value).map_err(Error::Json)?;
+
+    // Removed again when dropped, however the editing goes.
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!("kin-{}-", template.kanji))
+        .suffix(".json")
+        .tempfile()
+        .map_err(Error::Io)?;
+    file.write_all(raw.as_bytes()).map_err(Error::Io)?;
+    file.flush().map_err(Error::Io)?;
+
+    edit_loop(rl, file.path(), validate)
+}
+
+fn edit_loop<F>(rl: &mut Prompt, path: &Path, validate: F) -> Result<Option<Entry>, Error>
+where
+    F: Fn(&Entry) -> Result<(), Error>,
+{
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    loop {
+        let status = Command::new(&editor)
+            .arg(path)
+            .status()
+            .map_err(Error::Io)?;
+        if !status.success() {
+            Err(Error::Other("The editor exited abnormally."))?;
+        }
+
+        let raw = std::fs::read_to_string(path).map_err(Error::Io)?;
+        let checked = parse(&raw).and_then(|mut entry| {
+            entry
+                .onyomi
+                .iter_mut()
+                .for_each(|y| y.reading = utils::normalize_kana(y).into());
+            validate(&entry).map(|_| entry)
+        });
+
+        match checked {
+            Ok(entry) => return Ok(Some(entry)),
+            Err(e) => {
+                println!("{}", e);
+                if !crate::confirm(rl, "Edit again? [y/N] ")? {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Read an edited entry, naming the field at fault if it can't be.
+fn parse(raw: &str) -> Result<Entry, Error> {
+    let mut de = serde_json::Deserializer::from_str(raw);
+    serde_path_to_error::deserialize(&mut de).map_err(|e| {
+        let path = e.path().to_string();
+        match path.as_str() {
+            "." => Error::Parse(format!("Couldn't read the entry: {}", e.inner())),
+            _ => Error::Parse(format!("Couldn't read `{}`: {}", path, e.inner())),
+        }
+    })
+}
@@ -0,0 +1,123 @@
+//! Committing changes to the data file, for those who keep it under git.
+//!
+//! The entries are read before a command runs and compared against those on
+//! disk afterwards, so every command that writes gets a sensible message like
+//! `add 語` without having to describe itself.
+
+use crate::Error;
+use kn_core::{Entry, Kanji};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Every entry in the data file, or none if it can't be read.
+pub fn snapshot(data: &Path) -> HashMap<Kanji, Entry> {
+    kn_core::read_entries(data)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|e| (e.kanji, e))
+        .collect()
+}
+
+/// A summary of what changed between two snapshots, or `None` if nothing did.
+pub fn message(before: &HashMap<Kanji, Entry>, after: &HashMap<Kanji, Entry>) -> Option<String> {
+    let mut added: Vec<Kanji> = after
+        .keys()
+        .filter(|k| !before.contains_key(k))
+        .copied()
+        .collect();
+    let mut edited: Vec<Kanji> = after
+        .iter()
+        .filter(|(k, e)| before.get(k).map(|b| b != *e).unwrap_or(false))
+        .map(|(k, _)| *k)
+        .collect();
+    let mut deleted: Vec<Kanji> = before
+        .keys()
+        .filter(|k| !after.contains_key(k))
+        .copied()
+        .collect();
+
+    added.sort();
+    edited.sort();
+    deleted.sort();
+
+    let parts: Vec<String> = [("add", added), ("edit", edited), ("delete", deleted)]
+        .into_iter()
+        .filter(|(_, ks)| !ks.is_empty())
+        .map(|(verb, ks)| {
+            let ks: String = ks.iter().map(|k| k.get()).collect();
+            format!("{} {}", verb, ks)
+        })
+        .collect();
+
+    match parts.is_empty() {
+        true => None,
+        false => Some(parts.join(", ")),
+    }
+}
+
+/// The contents of the data file as of some revision, like `HEAD~5`.
+pub fn show(data: &Path, rev: &str) -> Result<Vec<u8>, Error> {
+    let dir = match data.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let file = data
+        .file_name()
+        .ok_or(Error::Other("No data file to look up."))?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", rev, file.to_string_lossy()))
+        .output()
+        .map_err(Error::Io)?;
+    if !output.status.success() {
+        return Err(Error::Other(
+            "Couldn't find the data file at that revision.",
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Commit the data file, and only the data file, within whatever repository
+/// it lives in.
+pub fn commit(data: &Path, message: &str) -> Result<(), Error> {
+    let dir = match data.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let file = data
+        .file_name()
+        .ok_or(Error::Other("No data file to commit."))?;
+
+    let added = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("add")
+        .arg(file)
+        .status()
+        .map_err(Error::Io)?;
+    if !added.success() {
+        return Err(Error::Other("Couldn't add the data file to git."));
+    }
+
+    let committed = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("commit")
+        .arg("--quiet")
+        .arg("-m")
+        .arg(message)
+        .arg("--")
+        .arg(file)
+        .status()
+        .map_err(Error::Io)?;
+    if !committed.success() {
+        return Err(Error::Other("Couldn't commit the data file."));
+    }
+
+    Ok(())
+}
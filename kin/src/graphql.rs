@@ -0,0 +1,260 @@
+//! A GraphQL schema over the Kanji Graph, served by `kin serve` at `/graphql`.
+//!
+//! Clients can ask for exactly the nested parents and children they need in one
+//! round-trip, e.g.
+//!
+//! ```graphql
+//! { kanji(id: "語") { onyomi parents { inherit kanji { kanji onyomi } } } }
+//! ```
+
+use juniper::{graphql_object, EmptyMutation, EmptySubscription, RootNode};
+use kn_core::{utils, Direction, Kanji, DB};
+
+/// What every resolver has access to.
+pub struct Context {
+    pub db: DB,
+}
+
+impl juniper::Context for Context {}
+
+pub type Schema = RootNode<'static, Query, EmptyMutation<Context>, EmptySubscription<Context>>;
+
+pub fn schema() -> Schema {
+    Schema::new(Query, EmptyMutation::new(), EmptySubscription::new())
+}
+
+/// The entry points of the schema.
+pub struct Query;
+
+#[graphql_object(context = Context)]
+impl Query {
+    /// A single Kanji, if it has an entry.
+    fn kanji(context: &Context, id: String) -> Option<Node> {
+        node(&context.db, &id)
+    }
+
+    /// A Kanji's ancestors and descendants, optionally only as far as `depth`
+    /// generations up and down.
+    fn family(context: &Context, id: String, depth: Option<i32>) -> Option<Family> {
+        let k = node(&context.db, &id)?.0;
+        let depth = depth.map(|d| d.max(0) as usize);
+        let graph = context.db.filtered_graph(vec![k], depth);
+
+        let mut nodes: Vec<Kanji> = graph.node_weights().copied().collect();
+        nodes.sort();
+        let mut edges: Vec<FamilyEdge> = graph
+            .raw_edges()
+            .iter()
+            .filter_map(|e| {
+                Some(FamilyEdge {
+                    parent: *graph.node_weight(e.source())?,
+                    child: *graph.node_weight(e.target())?,
+                    inherit: e.weight.to_string(),
+                })
+            })
+            .collect();
+        edges.sort_by_key(|e| (e.parent, e.child));
+
+        Some(Family { nodes, edges })
+    }
+
+    /// Every Kanji with the given 音読み, in kana or romaji.
+    fn search(context: &Context, reading: String) -> Vec<Node> {
+        let yomi = utils::to_hiragana(&utils::romaji_to_hiragana(reading.trim()));
+        context
+            .db
+            .with_reading(&yomi)
+            .into_iter()
+            .map(Node)
+            .collect()
+    }
+}
+
+/// A single Kanji with an entry in the database.
+pub struct Node(Kanji);
+
+fn node(db: &DB, id: &str) -> Option<Node> {
+    let mut chars = id.chars();
+    match (chars.next().and_then(Kanji::new), chars.next()) {
+        (Some(k), None) if db.entries.contains_key(&k) => Some(Node(k)),
+        _ => None,
+    }
+}
+
+impl Node {
+    /// Neighbours along edges in one direction, in order.
+    fn relations(&self, db: &DB, dir: Direction) -> Vec<Relation> {
+        let mut rs: Vec<Relation> = db
+            .index
+            .get(&self.0)
+            .into_iter()
+            .flat_map(|ix| db.neighbours(*ix, dir))
+            .filter_map(|(ix, inherit)| {
+                db.graph.node_weight(ix).map(|k| Relation {
+                    kanji: *k,
+                    inherit: inherit.to_string(),
+                })
+            })
+            .collect();
+        rs.sort_by_key(|r| r.kanji);
+        rs
+    }
+}
+
+#[graphql_object(name = "Kanji", context = Context)]
+impl Node {
+    /// The Kanji itself.
+    fn kanji(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// Its exam level, if it has one.
+    fn level(&self) -> Option<String> {
+        kanji::level_table().get(&self.0).map(|l| l.to_string())
+    }
+
+    /// Its 音読み, in Hiragana.
+    fn onyomi(&self, context: &Context) -> Vec<String> {
+        let e = context.db.entries.get(&self.0);
+        e.into_iter()
+            .flat_map(|e| &e.onyomi)
+            .map(|y| y.reading.to_string())
+            .collect()
+    }
+
+    /// Its representative words.
+    fn daihyou(&self, context: &Context) -> Vec<String> {
+        let e = context.db.entries.get(&self.0);
+        e.map(|e| e.daihyou.clone()).unwrap_or_default()
+    }
+
+    /// Free-form remarks.
+    fn notes(&self, context: &Context) -> Vec<String> {
+        let e = context.db.entries.get(&self.0);
+        e.map(|e| e.notes.clone()).unwrap_or_default()
+    }
+
+    /// Its parents, and how its readings relate to theirs.
+    fn parents(&self, context: &Context) -> Vec<Relation> {
+        self.relations(&context.db, Direction::Incoming)
+    }
+
+    /// Its children, and how their readings relate to its own.
+    fn children(&self, context: &Context) -> Vec<Relation> {
+        self.relations(&context.db, Direction::Outgoing)
+    }
+}
+
+/// A neighbouring Kanji, and the relationship of the edge between them.
+pub struct Relation {
+    kanji: Kanji,
+    inherit: String,
+}
+
+#[graphql_object(context = Context)]
+impl Relation {
+    /// The Kanji on the other end of the edge.
+    fn kanji(&self) -> Node {
+        Node(self.kanji)
+    }
+
+    /// How the child's readings relate to the parent's, e.g. `Voicing`.
+    fn inherit(&self) -> &str {
+        &self.inherit
+    }
+}
+
+/// A Kanji family, as lists of nodes and edges.
+pub struct Family {
+    nodes: Vec<Kanji>,
+    edges: Vec<FamilyEdge>,
+}
+
+#[graphql_object(context = Context)]
+impl Family {
+    /// Every Kanji in the family, in order.
+    fn nodes(&self) -> Vec<Node> {
+        self.nodes.iter().copied().map(Node).collect()
+    }
+
+    /// Every edge between them.
+    fn edges(&self) -> Vec<FamilyEdge> {
+        self.edges.clone()
+    }
+}
+
+/// A single parent-child edge within a `Family`.
+#[derive(Clone)]
+pub struct FamilyEdge {
+    parent: Kanji,
+    child: Kanji,
+    inherit: String,
+}
+
+#[graphql_object(context = Context)]
+impl FamilyEdge {
+    /// The parent end of the edge.
+    fn parent(&self) -> Node {
+        Node(self.parent)
+    }
+
+    /// The child end of the edge.
+    fn child(&self) -> Node {
+        Node(self.child)
+    }
+
+    /// How the child's readings relate to the parent's, e.g. `Voicing`.
+    fn inherit(&self) -> &str {
+        &self.inherit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use juniper::{graphql_value, Variables};
+    use kn_core::{Entry, Onyomi};
+
+    fn context(entries: &[(char, &str)]) -> Context {
+        let entries = entries
+            .iter()
+            .map(|(c, oya)| {
+                let kanji = Kanji::new(*c).unwrap();
+                let e = Entry {
+                    oya: oya.chars().filter_map(Kanji::new).collect(),
+                    onyomi: vec![Onyomi::from("たん")],
+                    ..Entry::new(kanji)
+                };
+                (kanji, e)
+            })
+            .collect();
+
+        Context {
+            db: DB::new(entries).unwrap(),
+        }
+    }
+
+    fn family(context: &Context, id: &str) -> juniper::Value {
+        let query = format!("{{ family(id: \"{}\") {{ nodes {{ kanji }} }} }}", id);
+        let (value, errors) =
+            juniper::execute_sync(&query, None, &schema(), &Variables::new(), context).unwrap();
+        assert!(errors.is_empty());
+        value
+    }
+
+    #[test]
+    fn family_of_cycles() {
+        // 坦 lists itself as a parent in the real data.
+        let ctx = context(&[('旦', ""), ('坦', "坦旦")]);
+        assert_eq!(
+            family(&ctx, "坦"),
+            graphql_value!({ "family": { "nodes": [{ "kanji": "坦" }, { "kanji": "旦" }] } })
+        );
+
+        let ctx = context(&[('甲', "乙"), ('乙', "甲")]);
+        assert_eq!(
+            family(&ctx, "甲"),
+            graphql_value!({ "family": { "nodes": [{ "kanji": "乙" }, { "kanji": "甲" }] } })
+        );
+    }
+}
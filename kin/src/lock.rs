@@ -0,0 +1,48 @@
+//! Keeping two `kin` processes from editing the data file at once.
+//!
+//! Commands that read the data, change it, and write it back first create a
+//! lock file next to the data file, like `data.json.lock`, and remove it when
+//! they're done. The lock is only advisory: other programs are free to ignore
+//! it. If `kin` is killed mid-command the lock stays behind, and must be
+//! removed by hand.
+
+use crate::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A held lock on a data file, released when dropped.
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Where the lock of a given data file lives.
+pub fn lock_path(data: &Path) -> PathBuf {
+    let mut name = data.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    data.with_file_name(name)
+}
+
+/// Lock the data file, failing if someone else already has.
+pub fn take(data: &Path) -> Result<Lock, Error> {
+    let path = lock_path(data);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => Error::Locked(path.clone()),
+            _ => Error::Io(e),
+        })?;
+
+    // Purely to help whoever finds a stale lock lying around.
+    writeln!(file, "{}", std::process::id()).map_err(Error::Io)?;
+
+    Ok(Lock { path })
+}
@@ -1,12 +1,36 @@
+mod backup;
+mod config;
+mod editor;
+mod git;
+mod graphql;
+mod lock;
+mod poster;
+mod prompt;
+mod repl;
+mod serve;
+mod sheet;
+mod site;
+mod usage;
+mod wanikani;
+
+use config::Config;
 use gumdrop::{Options, ParsingStyle};
 use kanji::exam_lists::*;
-use kn_core::{self as core, DotMode, Entry, Kanji, Level};
-use rustyline::history::{FileHistory, History};
-use rustyline::Editor;
-use std::collections::{HashMap, HashSet};
+use kn_core::export::GraphFormat;
+use kn_core::ids::{self, Components};
+use kn_core::jmdict::{self, Word};
+use kn_core::merge::{Conflict, Side};
+use kn_core::report::{Drift, Likeness, Lookalikes, MissingParent, ReadingMatch};
+use kn_core::unihan;
+use kn_core::{
+    self as core, phonology, progress, utils, ColourBy, Direction, DotMode, DotOptions, Entry,
+    Inherit, Kanji, Labels, Level, Match, NodeIndex, Onyomi, RankDir, Rules, DB,
+};
+use prompt::{Complete, Prompt};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Options)]
 struct Args {
@@ -15,8 +39,12 @@ struct Args {
     /// Show the current version of `kin`.
     version: bool,
     /// Path to the Kanji data file.
-    #[options(meta = "PATH", default = "/home/colin/code/rust/kanji-net/data.json")]
-    data: PathBuf,
+    #[options(meta = "PATH")]
+    data: Option<PathBuf>,
+    /// Commit any changes to the data file with git afterwards.
+    commit: bool,
+    /// Print results as JSON, where supported.
+    json: bool,
     #[options(command)]
     command: Option<Command>,
 }
@@ -25,6 +53,16 @@ struct Args {
 enum Command {
     /// Add a new entry to the database.
     New(New),
+    /// Change an existing entry.
+    Edit(Edit),
+    /// Replace a Kanji everywhere it appears.
+    Rename(Rename),
+    /// Restore the data file from its most recent backup.
+    Undo(Undo),
+    /// List parents that have no entries of their own.
+    Missing(Missing),
+    /// List Kanji with no parents and no children.
+    Orphans(Orphans),
     /// Output the content of the Kanji Graph in Dot format.
     Graph(Graph),
     /// Show database statistics.
@@ -33,20 +71,231 @@ enum Command {
     Levels(Levels),
     /// Give the next Kanji yet unentered into the DB.
     Next(Next),
+    /// List the Kanji with the largest families, the most valuable to learn first.
+    Top(Top),
+    /// List every Kanji yet unentered into the DB, in level order.
+    Remaining(Remaining),
+    /// List the Kanji entered recently.
+    Recent(Recent),
+    /// Show the shortest chain of relations between two Kanji.
+    Path(Chain),
+    /// Compare two versions of the database.
+    Diff(Diff),
+    /// Guess the 音読み of a Kanji from a proposed parent.
+    Predict(Predict),
+    /// Check the database for structural problems.
+    Lint(Lint),
+    /// Produce an order in which to learn every Kanji.
+    Plan(Plan),
+    /// Export the Kanji Graph for use in other tools.
+    Export(Export),
+    /// Print the family of a Kanji as a tree.
+    Tree(Tree),
+    /// List the families of some Kanji, with the edges between their members.
+    Families(Families),
+    /// Display entries in full.
+    Show(Show),
+    /// Compare recorded readings against KANJIDIC.
+    AuditReadings(Audit),
+    /// Show all Kanji with a given reading.
+    Reading(Reading),
+    /// Find Kanji by meaning, among their 代表, notes, and dictionary meanings.
+    Search(Search),
+    /// Find Kanji by anything: the Kanji itself, a reading, or a gloss.
+    Find(Find),
+    /// List the readings shared by the most Kanji.
+    Homophones(Homophones),
+    /// List the Kanji that look like some others.
+    Confusables(Confusables),
+    /// Attach parents to Kanji that have none.
+    Consolidate(Consolidate),
+    /// Import data exported by `kin export`.
+    Import(Import),
+    /// Review the readings of Kanji that are due.
+    Review(Review),
+    /// A multiple-choice reading quiz.
+    Quiz(Quiz),
+    /// Serve the Kanji Graph over HTTP.
+    Serve(Serve),
+    /// Pin the relationship between a Kanji and one of its parents.
+    Edge(Edge),
+    /// Add or remove ad-hoc tags, or list those in use.
+    Tag(Tag),
+    /// Run an interactive command with answers read from a file.
+    CheckCli(CheckCli),
+    /// Draw the largest families as a printable wall chart.
+    Poster(Poster),
+    /// Render every family to its own image, with an index page.
+    Atlas(Atlas),
+    /// Write a static website with a page for every Kanji.
+    Site(Site),
+    /// Summarize the local usage ledger.
+    Usage(UsageReport),
+    /// Manage the binary cache of the database.
+    Cache(Cache),
+    /// Upgrade the data file's layout, or move it between JSON and SQLite.
+    Migrate(Migrate),
+    /// Bring in study progress from WaniKani.
+    Wanikani(Wanikani),
+    /// Mark Kanji as learned.
+    Learn(Learn),
+    /// Mark Kanji as no longer learned.
+    Unlearn(Learn),
+    /// Bring in vocabulary from JMdict, for suggesting 代表 words.
+    Jmdict(Jmdict),
+    /// Link variant forms of Kanji, like 國 and 国, from Unihan.
+    Unihan(Unihan),
+    /// Bring in the structure of Kanji from CHISE's IDS data.
+    Ids(Ids),
+    /// Repair problems in the data file in bulk.
+    Fix(Fix),
+    /// Bring the entries of another data file into this one.
+    Merge(Merge),
+    /// Explore and extend the database interactively, reading it only once.
+    Repl(Repl),
+}
+
+/// Give `--kanji` to skip the prompts and take every field from flags instead.
+#[derive(Options, Default)]
+struct New {
+    /// Show this help message.
+    help: bool,
+    /// The Kanji to add.
+    #[options(meta = "KANJI")]
+    kanji: Option<String>,
+    /// Parents of the new Kanji.
+    #[options(meta = "KANJI")]
+    oya: Vec<String>,
+    /// Hidden parents of the new Kanji.
+    #[options(meta = "KANJI")]
+    kakushi_oya: Vec<String>,
+    /// A reading, in hiragana, katakana, or romaji, e.g. gyou(呉) with its stratum.
+    #[options(meta = "YOMI")]
+    onyomi: Vec<String>,
+    /// A representative word.
+    #[options(meta = "WORD")]
+    daihyou: Vec<String>,
+    /// Keep prompting for the next unentered Kanji until told to quit.
+    #[options(no_short, long = "loop")]
+    looping: bool,
+    /// Refuse entries whose parents aren't in the database yet.
+    strict: bool,
+    /// Fill in the entry in $EDITOR instead of at the prompts.
+    editor: bool,
+    /// Also prompt for free-form notes.
+    #[options(no_short)]
+    with_notes: bool,
+}
+
+/// Each undo goes one backup further back.
+#[derive(Options)]
+struct Undo {
+    /// Show this help message.
+    help: bool,
+}
+
+/// Kanji referenced as parents but never entered, most needed first.
+#[derive(Options)]
+struct Missing {
+    /// Show this help message.
+    help: bool,
+}
+
+/// Kanji that are connected to nothing, either because they're genuinely
+/// independent or because their parents were never recorded.
+#[derive(Options)]
+struct Orphans {
+    /// Show this help message.
+    help: bool,
+    /// Only show Kanji of this level (e.g. 5, 準2).
+    #[options(meta = "LEVEL", parse(try_from_str = "level_from_str"))]
+    level: Option<Level>,
 }
 
+/// Swap one Kanji for another, e.g. after entering the wrong variant.
 #[derive(Options)]
-struct New {}
+struct Rename {
+    /// Show this help message.
+    help: bool,
+    /// The Kanji to replace, then its replacement.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
 
+/// Change an existing entry, at the prompts or in $EDITOR.
 #[derive(Options)]
+struct Edit {
+    /// Show this help message.
+    help: bool,
+    /// Edit the entry in $EDITOR instead of at the prompts.
+    editor: bool,
+    /// Refuse parents that aren't in the database yet.
+    strict: bool,
+    /// The Kanji to edit.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
+
+#[derive(Options, Clone)]
 struct Graph {
     /// Show this help message.
     help: bool,
     /// Search via the given Kanji's parents, not the Kanji itself.
     parents: bool,
+    /// Choose which parent lineages to follow for Kanji with several parents.
+    choose: bool,
+    /// Classify は行 voicing (e.g. ふ→ぶ) as its own kind of edge.
+    rendaku: bool,
+    /// Only compare readings of the same stratum (呉音, 漢音, 唐音) where known.
+    #[options(no_short)]
+    strata: bool,
+    /// Draw parents that have no entries yet as dashed grey nodes.
+    ghosts: bool,
+    /// Only draw edges of these kinds (e.g. same,voicing).
+    #[options(no_short, meta = "KINDS", parse(try_from_str = "inherits_from_str"))]
+    only: Vec<Vec<Inherit>>,
+    /// Never draw edges of these kinds (e.g. differ).
+    #[options(no_short, meta = "KINDS", parse(try_from_str = "inherits_from_str"))]
+    hide: Vec<Vec<Inherit>>,
+    /// Override edge colours (e.g. differ=#ff8080,same=green).
+    #[options(no_short, meta = "COLOURS", parse(try_from_str = "palette_from_str"))]
+    palette: Vec<Vec<(Inherit, String)>>,
+    /// Only follow families this many generations up and down.
+    #[options(meta = "N")]
+    depth: Option<usize>,
+    /// Only draw Kanji of this level or easier (e.g. 3, 準2).
+    #[options(no_short, meta = "LEVEL", parse(try_from_str = "level_from_str"))]
+    max_level: Option<Level>,
+    /// Fill nodes by some property (nothing, level, wanikani, learned).
+    #[options(long = "color-by", meta = "BY", default = "nothing")]
+    colour_by: ColourBy,
+    /// What to write inside each node (kanji, full).
+    #[options(meta = "LABELS", default = "kanji")]
+    labels: Labels,
+    /// The image format (png, svg, pdf, ...), or dot for the raw DOT text.
+    #[options(meta = "FORMAT")]
+    format: Option<String>,
+    /// The Graphviz layout engine (dot, neato, fdp, sfdp, ...).
+    #[options(meta = "ENGINE", default = "dot")]
+    engine: String,
+    /// The direction to lay out generations in (TB, LR, BT, RL).
+    #[options(no_short, meta = "DIR", default = "TB")]
+    rankdir: RankDir,
+    /// Also focus on every Kanji with this tag.
+    #[options(no_short, meta = "TAG")]
+    tag: Vec<String>,
+    /// Show each Kanji's notes when hovering over it in SVG output.
+    #[options(no_short)]
+    tooltips: bool,
     /// Filepath to write the image to.
-    #[options(meta = "PATH", default = "graph.png")]
-    output: PathBuf,
+    #[options(meta = "PATH")]
+    output: Option<PathBuf>,
+    /// Keep running, and render again whenever the data file changes.
+    #[options(no_short)]
+    watch: bool,
+    /// Open the image in the default viewer once it's rendered.
+    #[options(no_short)]
+    open: bool,
     /// Kanji whose families you wish to focus on.
     #[options(free, parse(from_str = "kanji_from_str"))]
     kanji: Vec<Vec<Kanji>>,
@@ -54,7 +303,12 @@ struct Graph {
 
 /// Various statistics about the Kanji database.
 #[derive(Options)]
-struct Stats {}
+struct Stats {
+    /// Show this help message.
+    help: bool,
+    /// Count each kind of edge instead, and find the least regular families.
+    edges: bool,
+}
 
 /// Inspect the levels of given Kanji.
 #[derive(Options)]
@@ -66,283 +320,3513 @@ struct Levels {
     kanji: Vec<String>,
 }
 
+/// Without flags, this is the first gap in the level lists, easiest first.
 #[derive(Options)]
-struct Next {}
-
-#[derive(Debug)]
-enum Error {
-    Core(core::Error),
-    Readline(rustyline::error::ReadlineError),
-    Io(std::io::Error),
-    /// Some lower-level error involving time measurement.
-    Time(std::time::SystemTimeError),
-    /// A given `Kanji` already exists in the database.
-    Exists(Kanji),
-    Other(&'static str),
+struct Next {
+    /// Show this help message.
+    help: bool,
+    /// How many Kanji to give at once.
+    #[options(meta = "N")]
+    count: Option<usize>,
+    /// Only give Kanji of this level (e.g. 5, 準2).
+    #[options(meta = "LEVEL", parse(try_from_str = "level_from_str"))]
+    level: Option<Level>,
+    /// Pick from all the gaps at random, rather than in order.
+    random: bool,
 }
 
-impl From<core::Error> for Error {
-    fn from(v: core::Error) -> Self {
-        Self::Core(v)
-    }
+/// Only entries made with `kin new` record when they were added.
+#[derive(Options)]
+struct Recent {
+    /// Show this help message.
+    help: bool,
+    /// How many days back to look.
+    #[options(meta = "N", default = "7")]
+    days: u64,
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::Io(e) => write!(f, "{e}"),
-            Error::Exists(k) => write!(f, "{} already has an entry in the database.", k.get()),
-            Error::Core(e) => write!(f, "{e}"),
-            Error::Readline(e) => write!(f, "{e}"),
-            Error::Time(e) => write!(f, "{e}"),
-            Error::Other(e) => write!(f, "{e}"),
-        }
-    }
+/// Give `--parents` to see which of them are already needed by some entry.
+#[derive(Options)]
+struct Remaining {
+    /// Show this help message.
+    help: bool,
+    /// Only list Kanji of this level (e.g. 5, 準2).
+    #[options(meta = "LEVEL", parse(try_from_str = "level_from_str"))]
+    level: Option<Level>,
+    /// Mark Kanji that are already the 親 of some entry, with those entries.
+    parents: bool,
 }
 
-fn main() -> Result<(), Error> {
-    let args = Args::parse_args_or_exit(ParsingStyle::AllOptions);
-
-    match args.command {
-        _ if args.version => {
-            let version = env!("CARGO_PKG_VERSION");
-            println!("{}", version);
-        }
-        Some(Command::New(_)) => new_entry(&args.data)?,
-        Some(Command::Graph(g)) => graph_dot(&args.data, g)?,
-        Some(Command::Stats(_)) => db_stats(&args.data)?,
-        Some(Command::Levels(l)) => levels(l.kanji),
-        Some(Command::Next(_)) => next(&args.data)?,
-        None => {}
-    }
+/// Report problems in the database.
+#[derive(Options)]
+struct Lint {}
 
-    Ok(())
+/// A study plan where parents always come before their children.
+#[derive(Options)]
+struct Plan {
+    /// Show this help message.
+    help: bool,
+    /// Split the plan into daily batches of this size.
+    #[options(meta = "N")]
+    per_day: Option<usize>,
+    /// Path to a UTF-8 KANJIDIC file, to put more common Kanji first.
+    #[options(meta = "PATH")]
+    kanjidic: Option<PathBuf>,
 }
 
-fn new_entry(path: &Path) -> Result<(), Error> {
-    let mut db = kn_core::open_db(path)?;
-    let entry = kanji_prompt()?;
-    let kanji = entry.kanji;
-
-    // On collision, the entry is put into the in-memory copy of the DB, but
-    // never makes it to the on-disk version.
-    match db.entries.insert(kanji, entry) {
-        Some(_) => Err(Error::Exists(kanji))?,
-        None => kn_core::write_db(path, db)?,
-    }
-
-    Ok(())
+/// Export the database in some other format.
+#[derive(Options)]
+struct Export {
+    /// Show this help message.
+    help: bool,
+    /// The output format (node-link, json-graph, gexf, cytoscape, csv, tsv, progress, phonology).
+    #[options(meta = "FORMAT", default = "node-link")]
+    format: Format,
+    /// Filepath to write to, instead of stdout.
+    #[options(meta = "PATH")]
+    output: Option<PathBuf>,
+    #[options(command)]
+    command: Option<ExportCommand>,
 }
 
-/// Prompt the user for the fields of an `Entry` to add to the database.
-fn kanji_prompt() -> Result<Entry, Error> {
-    let mut rl = Editor::<(), FileHistory>::new().map_err(Error::Readline)?;
-    rl.load_history("history.txt").map_err(Error::Readline)?;
-
-    let oya: Vec<Kanji> = get_line(&mut rl, "親: ")?
-        .split_whitespace()
-        .flat_map(|s| s.chars())
-        .filter_map(Kanji::new)
-        .collect();
-
-    let kakushi_oya: Vec<Kanji> = get_line(&mut rl, "隠し親: ")?
-        .split_whitespace()
-        .flat_map(|s| s.chars())
-        .filter_map(Kanji::new)
-        .collect();
+#[derive(Options)]
+enum ExportCommand {
+    /// Write a graph and an entry list for each level, as study handouts.
+    Levels(ExportLevels),
+}
 
-    let kanji = get_legal_kanji(&mut rl, "漢字: ")?;
+/// Each level's graph also draws, in grey, the parents its Kanji have in
+/// easier levels.
+#[derive(Options)]
+struct ExportLevels {
+    /// Show this help message.
+    help: bool,
+    /// The directory to write to.
+    #[options(meta = "DIR", required)]
+    out: PathBuf,
+    /// The image format (png, svg, pdf, ...), or dot for the DOT text alone.
+    #[options(meta = "FORMAT")]
+    format: Option<String>,
+}
 
-    let onyomi = get_line(&mut rl, "音読み: ")?
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
+/// Bring outside data into the database.
+#[derive(Options)]
+struct Import {
+    /// Show this help message.
+    help: bool,
+    /// The input format (csv, tsv, progress).
+    #[options(meta = "FORMAT", default = "progress")]
+    format: Format,
+    /// The file to import.
+    #[options(free)]
+    file: Option<PathBuf>,
+}
 
-    let daihyou: Vec<String> = get_line(&mut rl, "代表: ")?
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
+/// The formats that `kin export` and `kin import` understand.
+enum Format {
+    /// The graph itself, in one of several formats.
+    Graph(GraphFormat),
+    /// Entries as spreadsheet rows, separated by commas.
+    Csv,
+    /// Entries as spreadsheet rows, separated by tabs.
+    Tsv,
+    /// The learned set, review schedule, and quiz statistics.
+    Progress,
+    /// CSV of every reading split into onset, glide, vowel, and coda.
+    Phonology,
+}
 
-    let entry = Entry {
-        kanji,
-        oya,
-        kakushi_oya,
-        onyomi,
-        daihyou,
-    };
+impl std::str::FromStr for Format {
+    type Err = String;
 
-    rl.save_history("history.txt").map_err(Error::Readline)?;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "progress" => Ok(Format::Progress),
+            "phonology" => Ok(Format::Phonology),
+            _ => s.parse().map(Format::Graph),
+        }
+    }
+}
 
-    Ok(entry)
+/// Kanji with no children are never listed.
+#[derive(Options)]
+struct Top {
+    /// Show this help message.
+    help: bool,
+    /// What to rank by (children, descendants).
+    #[options(meta = "BY", default = "descendants")]
+    by: TopBy,
+    /// How many Kanji to list.
+    #[options(meta = "N", default = "20")]
+    count: usize,
 }
 
-fn get_line<H>(rl: &mut Editor<(), H>, label: &str) -> Result<String, Error>
-where
-    H: History,
-{
-    match rl.readline(label) {
-        Ok(line) => {
-            rl.add_history_entry(&line).map_err(Error::Readline)?;
-            Ok(line)
-        }
-        Err(_) => Err(Error::Other("CLI input failed."))?,
-    }
+/// The measures that `kin top` ranks by.
+enum TopBy {
+    /// Immediate children only.
+    Children,
+    /// Children, grandchildren, and so on.
+    Descendants,
 }
 
-/// Loop on the input of legal Kanji.
-fn get_legal_kanji<H>(rl: &mut Editor<(), H>, label: &str) -> Result<Kanji, Error>
-where
-    H: History,
-{
-    let line = get_line(rl, label)?;
-    let mut chars = line.chars();
+impl std::str::FromStr for TopBy {
+    type Err = String;
 
-    match chars.next().and_then(Kanji::new) {
-        Some(k) => Ok(k),
-        _ => {
-            println!("Invalid input! Try again.");
-            get_legal_kanji(rl, label)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "children" => Ok(TopBy::Children),
+            "descendants" => Ok(TopBy::Descendants),
+            _ => Err(format!("Not a ranking: {}", s)),
         }
     }
 }
 
-// FIXME This should use `NESet` from the get-go.
-fn graph_dot(path: &Path, g: Graph) -> Result<(), Error> {
-    let ks: Vec<Kanji> = g.kanji.into_iter().flatten().collect();
-    let db = kn_core::open_db(path)?;
-
-    // Note: This demonstrates how to do shell piping from within Rust.
-    let mut child = std::process::Command::new("dot")
-        .arg("-Tpng")
-        .arg("-o")
-        .arg(g.output)
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .spawn()
-        .map_err(Error::Io)?;
-
-    let dot = if ks.is_empty() {
-        db.dot()
-    } else {
-        // The kanji we should specially highlight in the final graph.
-        let highlight_by: HashSet<Kanji> = ks.iter().copied().collect();
-
-        // The kanji by which we filter the graph down.
-        let hone_by = if g.parents {
-            ks.iter()
-                .filter_map(|k| db.entries.get(k).map(|e| e.oya.iter()))
-                .flatten()
-                .copied()
-                .collect()
-        } else {
-            ks
-        };
+/// A terminal-friendly view of a Kanji's ancestors and descendants.
+#[derive(Options)]
+struct Tree {
+    /// Show this help message.
+    help: bool,
+    /// The Kanji whose family you wish to see.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
 
-        db.dot_custom(DotMode::Groups, highlight_by, &db.filtered_graph(hone_by))
-    };
+/// Every ancestor and descendant of the given Kanji. With `--json`, the
+/// families are written as by `kin export --format json-graph`.
+#[derive(Options)]
+struct Families {
+    /// Show this help message.
+    help: bool,
+    /// Only follow families this many generations up and down.
+    #[options(meta = "N")]
+    depth: Option<usize>,
+    /// The Kanji whose families you wish to list.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
 
-    // Ensures that the handle to `stdin` drops and closes, avoiding a deadlock.
-    // The `unwrap` here is on purpose and advertised in the definition of `stdin` itself.
-    {
-        let mut stdin: std::process::ChildStdin = child.stdin.take().unwrap();
-        writeln!(stdin, "{}", dot).map_err(Error::Io)?; // FIXME Write the bytes directly?
-    }
+/// Everything about some Kanji: their readings, relatives, and so on.
+#[derive(Options)]
+struct Show {
+    /// Show this help message.
+    help: bool,
+    /// Print the entries as JSON instead.
+    json: bool,
+    /// Path to a UTF-8 KANJIDIC file, to also show 訓読み.
+    #[options(meta = "PATH")]
+    kanjidic: Option<PathBuf>,
+    /// The Kanji to show.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
 
-    child.wait().map_err(Error::Io)?;
-    Ok(())
+/// Compare 音読み against a dictionary.
+#[derive(Options)]
+struct Audit {
+    /// Show this help message.
+    help: bool,
+    /// Path to a UTF-8 KANJIDIC file.
+    #[options(meta = "PATH", required)]
+    kanjidic: PathBuf,
+    /// Interactively accept or reject each difference.
+    merge: bool,
 }
 
-fn kanji_from_str(s: &str) -> Vec<Kanji> {
-    s.chars().filter_map(Kanji::new).collect()
+/// Matching ignores case, and the matching part of each text is highlighted.
+#[derive(Options)]
+struct Search {
+    /// Show this help message.
+    help: bool,
+    /// Text to look for, e.g. water.
+    #[options(meta = "TEXT", required)]
+    meaning: String,
+    /// Path to a UTF-8 KANJIDIC file, to also search its English meanings.
+    #[options(meta = "PATH")]
+    kanjidic: Option<PathBuf>,
 }
 
-fn db_stats(path: &Path) -> Result<(), Error> {
-    let now = SystemTime::now();
-    let db = kn_core::open_db(path)?;
-    let micros = now.elapsed().map_err(Error::Time)?.as_micros();
-    let levels = db.levels();
+/// Hits are ranked: the Kanji itself, then an exact reading, a reading prefix,
+/// a gloss containing the query, and a gloss with its letters in order.
+#[derive(Options)]
+struct Find {
+    /// Show this help message.
+    help: bool,
+    /// Path to a UTF-8 KANJIDIC file, to also search its English meanings.
+    #[options(meta = "PATH")]
+    kanjidic: Option<PathBuf>,
+    /// How many hits to show.
+    #[options(meta = "N", default = "20")]
+    count: usize,
+    /// What to look for, in Kanji, kana, romaji, or English.
+    #[options(free)]
+    query: Vec<String>,
+}
 
-    let pairs = vec![
-        (Level::Ten, LEVEL_10.chars().count()),
-        (Level::Nine, LEVEL_09.chars().count()),
-        (Level::Eight, LEVEL_08.chars().count()),
-        (Level::Seven, LEVEL_07.chars().count()),
-        (Level::Six, LEVEL_06.chars().count()),
-        (Level::Five, LEVEL_05.chars().count()),
-        (Level::Four, LEVEL_04.chars().count()),
-        (Level::Three, LEVEL_03.chars().count()),
-        (Level::PreTwo, LEVEL_02_PRE.chars().count()),
-        (Level::Two, LEVEL_02.chars().count()),
-        (Level::PreOne, LEVEL_01_PRE.chars().count()),
-        (Level::One, LEVEL_01.chars().count()),
-    ];
+/// Look up Kanji by their 音読み.
+#[derive(Options)]
+struct Reading {
+    /// Show this help message.
+    help: bool,
+    /// Readings to look up, in Hiragana or romaji.
+    #[options(free)]
+    readings: Vec<String>,
+}
 
-    println!("DB loaded in {} microseconds.", micros);
-    println!("DB contains {} entries.", db.entries.len());
-    println!("Kanji Levels completed:");
+/// Lookalikes are followed through each other, so the whole cluster is listed.
+#[derive(Options)]
+struct Confusables {
+    /// Show this help message.
+    help: bool,
+    /// The Kanji to find lookalikes of.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
 
-    let jouyou: usize = pairs.iter().take(10).map(|(_, c)| c).sum();
-    let level_totals: HashMap<Level, usize> = pairs
-        .iter()
-        .map(|(level, _)| {
-            let found = levels.iter().filter(|(_, l)| *l == level).count();
-            (*level, found)
-        })
-        .collect();
+/// Kanji linked to one another through any of their parents are grouped
+/// together, under those the rest descend from.
+#[derive(Options)]
+struct Homophones {
+    /// Show this help message.
+    help: bool,
+    /// How many readings to list.
+    #[options(meta = "N", default = "10")]
+    count: usize,
+}
 
-    pairs.iter().for_each(|(level, len)| {
-        let found = level_totals.get(level).unwrap();
-        println!("  - {:?}: {}/{}", level, found, len);
-    });
+/// Walk through parentless Kanji, suggesting families by reading.
+#[derive(Options)]
+struct Consolidate {}
 
-    let entered_jouyou: usize = level_totals
-        .iter()
-        .filter(|(&l, _)| l < Level::PreOne)
-        .map(|(_, s)| s)
-        .sum();
-    let percent = 100.0 * (entered_jouyou as f64) / (jouyou as f64);
-    println!(
-        "常用 Completion: {}/{} ({:.2}%)",
-        entered_jouyou, jouyou, percent
-    );
+/// A spaced-repetition review session.
+#[derive(Options)]
+struct Review {
+    /// Show this help message.
+    help: bool,
+    /// End the session after this many minutes.
+    #[options(meta = "N")]
+    minutes: Option<u64>,
+    /// Add this many new Kanji from the study plan before starting.
+    #[options(meta = "N")]
+    new: usize,
+}
 
-    Ok(())
+/// Pick the right reading from a list, no typing required.
+#[derive(Options)]
+struct Quiz {
+    /// Show this help message.
+    help: bool,
+    /// The number of readings to choose from.
+    #[options(meta = "N", default = "4")]
+    choices: usize,
+    /// The number of questions to ask.
+    #[options(meta = "N", default = "10")]
+    count: usize,
+    /// Also write the results as a page of the `kin site` viewer.
+    #[options(meta = "PATH")]
+    export: Option<PathBuf>,
 }
 
-fn levels(ks: Vec<String>) {
-    let table = kanji::level_table();
+/// Browse the graph from a web browser.
+#[derive(Options)]
+struct Serve {
+    /// Show this help message.
+    help: bool,
+    /// The port to listen on.
+    #[options(meta = "PORT", default = "8080")]
+    port: u16,
+    /// Also serve the interactive viewer, not just the JSON and GraphQL APIs.
+    ui: bool,
+}
 
-    ks.iter()
-        .flat_map(|s| s.chars())
-        .filter_map(Kanji::new)
-        .for_each(|k| {
-            table
-                .get(&k)
-                .into_iter()
-                .for_each(|l| println!("{}: {:?}", k, l))
-        })
+/// Override the automatic classification of an edge.
+#[derive(Options)]
+struct Edge {
+    /// Show this help message.
+    help: bool,
+    /// The child, its parent, and the relationship (e.g. Voicing), or `auto` to
+    /// go back to automatic classification.
+    #[options(free)]
+    args: Vec<String>,
 }
 
-fn next(path: &Path) -> Result<(), core::Error> {
-    let db = kn_core::open_db(path)?;
+/// Group Kanji in ways the levels don't, e.g. names or chemistry. With no
+/// subcommand, list every tag in use.
+#[derive(Options)]
+struct Tag {
+    /// Show this help message.
+    help: bool,
+    #[options(command)]
+    command: Option<TagCommand>,
+}
+
+#[derive(Options)]
+enum TagCommand {
+    /// Give a Kanji a tag.
+    Add(TagArgs),
+    /// Take a tag away from a Kanji.
+    Remove(TagArgs),
+}
+
+#[derive(Options)]
+struct TagArgs {
+    /// Show this help message.
+    help: bool,
+    /// The Kanji, then the tag.
+    #[options(free)]
+    args: Vec<String>,
+}
+
+/// Type `help` at the prompt for the commands available, and `quit` to leave.
+#[derive(Options)]
+struct Repl {}
+
+/// Drive an interactive flow from a file of answers, one per line, for
+/// automated testing and reproducible bug reports.
+#[derive(Options)]
+struct CheckCli {
+    /// Show this help message.
+    help: bool,
+    /// File of answers to feed to the prompts.
+    #[options(meta = "PATH", required)]
+    answers: PathBuf,
+    /// For the merge flow, settle every conflict in favour of one side.
+    #[options(meta = "SIDE", parse(try_from_str = "side_from_str"))]
+    prefer: Option<Side>,
+    /// The flow to run (new, new-notes, new-loop, edit KANJI, consolidate, merge FILE).
+    #[options(free)]
+    flow: Vec<String>,
+}
+
+/// What has been consulted most, according to the usage ledger.
+#[derive(Options)]
+struct UsageReport {
+    /// Show this help message.
+    help: bool,
+    /// How many of each to show.
+    #[options(meta = "N", default = "10")]
+    top: usize,
+}
+
+/// With no direction, rewrite the data file at the newest layout version.
+/// Otherwise, copy the whole database into the other kind of storage, leaving
+/// the original as it is.
+#[derive(Options)]
+struct Migrate {
+    /// Show this help message.
+    help: bool,
+    /// Either json-to-sqlite or sqlite-to-json, if given.
+    #[options(free)]
+    direction: Option<String>,
+    /// Filepath to write to, instead of beside the data file.
+    #[options(meta = "PATH")]
+    output: Option<PathBuf>,
+}
+
+/// What's learned is kept in the progress file, and shows in `kin stats` and
+/// `kin graph --color-by learned`.
+#[derive(Options)]
+struct Learn {
+    /// Show this help message.
+    help: bool,
+    /// The Kanji to mark.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
+
+/// WaniKani's SRS stages are kept in the progress file, for use by
+/// `kin graph --color-by wanikani`.
+#[derive(Options)]
+struct Wanikani {
+    /// Show this help message.
+    help: bool,
+    #[options(command)]
+    command: Option<WanikaniCommand>,
+}
+
+#[derive(Options)]
+enum WanikaniCommand {
+    /// Fetch the SRS stage of every Kanji, replacing any from before.
+    Sync(WanikaniSync),
+}
+
+#[derive(Options)]
+struct WanikaniSync {
+    /// Show this help message.
+    help: bool,
+    /// A WaniKani API token with read access.
+    #[options(meta = "TOKEN", required)]
+    token: String,
+}
+
+/// Once imported, common words using a new Kanji's 音読み are offered at the 代表
+/// prompt of `kin new`.
+#[derive(Options)]
+struct Jmdict {
+    /// Show this help message.
+    help: bool,
+    #[options(command)]
+    command: Option<JmdictCommand>,
+}
+
+#[derive(Options)]
+enum JmdictCommand {
+    /// Read the common words out of a JMdict XML file, replacing any from before.
+    Import(JmdictImport),
+}
+
+#[derive(Options)]
+struct JmdictImport {
+    /// Show this help message.
+    help: bool,
+    /// Path to a JMdict XML file, e.g. JMdict_e.
+    #[options(free)]
+    file: Option<PathBuf>,
+}
+
+/// Only entries already in the database gain variants, though the variants
+/// themselves needn't have entries of their own.
+#[derive(Options)]
+struct Unihan {
+    /// Show this help message.
+    help: bool,
+    #[options(command)]
+    command: Option<UnihanCommand>,
+}
+
+#[derive(Options)]
+enum UnihanCommand {
+    /// Read variant links out of a Unihan_Variants.txt file.
+    Import(UnihanImport),
+}
+
+#[derive(Options)]
+struct UnihanImport {
+    /// Show this help message.
+    help: bool,
+    /// Path to a Unihan_Variants.txt file.
+    #[options(free)]
+    file: Option<PathBuf>,
+}
+
+/// Entries only in the other file are added. Where both files have an entry
+/// for a Kanji but disagree on its readings or parents, you choose which to
+/// keep, or give `--prefer` to choose the same way every time.
+#[derive(Options)]
+struct Merge {
+    /// Show this help message.
+    help: bool,
+    /// Settle every conflict in favour of one side (ours, theirs).
+    #[options(meta = "SIDE", parse(try_from_str = "side_from_str"))]
+    prefer: Option<Side>,
+    /// The data file to merge in.
+    #[options(free)]
+    file: Option<PathBuf>,
+}
+
+/// Readings that still aren't Hiragana afterwards are listed, to be fixed by
+/// hand with `kin edit`.
+#[derive(Options)]
+struct Fix {
+    /// Show this help message.
+    help: bool,
+    /// Bring every 音読み into Hiragana, from romaji, Katakana, and so on.
+    #[options(no_short)]
+    normalize_kana: bool,
+}
+
+/// Once imported, `kin new` offers a Kanji's components as 親, and `kin lint`
+/// reports parents that aren't among them.
+#[derive(Options)]
+struct Ids {
+    /// Show this help message.
+    help: bool,
+    #[options(command)]
+    command: Option<IdsCommand>,
+}
+
+#[derive(Options)]
+enum IdsCommand {
+    /// Read the decompositions out of an IDS file, replacing any from before.
+    Import(IdsImport),
+}
+
+#[derive(Options)]
+struct IdsImport {
+    /// Show this help message.
+    help: bool,
+    /// Path to an IDS file, e.g. IDS-UCS-Basic.txt.
+    #[options(free)]
+    file: Option<PathBuf>,
+}
+
+/// The cache is kept fresh automatically; this is only an escape hatch.
+#[derive(Options)]
+struct Cache {
+    /// Show this help message.
+    help: bool,
+    #[options(command)]
+    command: Option<CacheCommand>,
+}
+
+#[derive(Options)]
+enum CacheCommand {
+    /// Throw away the cache and build it anew.
+    Rebuild(CacheRebuild),
+}
+
+#[derive(Options)]
+struct CacheRebuild {}
+
+/// A large-format SVG of the biggest families, for printing.
+#[derive(Options)]
+struct Poster {
+    /// Show this help message.
+    help: bool,
+    /// Only consider Kanji of these levels (e.g. 10..5, 準2..2).
+    #[options(meta = "FROM..TO")]
+    level_range: Option<String>,
+    /// How many families to draw.
+    #[options(meta = "N", default = "30")]
+    families: usize,
+    /// Filepath to write to. A `.pdf` is converted with `rsvg-convert`.
+    #[options(meta = "PATH", default = "poster.svg")]
+    output: PathBuf,
+}
+
+/// One image per connected family, plus an `index.html` linking them.
+#[derive(Options)]
+struct Atlas {
+    /// Show this help message.
+    help: bool,
+    /// The image format (png, svg, pdf, ...), or dot for the raw DOT text.
+    #[options(meta = "FORMAT")]
+    format: Option<String>,
+    /// Skip families with fewer Kanji than this.
+    #[options(meta = "N", default = "2")]
+    min: usize,
+    /// The directory to write to.
+    #[options(meta = "PATH", default = "atlas")]
+    output: PathBuf,
+}
+
+/// A browsable website of the whole database, needing no server.
+#[derive(Options)]
+struct Site {
+    /// Show this help message.
+    help: bool,
+    /// How many generations up and down to show in each family graph.
+    #[options(meta = "N", default = "2")]
+    depth: usize,
+    /// The directory to write to.
+    #[options(meta = "PATH", default = "site")]
+    out: PathBuf,
+}
+
+/// Explain how two Kanji are connected.
+#[derive(Options)]
+struct Chain {
+    /// Show this help message.
+    help: bool,
+    /// The two Kanji to connect.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
+
+/// Give two data files, or `--git` with a revision to compare the data file as
+/// it was then against how it is now.
+#[derive(Options)]
+struct Diff {
+    /// Show this help message.
+    help: bool,
+    /// A git revision of the data file to compare against, e.g. HEAD~5.
+    #[options(meta = "REV")]
+    git: Option<String>,
+    /// The old and new data files, or with --git, just the one.
+    #[options(free)]
+    files: Vec<PathBuf>,
+}
+
+/// If the Kanji already has an entry, its real readings are marked among the
+/// guesses, as a check on the inheritance model.
+#[derive(Options)]
+struct Predict {
+    /// Show this help message.
+    help: bool,
+    /// Classify は行 voicing (e.g. ふ→ぶ) as its own kind of edge.
+    rendaku: bool,
+    /// The Kanji, then its proposed parent.
+    #[options(free, parse(from_str = "kanji_from_str"))]
+    kanji: Vec<Vec<Kanji>>,
+}
+
+#[derive(Debug)]
+enum Error {
+    Core(core::Error),
+    Readline(rustyline::error::ReadlineError),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// A spreadsheet could not be read or written.
+    Csv(csv::Error),
+    /// A request to some web service failed.
+    Http(Box<ureq::Error>),
+    /// The config file could not be understood.
+    Toml(toml::de::Error),
+    /// Some lower-level error involving time measurement.
+    Time(std::time::SystemTimeError),
+    /// The data file could not be watched for changes.
+    Watch(notify::Error),
+    /// A given `Kanji` already exists in the database.
+    Exists(Kanji),
+    /// Some given text was expected to be a single Kanji.
+    NotKanji(String),
+    /// Adding a parent to a `Kanji` would form a cycle.
+    Cycle(Kanji, Kanji),
+    /// The second `Kanji` is not a parent of the first.
+    NotParent(Kanji, Kanji),
+    /// These parents of the `Kanji` have no entries of their own.
+    UnknownParents(Kanji, Vec<Kanji>),
+    /// A reading of the `Kanji` isn't written in Hiragana alone.
+    NotHiragana(Kanji, String),
+    /// Another `kin` is editing the data file, as shown by this lock file.
+    Locked(PathBuf),
+    /// Some user input could not be understood.
+    Parse(String),
+    Other(&'static str),
+}
+
+impl From<core::Error> for Error {
+    fn from(v: core::Error) -> Self {
+        Self::Core(v)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Json(e) => write!(f, "{e}"),
+            Error::Csv(e) => write!(f, "{e}"),
+            Error::Http(e) => write!(f, "{e}"),
+            Error::Toml(e) => write!(f, "{e}"),
+            Error::Exists(k) => write!(f, "{} already has an entry in the database.", k.get()),
+            Error::NotKanji(s) => write!(f, "{} is not a Kanji.", s),
+            Error::Cycle(k, o) if k == o => write!(f, "{} cannot be its own parent.", k),
+            Error::Cycle(k, o) => write!(f, "{} and {} would be each other's parents.", k, o),
+            Error::NotParent(k, o) => write!(f, "{} is not a parent of {}.", o, k),
+            Error::UnknownParents(k, os) => {
+                let os: String = os.iter().map(|o| o.get()).collect();
+                write!(f, "{}'s parents {} aren't in the database yet.", k, os)
+            }
+            Error::NotHiragana(k, y) => write!(f, "{}'s reading {} isn't all Hiragana.", k, y),
+            Error::Locked(p) => write!(
+                f,
+                "The data file is being edited elsewhere. If not, remove {}.",
+                p.display()
+            ),
+            Error::Core(e) => write!(f, "{e}"),
+            Error::Readline(e) => write!(f, "{e}"),
+            Error::Time(e) => write!(f, "{e}"),
+            Error::Watch(e) => write!(f, "{e}"),
+            Error::Parse(e) => write!(f, "{e}"),
+            Error::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let args = Args::parse_args_or_exit(ParsingStyle::AllOptions);
+    let config = Config::load()?;
+    let data = args.data.unwrap_or_else(|| config.data.clone());
+
+    // Taken now, but only recorded once the command has gone through.
+    let used = match (config.usage, &args.command) {
+        (true, Some(c)) => Some((c.command_name().unwrap_or_default(), consulted(c))),
+        _ => None,
+    };
+
+    // Held until the end, so that nothing slips in before the commit.
+    let writing = args.command.as_ref().is_some_and(writes);
+    let _lock = match writing {
+        true => Some(lock::take(&data)?),
+        false => None,
+    };
+
+    // Only worth reading if we'll have something to compare against later.
+    let commit = args.commit || config.commit;
+    let before = match commit {
+        true => git::snapshot(&data),
+        false => HashMap::new(),
+    };
+
+    match args.command {
+        _ if args.version => {
+            let version = env!("CARGO_PKG_VERSION");
+            println!("{}", version);
+        }
+        Some(Command::New(n)) => new_entry(&data, &config, n, &mut Prompt::tty()?)?,
+        Some(Command::Edit(e)) => edit(&data, &config, e, &mut Prompt::tty()?)?,
+        Some(Command::Rename(r)) => rename(&data, r)?,
+        Some(Command::Undo(_)) => undo(&data)?,
+        Some(Command::Missing(_)) => missing(&data, args.json)?,
+        Some(Command::Orphans(o)) => orphans(&data, o, args.json)?,
+        Some(Command::Graph(g)) => graph_dot(&data, g, &config)?,
+        Some(Command::Stats(s)) if s.edges => edge_stats(&data, args.json)?,
+        Some(Command::Stats(_)) => db_stats(&data, args.json)?,
+        Some(Command::Levels(l)) => levels(l.kanji, args.json)?,
+        Some(Command::Next(n)) => next(&data, n, args.json)?,
+        Some(Command::Remaining(r)) => remaining(&data, r, args.json)?,
+        Some(Command::Recent(r)) => recent(&data, r, args.json)?,
+        Some(Command::Top(t)) => top(&data, t, args.json)?,
+        Some(Command::Predict(p)) => predict(&data, p, args.json)?,
+        Some(Command::Diff(d)) => diff(&data, d, args.json)?,
+        Some(Command::Path(c)) => path(&data, c, &config)?,
+        Some(Command::Lint(_)) => lint(&data, args.json)?,
+        Some(Command::Plan(p)) => plan(&data, p)?,
+        Some(Command::Export(e)) => export(&data, e, &config)?,
+        Some(Command::Tree(t)) => tree(&data, t)?,
+        Some(Command::Families(f)) => families(&data, f, args.json)?,
+        Some(Command::Show(s)) => show(&data, s, args.json)?,
+        Some(Command::AuditReadings(a)) => audit_readings(&data, a)?,
+        Some(Command::Reading(r)) => reading(&data, r, args.json)?,
+        Some(Command::Search(s)) => search(&data, s, args.json)?,
+        Some(Command::Find(f)) => find(&data, f, args.json)?,
+        Some(Command::Homophones(h)) => homophones(&data, h, args.json)?,
+        Some(Command::Confusables(c)) => confusables(&data, c, args.json)?,
+        Some(Command::Consolidate(_)) => consolidate(&data, &mut Prompt::tty()?)?,
+        Some(Command::CheckCli(c)) => check_cli(&data, &config, c)?,
+        Some(Command::Poster(p)) => poster(&data, p)?,
+        Some(Command::Atlas(a)) => atlas(&data, a, &config)?,
+        Some(Command::Site(s)) => site::site(&kn_core::open_db(&data)?, &s.out, s.depth)?,
+        Some(Command::Usage(u)) => usage_report(&data, u)?,
+        Some(Command::Cache(c)) => cache(&data, c, &config)?,
+        Some(Command::Migrate(m)) => migrate(&data, m)?,
+        Some(Command::Wanikani(w)) => wanikani(&data, w)?,
+        Some(Command::Learn(l)) => learn(&data, l, true)?,
+        Some(Command::Unlearn(l)) => learn(&data, l, false)?,
+        Some(Command::Jmdict(j)) => jmdict(&data, j)?,
+        Some(Command::Unihan(u)) => unihan(&data, u)?,
+        Some(Command::Ids(i)) => ids(&data, i)?,
+        Some(Command::Fix(f)) => fix(&data, f)?,
+        Some(Command::Merge(m)) => merge(&data, m, &mut Prompt::tty()?)?,
+        Some(Command::Import(i)) => import(&data, i)?,
+        Some(Command::Review(r)) => review(&data, r)?,
+        Some(Command::Quiz(q)) => quiz(&data, q)?,
+        Some(Command::Edge(e)) => edge(&data, e)?,
+        Some(Command::Tag(t)) => tag(&data, t)?,
+        Some(Command::Serve(s)) => serve::serve(kn_core::open_db(&data)?, s.port, s.ui)?,
+        Some(Command::Repl(_)) => repl::repl(&data, &config)?,
+        None => {}
+    }
+
+    if let Some(msg) = commit
+        .then(|| git::message(&before, &git::snapshot(&data)))
+        .flatten()
+    {
+        git::commit(&data, &msg)?;
+    }
+
+    if let Some((name, ks)) = used {
+        let upath = usage::usage_path(&data);
+        let mut ledger = usage::read_usage(&upath)?;
+        ledger.record(name, &ks);
+        usage::write_usage(&upath, &ledger)?;
+    }
+
+    // Only a write can have added a backup.
+    if writing {
+        backup::prune(&data, &config.backups, now()?)?;
+    }
+
+    Ok(())
+}
+
+/// Does a command read, change, and write back the data file?
+fn writes(c: &Command) -> bool {
+    matches!(
+        c,
+        Command::New(_)
+            | Command::Edit(_)
+            | Command::Rename(_)
+            | Command::Undo(_)
+            | Command::AuditReadings(_)
+            | Command::Consolidate(_)
+            | Command::CheckCli(_)
+            | Command::Edge(_)
+            | Command::Import(_)
+            | Command::Repl(_)
+            | Command::Fix(_)
+            | Command::Merge(_)
+    ) || matches!(c, Command::Tag(t) if t.command.is_some())
+        || matches!(c, Command::Migrate(m) if m.direction.is_none())
+        || matches!(c, Command::Unihan(u) if u.command.is_some())
+}
+
+/// The Kanji that a command asks about, for the usage ledger.
+fn consulted(c: &Command) -> Vec<Kanji> {
+    match c {
+        Command::Graph(g) => g.kanji.concat(),
+        Command::Path(c) => c.kanji.concat(),
+        Command::Tree(t) => t.kanji.concat(),
+        Command::Families(f) => f.kanji.concat(),
+        Command::Show(s) => s.kanji.concat(),
+        Command::Confusables(c) => c.kanji.concat(),
+        Command::Levels(l) => l.kanji.iter().flat_map(|s| kanji_from_str(s)).collect(),
+        Command::Edge(e) => e
+            .args
+            .iter()
+            .take(2)
+            .flat_map(|s| kanji_from_str(s))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn new_entry(path: &Path, config: &Config, n: New, rl: &mut Prompt) -> Result<(), Error> {
+    let mut db = kn_core::open_db_with(path, config.rules)?;
+    add_entry(path, &mut db, config, n, rl)
+}
+
+/// Add a new entry to an already-open database, saving it and refreshing the
+/// graph to match.
+fn add_entry(
+    path: &Path,
+    db: &mut DB,
+    config: &Config,
+    n: New,
+    rl: &mut Prompt,
+) -> Result<(), Error> {
+    if n.looping {
+        return new_loop(path, db, config, n.strict, n.with_notes, rl);
+    }
+
+    let strict = n.strict;
+    let words = jmdict::read_words(&jmdict::jmdict_path(path))?;
+    let table = ids::read_components(&ids::ids_path(path))?;
+    rl.complete_from(db);
+    let entry = match (n.editor, n.kanji.is_some()) {
+        (true, has_kanji) => {
+            let template = match has_kanji {
+                true => flags_entry(n)?,
+                false => Entry::new(next_kanji(db).ok_or(Error::Other("Please give --kanji."))?),
+            };
+            let validate = |e: &Entry| check_new(db, e, strict);
+
+            match editor::edit(rl, &template, validate)? {
+                Some(e) => e,
+                None => return Ok(()),
+            }
+        }
+        (false, true) => flags_entry(n)?,
+        (false, false) => {
+            match kanji_prompt(
+                rl,
+                &config.history,
+                None,
+                None,
+                n.with_notes,
+                &words,
+                &table,
+            )? {
+                Some(e) => e,
+                None => return Ok(()),
+            }
+        }
+    };
+    let kanji = entry.kanji;
+    check_new(db, &entry, strict)?;
+
+    let entry = Entry {
+        added: Some(now()?),
+        ..entry
+    };
+    db.entries.insert(kanji, entry);
+    save_entry(path, db, kanji)?;
+    rebuild(db, config.rules)?;
+
+    Ok(())
+}
+
+/// Bring the graph up to date with the entries. Should that fail, the database
+/// is left as it was.
+fn rebuild(db: &mut DB, rules: Rules) -> Result<(), Error> {
+    *db = DB::new_with(db.entries.clone(), rules)?;
+    Ok(())
+}
+
+/// Enter one Kanji after another, in level order, saving after each.
+fn new_loop(
+    path: &Path,
+    db: &mut DB,
+    config: &Config,
+    strict: bool,
+    with_notes: bool,
+    rl: &mut Prompt,
+) -> Result<(), Error> {
+    let words = jmdict::read_words(&jmdict::jmdict_path(path))?;
+    let table = ids::read_components(&ids::ids_path(path))?;
+    rl.complete_from(db);
+
+    while let Some(k) = next_kanji(db) {
+        println!("Next: {} (quit to stop)", k);
+
+        let entry = match kanji_prompt(
+            rl,
+            &config.history,
+            Some(k),
+            None,
+            with_notes,
+            &words,
+            &table,
+        )? {
+            Some(e) => e,
+            None => break,
+        };
+
+        // Give the same Kanji another go.
+        if let Err(e) = check_new(db, &entry, strict) {
+            println!("{}", e);
+            continue;
+        }
+
+        let entry = Entry {
+            added: Some(now()?),
+            ..entry
+        };
+        db.entries.insert(k, entry);
+        save_entry(path, db, k)?;
+        rebuild(db, config.rules)?;
+        rl.complete_from(db);
+    }
+
+    Ok(())
+}
+
+fn edit(path: &Path, config: &Config, e: Edit, rl: &mut Prompt) -> Result<(), Error> {
+    let k = match e.kanji.concat()[..] {
+        [k] => k,
+        _ => Err(Error::Other("Please give exactly one Kanji."))?,
+    };
+    let mut db = kn_core::open_db(path)?;
+    let old = db
+        .entries
+        .get(&k)
+        .cloned()
+        .ok_or(Error::Other("No such Kanji in the database."))?;
+    rl.complete_from(&db);
+
+    let validate = |new: &Entry| match new.kanji == k {
+        true => check_entry(&db, new, e.strict),
+        false => Err(Error::Other(
+            "The Kanji itself can't be changed by editing.",
+        )),
+    };
+
+    let new = if e.editor {
+        editor::edit(rl, &old, validate)?
+    } else {
+        match kanji_prompt(
+            rl,
+            &config.history,
+            Some(k),
+            Some(&old),
+            false,
+            &[],
+            &Components::new(),
+        )? {
+            Some(new) => validate(&new).map(|_| Some(new))?,
+            None => None,
+        }
+    };
+
+    if let Some(new) = new {
+        db.entries.insert(k, new);
+        save_entry(path, &db, k)?;
+    }
+
+    Ok(())
+}
+
+fn missing(path: &Path, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let missing = db.missing_report();
+
+    if json {
+        return print_json(&missing);
+    }
+
+    missing.into_iter().for_each(|m| {
+        let children: String = m.children.iter().map(|k| k.get()).collect();
+        println!("{} ({}): {}", m.kanji, m.children.len(), children);
+    });
+
+    Ok(())
+}
+
+fn orphans(path: &Path, o: Orphans, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let levels = db.levels();
+
+    let orphans: Vec<Kanji> = db
+        .orphans()
+        .into_iter()
+        .filter(|k| o.level.is_none() || levels.get(k) == o.level.as_ref())
+        .collect();
+
+    if json {
+        return print_json(&orphans);
+    }
+
+    let orphans: String = orphans.iter().map(|k| k.get()).collect();
+    println!("{}", orphans);
+
+    Ok(())
+}
+
+fn merge(path: &Path, m: Merge, rl: &mut Prompt) -> Result<(), Error> {
+    let file = m
+        .file
+        .ok_or(Error::Other("Please give a data file to merge in."))?;
+    let ours = kn_core::read_entries(path)?;
+    let theirs = kn_core::read_entries(&file)?;
+    let result = core::merge::merge(ours, theirs);
+    let resolved = result.conflicts.len();
+    let mut entries = result.merged;
+
+    for c in result.conflicts {
+        let side = match m.prefer {
+            Some(side) => side,
+            None => choose_side(rl, &c)?,
+        };
+        entries.push(c.resolve(side));
+    }
+
+    save_entries(path, entries)?;
+    let added: String = result.added.iter().map(|k| k.get()).collect();
+    println!("Added {} entries: {}", result.added.len(), added);
+    println!("Resolved {} conflicts.", resolved);
+
+    Ok(())
+}
+
+/// Show both sides of a conflict, and ask which to keep.
+fn choose_side(rl: &mut Prompt, c: &Conflict) -> Result<Side, Error> {
+    let side = |e: &Entry| {
+        let parents: String = e.oya.iter().map(|k| k.get()).collect();
+        format!("{} / 親: {}", onyomi_words(&e.onyomi, " "), parents)
+    };
+
+    println!("{} differs:", c.kanji());
+    println!("  ours:   {}", side(&c.ours));
+    println!("  theirs: {}", side(&c.theirs));
+
+    loop {
+        match get_line(rl, "Keep (o)urs or (t)heirs? ")?.trim() {
+            "o" | "ours" => return Ok(Side::Ours),
+            "t" | "theirs" => return Ok(Side::Theirs),
+            _ => {}
+        }
+    }
+}
+
+fn fix(path: &Path, f: Fix) -> Result<(), Error> {
+    if !f.normalize_kana {
+        Err(Error::Other("Usage: kin fix --normalize-kana"))?;
+    }
+
+    let mut entries = kn_core::read_entries(path)?;
+    entries.sort_by_key(|e| e.kanji);
+    let mut fixed = 0;
+
+    entries.iter_mut().for_each(|e| {
+        e.onyomi.iter_mut().for_each(|y| {
+            let normal = utils::normalize_kana(y);
+            if y.reading != normal {
+                println!("{}: {} → {}", e.kanji, y.reading, normal);
+                y.reading = normal.into();
+                fixed += 1;
+            }
+        });
+    });
+
+    entries.iter().for_each(|e| {
+        e.onyomi
+            .iter()
+            .filter(|y| !utils::is_hiragana(y))
+            .for_each(|y| println!("{}", Error::NotHiragana(e.kanji, y.reading.to_string())));
+    });
+
+    if fixed > 0 {
+        save_entries(path, entries)?;
+    }
+
+    println!("Fixed {} readings.", fixed);
+    Ok(())
+}
+
+fn rename(path: &Path, r: Rename) -> Result<(), Error> {
+    let (from, to) = match r.kanji.concat()[..] {
+        [from, to] => (from, to),
+        _ => Err(Error::Other("Please give exactly two Kanji."))?,
+    };
+    let db = kn_core::open_db(path)?;
+
+    if !db.entries.contains_key(&from) {
+        Err(Error::Other("No such Kanji in the database."))?;
+    }
+    if db.entries.contains_key(&to) {
+        Err(Error::Exists(to))?;
+    }
+
+    let entries = db
+        .entries
+        .into_values()
+        .map(|mut e| {
+            e.replace(from, to);
+            e
+        })
+        .collect();
+    save_entries(path, entries)?;
+
+    Ok(())
+}
+
+/// The checks every new or changed entry must pass before being written.
+/// Everything a new entry must pass, however its fields were given.
+fn check_new(db: &DB, entry: &Entry, strict: bool) -> Result<(), Error> {
+    if db.entries.contains_key(&entry.kanji) {
+        Err(Error::Exists(entry.kanji))?;
+    }
+
+    check_entry(db, entry, strict)
+}
+
+fn check_entry(db: &DB, entry: &Entry, strict: bool) -> Result<(), Error> {
+    if let Some(o) = db.cyclic_parents(entry).first() {
+        Err(Error::Cycle(entry.kanji, *o))?;
+    }
+
+    // Anything else would throw off every comparison of readings.
+    if let Some(y) = entry.onyomi.iter().find(|y| !utils::is_hiragana(y)) {
+        Err(Error::NotHiragana(entry.kanji, y.reading.to_string()))?;
+    }
+
+    check_parents(db, entry, strict)
+}
+
+/// Parents without entries of their own never get an edge drawn to them, so
+/// warn about them, or refuse them outright if `strict`.
+fn check_parents(db: &DB, entry: &Entry, strict: bool) -> Result<(), Error> {
+    let missing: Vec<Kanji> = entry
+        .oya
+        .iter()
+        .filter(|o| !db.entries.contains_key(o))
+        .copied()
+        .collect();
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false if strict => Err(Error::UnknownParents(entry.kanji, missing)),
+        false => {
+            println!("Warning: {}", Error::UnknownParents(entry.kanji, missing));
+            Ok(())
+        }
+    }
+}
+
+/// Build an `Entry` purely from the flags given to `kin new`.
+fn flags_entry(n: New) -> Result<Entry, Error> {
+    let kanji = n.kanji.unwrap_or_default();
+    let mut chars = kanji.chars();
+    let kanji = match (chars.next().and_then(Kanji::new), chars.next()) {
+        (Some(k), None) => k,
+        _ => Err(Error::NotKanji(kanji))?,
+    };
+
+    let entry = Entry {
+        oya: strict_kanji(&n.oya)?,
+        kakushi_oya: strict_kanji(&n.kakushi_oya)?,
+        onyomi: n
+            .onyomi
+            .iter()
+            .flat_map(|s| s.split_whitespace())
+            .map(onyomi_from_str)
+            .collect(),
+        daihyou: n
+            .daihyou
+            .iter()
+            .flat_map(|s| s.split_whitespace())
+            .map(|s| s.to_string())
+            .collect(),
+        ..Entry::new(kanji)
+    };
+
+    Ok(entry)
+}
+
+/// Every character of the given words as a `Kanji`, failing on the first
+/// character that isn't one.
+fn strict_kanji(words: &[String]) -> Result<Vec<Kanji>, Error> {
+    words
+        .iter()
+        .flat_map(|w| w.split_whitespace())
+        .flat_map(|w| w.chars())
+        .map(|c| Kanji::new(c).ok_or_else(|| Error::NotKanji(c.to_string())))
+        .collect()
+}
+
+/// Prompt the user for the fields of an `Entry` to add to the database. If the
+/// Kanji itself is already known, it isn't asked for. Given an `old` entry, its
+/// fields are offered for editing. Notes are only asked for if `with_notes`,
+/// and are otherwise kept as they were. Any `words` that fit the Kanji and its
+/// 音読み are offered as 代表 candidates. Answering `quit` to the first prompt
+/// yields nothing.
+fn kanji_prompt(
+    rl: &mut Prompt,
+    history: &Path,
+    known: Option<Kanji>,
+    old: Option<&Entry>,
+    with_notes: bool,
+    words: &[Word],
+    table: &Components,
+) -> Result<Option<Entry>, Error> {
+    rl.load_history(history)?;
+
+    let kanji_field = |f: fn(&Entry) -> &Vec<Kanji>| -> String {
+        old.map(|e| f(e).iter().map(|k| k.get()).collect())
+            .unwrap_or_default()
+    };
+    let text_field = |f: fn(&Entry) -> &Vec<String>| -> String {
+        old.map(|e| f(e).join(" ")).unwrap_or_default()
+    };
+    let onyomi_field = old
+        .map(|e| onyomi_words(&e.onyomi, " "))
+        .unwrap_or_default();
+
+    rl.completing(Complete::Kanji);
+    let line = rl.line_with("親: ", &kanji_field(|e| &e.oya))?;
+    if line.trim() == "quit" {
+        return Ok(None);
+    }
+
+    let mut oya: Vec<Kanji> = line
+        .split_whitespace()
+        .flat_map(|s| s.chars())
+        .filter_map(Kanji::new)
+        .collect();
+
+    let kakushi_oya: Vec<Kanji> = rl
+        .line_with("隠し親: ", &kanji_field(|e| &e.kakushi_oya))?
+        .split_whitespace()
+        .flat_map(|s| s.chars())
+        .filter_map(Kanji::new)
+        .collect();
+
+    rl.completing(Complete::Nothing);
+    let kanji = match known {
+        Some(k) => k,
+        None => get_legal_kanji(rl, "漢字: ")?,
+    };
+
+    // Offer whatever the decomposition has that wasn't already given.
+    for c in table.get(&kanji).into_iter().flatten() {
+        if !oya.contains(c)
+            && !kakushi_oya.contains(c)
+            && confirm(
+                rl,
+                &format!("{} contains {} — add as parent? [y/N] ", kanji, c),
+            )?
+        {
+            oya.push(*c);
+        }
+    }
+
+    rl.completing(Complete::Reading);
+    let onyomi: Vec<Onyomi> = rl
+        .line_with("音読み: ", &onyomi_field)?
+        .split_whitespace()
+        .map(onyomi_from_str)
+        .collect();
+
+    // Chosen by number at the prompt.
+    let mut suggestions: Vec<&Word> = Vec::new();
+    onyomi
+        .iter()
+        .flat_map(|y| jmdict::suggest(words, kanji, y))
+        .for_each(|w| {
+            if suggestions.len() < 9 && !suggestions.contains(&w) {
+                suggestions.push(w);
+            }
+        });
+    suggestions
+        .iter()
+        .enumerate()
+        .for_each(|(i, w)| println!("  {}) {} ({})", i + 1, w.word, w.reading));
+
+    rl.completing(Complete::Nothing);
+    let daihyou: Vec<String> = rl
+        .line_with("代表: ", &text_field(|e| &e.daihyou))?
+        .split_whitespace()
+        .map(|s| {
+            match s
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| suggestions.get(i.checked_sub(1)?))
+            {
+                Some(w) => w.word.clone(),
+                None => s.to_string(),
+            }
+        })
+        .collect();
+
+    let old_notes = old.map(|e| e.notes.as_slice()).unwrap_or_default();
+    let notes = match with_notes {
+        true => notes_prompt(rl, old_notes)?,
+        false => old_notes.to_vec(),
+    };
+
+    let tags = old.map(|e| e.tags.clone()).unwrap_or_default();
+    let variants = old.map(|e| e.variants.clone()).unwrap_or_default();
+    let nite = old.map(|e| e.nite.clone()).unwrap_or_default();
+    let added = old.and_then(|e| e.added);
+
+    // Pinned edges survive, so long as their parent does.
+    let oya_overrides = old
+        .map(|e| {
+            e.oya_overrides
+                .iter()
+                .filter(|(o, _)| oya.contains(o))
+                .map(|(o, i)| (*o, *i))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let entry = Entry {
+        kanji,
+        oya,
+        oya_overrides,
+        kakushi_oya,
+        onyomi,
+        daihyou,
+        notes,
+        tags,
+        variants,
+        nite,
+        added,
+    };
+
+    rl.save_history(history)?;
+
+    Ok(Some(entry))
+}
+
+/// Prompt for one note per line, offering any old ones for editing, until a
+/// blank line.
+fn notes_prompt(rl: &mut Prompt, old: &[String]) -> Result<Vec<String>, Error> {
+    let mut notes = Vec::new();
+
+    loop {
+        let prefill = old.get(notes.len()).map(|n| n.as_str()).unwrap_or_default();
+        match rl.line_with("備考: ", prefill)?.trim() {
+            "" => break,
+            n => notes.push(n.to_string()),
+        }
+    }
+
+    Ok(notes)
+}
+
+fn get_line(rl: &mut Prompt, label: &str) -> Result<String, Error> {
+    rl.line(label)
+}
+
+/// Loop on the input of legal Kanji.
+fn get_legal_kanji(rl: &mut Prompt, label: &str) -> Result<Kanji, Error> {
+    let line = get_line(rl, label)?;
+    let mut chars = line.chars();
+
+    match chars.next().and_then(Kanji::new) {
+        Some(k) => Ok(k),
+        _ => {
+            println!("{} Try again.", Error::NotKanji(line.trim().to_string()));
+            get_legal_kanji(rl, label)
+        }
+    }
+}
+
+// FIXME This should use `NESet` from the get-go.
+fn graph_dot(path: &Path, g: Graph, config: &Config) -> Result<(), Error> {
+    match g.watch {
+        true => watch_graph(path, g, config),
+        false => render_graph(path, g, config),
+    }
+}
+
+/// Render the graph, then render it again every time the data file changes,
+/// until interrupted. A failed render, say of a half-edited file, is reported
+/// and waited out.
+fn watch_graph(path: &Path, mut g: Graph, config: &Config) -> Result<(), Error> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(Error::Watch)?;
+
+    // Editors often save by replacing the file outright, which would end a
+    // watch on the file itself, so its directory is watched instead. A
+    // directory of shards is watched whole.
+    let shards = path.is_dir();
+    let (dir, mode) = match path.parent() {
+        _ if shards => (path, RecursiveMode::Recursive),
+        Some(d) if !d.as_os_str().is_empty() => (d, RecursiveMode::NonRecursive),
+        _ => (Path::new("."), RecursiveMode::NonRecursive),
+    };
+    watcher.watch(dir, mode).map_err(Error::Watch)?;
+    let relevant = |p: &Path| match shards {
+        true => p.extension().and_then(|e| e.to_str()) == Some("json"),
+        false => p.file_name() == path.file_name(),
+    };
+
+    loop {
+        match render_graph(path, g.clone(), config) {
+            Ok(()) => eprintln!("Rendered. Watching {} for changes...", path.display()),
+            Err(e) => eprintln!("{}", e),
+        }
+
+        // The viewer will show the later renders by itself, if it's any good.
+        g.open = false;
+
+        loop {
+            let event = rx
+                .recv()
+                .map_err(|_| Error::Other("Stopped watching the data file."))?
+                .map_err(Error::Watch)?;
+            let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+
+            if written && event.paths.iter().any(|p| relevant(p)) {
+                break;
+            }
+        }
+
+        // A save can arrive as several events. Let them settle first.
+        while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+    }
+}
+
+fn render_graph(path: &Path, g: Graph, config: &Config) -> Result<(), Error> {
+    let ks: Vec<Kanji> = g.kanji.into_iter().flatten().collect();
+    // The flags can only turn on what the config leaves off.
+    let rules = Rules {
+        rendaku: g.rendaku || config.rules.rendaku,
+        strata: g.strata || config.rules.strata,
+    };
+    let db = kn_core::open_db_with(path, rules)?;
+
+    // Tagged Kanji seed the family too, and are highlighted like the others.
+    let tagged = db.tagged(&g.tag);
+    if !g.tag.is_empty() && tagged.is_empty() {
+        Err(Error::Other("No Kanji has any of those tags."))?;
+    }
+
+    let lineages = if g.choose {
+        choose_lineages(&db, &ks)?
+    } else {
+        HashMap::new()
+    };
+
+    let mut palette = config.colours.clone();
+    g.palette
+        .into_iter()
+        .flatten()
+        .for_each(|(i, c)| palette.set(i, c));
+
+    let mut opts = DotOptions {
+        mode: DotMode::NoGroups,
+        palette,
+        ghosts: g.ghosts,
+        colour_by: g.colour_by,
+        labels: g.labels,
+        rankdir: g.rankdir,
+        tooltips: g.tooltips,
+        srs: BTreeMap::new(),
+        learned: BTreeSet::new(),
+        context: BTreeSet::new(),
+    };
+
+    if g.colour_by == ColourBy::WaniKani {
+        opts.srs = progress::read_progress(&progress::progress_path(path))?.wanikani;
+        if opts.srs.is_empty() {
+            Err(Error::Other("No WaniKani data yet. Try kin wanikani sync."))?;
+        }
+    }
+
+    if g.colour_by == ColourBy::Learned {
+        opts.learned = progress::read_progress(&progress::progress_path(path))?.learned;
+        if opts.learned.is_empty() {
+            Err(Error::Other("Nothing learned yet. Try kin learn."))?;
+        }
+    }
+
+    let only: HashSet<Inherit> = g.only.into_iter().flatten().collect();
+    let hide: HashSet<Inherit> = g.hide.into_iter().flatten().collect();
+    let keep = |i: Inherit| (only.is_empty() || only.contains(&i)) && !hide.contains(&i);
+    let levels = db.levels();
+    let shown = |graph: &core::KGraph| match g.max_level {
+        Some(max) => core::filter_nodes(&core::filter_edges(graph, keep), |k| {
+            levels.get(&k).is_some_and(|l| *l <= max)
+        }),
+        None => core::filter_edges(graph, keep),
+    };
+
+    let dot = if ks.is_empty() && tagged.is_empty() {
+        db.dot_custom(HashSet::new(), &shown(&db.graph), &opts)
+    } else {
+        // The kanji we should specially highlight in the final graph.
+        let highlight_by: HashSet<Kanji> = ks.iter().chain(&tagged).copied().collect();
+
+        // The kanji by which we filter the graph down.
+        let hone_by = if g.parents {
+            ks.iter()
+                .filter_map(|k| db.entries.get(k).map(|e| e.oya.iter()))
+                .flatten()
+                .copied()
+                .collect()
+        } else {
+            ks
+        };
+
+        let filtered = db.filtered_graph_via(hone_by, &g.tag, &lineages, g.depth);
+        opts.mode = DotMode::Groups;
+        db.dot_custom(highlight_by, &shown(&filtered), &opts)
+    };
+
+    let format = g.format.as_ref().unwrap_or(&config.graph.format);
+
+    // Raw DOT needs no help from Graphviz.
+    if format == "dot" {
+        if g.open {
+            Err(Error::Other("There's no image to open for DOT output."))?;
+        }
+
+        let mut out: Box<dyn Write> = match g.output {
+            Some(p) => Box::new(std::fs::File::create(p).map_err(Error::Io)?),
+            None => Box::new(std::io::stdout()),
+        };
+        return write!(out, "{}", dot).map_err(Error::Io);
+    }
+
+    let output = g
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("graph.{}", format)));
+
+    render_dot(&dot, &g.engine, format, &output)?;
+
+    if g.open {
+        open_file(&output)?;
+    }
+
+    Ok(())
+}
+
+/// Show a file in the platform's default viewer, without waiting for it.
+fn open_file(path: &Path) -> Result<(), Error> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        // `start` is built into the shell, and takes its first quoted argument
+        // as a window title.
+        let mut c = std::process::Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+
+    command.arg(path).spawn().map_err(Error::Io)?;
+    Ok(())
+}
+
+/// Have Graphviz render some DOT text to a file.
+fn render_dot(dot: &str, engine: &str, format: &str, output: &Path) -> Result<(), Error> {
+    // Note: This demonstrates how to do shell piping from within Rust.
+    let mut child = std::process::Command::new("dot")
+        .arg(format!("-K{}", engine))
+        .arg(format!("-T{}", format))
+        .arg("-o")
+        .arg(output)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(Error::Io)?;
+
+    // Ensures that the handle to `stdin` drops and closes, avoiding a deadlock.
+    // The `unwrap` here is on purpose and advertised in the definition of `stdin` itself.
+    {
+        let mut stdin: std::process::ChildStdin = child.stdin.take().unwrap();
+        writeln!(stdin, "{}", dot).map_err(Error::Io)?; // FIXME Write the bytes directly?
+    }
+
+    if !child.wait().map_err(Error::Io)?.success() {
+        Err(Error::Other("Graphviz failed to render the graph."))?;
+    }
+
+    Ok(())
+}
+
+fn atlas(path: &Path, a: Atlas, config: &Config) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let format = a.format.as_ref().unwrap_or(&config.graph.format);
+    let opts = DotOptions {
+        mode: DotMode::Groups,
+        palette: config.colours.clone(),
+        ..Default::default()
+    };
+
+    std::fs::create_dir_all(&a.output).map_err(Error::Io)?;
+
+    let mut index = String::new();
+    index.push_str("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Kanji Atlas</title></head>\n<body>\n<ul>\n");
+
+    for family in db.components().iter().filter(|c| c.node_count() >= a.min) {
+        // Named after its most prolific parentless member. Only a cycle has
+        // no such member at all.
+        let name = family
+            .node_indices()
+            .filter_map(|ix| family.node_weight(ix).map(|k| (ix, *k)))
+            .filter(|(_, k)| db.entries.get(k).map(|e| e.oya.is_empty()).unwrap_or(false))
+            .min_by_key(|(ix, k)| (std::cmp::Reverse(family.neighbors(*ix).count()), *k))
+            .map(|(_, k)| k)
+            .or_else(|| family.node_weights().min().copied())
+            .map(|k| k.to_string())
+            .unwrap_or_default();
+        let file = format!("{}.{}", name, format);
+
+        let dot = db.dot_custom(HashSet::new(), family, &opts);
+        let out = a.output.join(&file);
+
+        match format.as_str() {
+            "dot" => std::fs::write(out, dot).map_err(Error::Io)?,
+            _ => render_dot(&dot, "dot", format, &out)?,
+        }
+
+        index.push_str(&format!(
+            "<li><a href=\"{}\">{}族</a> ({})</li>\n",
+            file,
+            name,
+            family.node_count()
+        ));
+    }
+
+    index.push_str("</ul>\n</body>\n</html>\n");
+    std::fs::write(a.output.join("index.html"), index).map_err(Error::Io)
+}
+
+/// Ask which parents to follow for each given Kanji that has more than one.
+fn choose_lineages(db: &DB, ks: &[Kanji]) -> Result<HashMap<Kanji, Vec<Kanji>>, Error> {
+    let mut rl = Prompt::tty()?;
+    let mut lineages = HashMap::new();
+
+    for k in ks {
+        let oya = match db.entries.get(k) {
+            Some(e) if e.oya.len() > 1 => &e.oya,
+            _ => continue,
+        };
+
+        let options: Vec<String> = oya
+            .iter()
+            .enumerate()
+            .map(|(i, o)| format!("{}) {}", i + 1, o))
+            .collect();
+        println!("{} has several parents: {}", k, options.join("  "));
+
+        let line = get_line(&mut rl, "Follow which? (blank for all): ")?;
+        let chosen: Vec<Kanji> = line
+            .split_whitespace()
+            .filter_map(|n| n.parse::<usize>().ok())
+            .filter_map(|n| n.checked_sub(1).and_then(|i| oya.get(i)))
+            .copied()
+            .collect();
+
+        if !chosen.is_empty() {
+            lineages.insert(*k, chosen);
+        }
+    }
+
+    Ok(lineages)
+}
+
+fn kanji_from_str(s: &str) -> Vec<Kanji> {
+    s.chars().filter_map(Kanji::new).collect()
+}
+
+/// A reading as typed, in kana or romaji, and perhaps marked with its stratum,
+/// e.g. `gyou(呉)`.
+fn onyomi_from_str(s: &str) -> Onyomi {
+    let y = Onyomi::parse(s);
+    Onyomi {
+        reading: utils::normalize_kana(&y.reading).into(),
+        ..y
+    }
+}
+
+/// Readings as `onyomi_from_str` would read them back.
+fn onyomi_words(ys: &[Onyomi], sep: &str) -> String {
+    ys.iter()
+        .map(|y| y.to_string())
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn db_stats(path: &Path, json: bool) -> Result<(), Error> {
+    let start = SystemTime::now();
+    let db = kn_core::open_db(path)?;
+    let micros = start.elapsed().map_err(Error::Time)?.as_micros();
+    let learned = progress::read_progress(&progress::progress_path(path))?.learned;
+    let stats = kn_core::report::Stats {
+        weekly: db.added_per_week(now()?, 12),
+        learned: (!learned.is_empty()).then(|| db.learned_stats(&learned, 10)),
+        ..db.stats()
+    };
+
+    if json {
+        return print_json(&stats);
+    }
+
+    println!("DB loaded in {} microseconds.", micros);
+    println!("DB contains {} entries.", stats.entries);
+    println!("Kanji Levels completed:");
+
+    stats.levels.iter().for_each(|l| {
+        println!(
+            "  - {:?}: {}/{}",
+            l.level, l.progress.entered, l.progress.total
+        );
+    });
+
+    let jouyou = &stats.jouyou;
+    let percent = 100.0 * (jouyou.entered as f64) / (jouyou.total as f64);
+    println!(
+        "常用 Completion: {}/{} ({:.2}%)",
+        jouyou.entered, jouyou.total, percent
+    );
+
+    if let Some(l) = &stats.learned {
+        println!(
+            "Learned: {}/{} entries ({:.2}%)",
+            l.entries.learned,
+            l.entries.total,
+            l.entries.percent()
+        );
+        l.levels.iter().for_each(|l| {
+            println!("  - {:?}: {}/{}", l.level, l.known.learned, l.known.total);
+        });
+        println!("Learned by family:");
+        l.families.iter().for_each(|f| {
+            println!("  - {}: {}/{}", f.kanji, f.known.learned, f.known.total);
+        });
+    }
+
+    if !stats.weekly.is_empty() {
+        let total: usize = stats.weekly.iter().sum();
+        println!(
+            "Added in the last {} weeks: {} {}",
+            stats.weekly.len(),
+            sparkline(&stats.weekly),
+            total
+        );
+    }
+
+    Ok(())
+}
+
+fn edge_stats(path: &Path, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let stats = db.edge_stats(3, 10);
+
+    if json {
+        return print_json(&stats);
+    }
+
+    println!("DB contains {} edges.", stats.edges);
+    stats.kinds.iter().for_each(|k| {
+        println!("  - {}: {} ({:.2}%)", k.inherit, k.count, k.percent);
+    });
+
+    if !stats.irregular.is_empty() {
+        println!("Families with the most Differ edges:");
+        stats.irregular.iter().for_each(|f| {
+            println!("  - {}: {}/{}", f.kanji, f.differ, f.children);
+        });
+    }
+
+    Ok(())
+}
+
+fn levels(ks: Vec<String>, json: bool) -> Result<(), Error> {
+    let ks: Vec<Kanji> = ks.iter().flat_map(|s| kanji_from_str(s)).collect();
+    let levels = core::report::levels_of(&ks);
+
+    if json {
+        return print_json(&levels);
+    }
+
+    levels
+        .iter()
+        .for_each(|l| println!("{}: {:?}", l.kanji, l.level));
+    Ok(())
+}
+
+/// Pretty-print anything serializable to stdout, for `--json`.
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), Error> {
+    let out = serde_json::to_string_pretty(value).map_err(Error::Json)?;
+    println!("{}", out);
+    Ok(())
+}
+
+fn next(path: &Path, n: Next, json: bool) -> Result<(), Error> {
+    let count = match n.count {
+        Some(0) => Err(Error::Other("--count must be at least 1."))?,
+        c => c.unwrap_or(1),
+    };
+    let db = kn_core::open_db(path)?;
+    let levels = kanji::level_table();
+    let gaps = gaps(&db).filter(|k| n.level.is_none() || levels.get(k) == n.level.as_ref());
+
+    let next: Vec<Kanji> = match n.random {
+        true => fastrand::choose_multiple(gaps, count),
+        false => gaps.take(count).collect(),
+    };
+
+    // A lone answer is given as it always was, not as a list.
+    if json {
+        return match n.count {
+            None => print_json(&next.first()),
+            Some(_) => print_json(&next),
+        };
+    }
+
+    next.iter().for_each(|k| println!("{}", k));
+    Ok(())
+}
+
+fn diff(path: &Path, d: Diff, json: bool) -> Result<(), Error> {
+    let at = |file: &Path, rev: &str| -> Result<DB, Error> {
+        Ok(kn_core::open_db_from_reader(&git::show(file, rev)?[..])?)
+    };
+    // Only a single JSON file can be read back out of git history.
+    if d.git.is_some() {
+        let file = d.files.first().map(|f| f.as_path()).unwrap_or(path);
+        if file.is_dir() {
+            Err(Error::Other(
+                "--git needs a single data file, not a directory of shards.",
+            ))?;
+        }
+        if core::store::is_sqlite(file) {
+            Err(Error::Other(
+                "--git needs a JSON data file, not a SQLite database.",
+            ))?;
+        }
+    }
+
+    let (old, new) = match (d.git, &d.files[..]) {
+        (None, [a, b]) => (kn_core::open_db(a)?, kn_core::open_db(b)?),
+        (Some(rev), []) => (at(path, &rev)?, kn_core::open_db(path)?),
+        (Some(rev), [f]) => (at(f, &rev)?, kn_core::open_db(f)?),
+        _ => Err(Error::Other(
+            "Please give two data files, or --git with at most one.",
+        ))?,
+    };
+    let diff = old.diff(&new);
+
+    if json {
+        return print_json(&diff);
+    }
+
+    if diff.is_empty() {
+        println!("No differences.");
+        return Ok(());
+    }
+
+    let kanji = |ks: &[Kanji]| -> String { ks.iter().map(|k| k.get()).collect() };
+    let inherit = |i: Option<Inherit>| i.map(|i| i.to_string()).unwrap_or("-".to_string());
+
+    if !diff.added.is_empty() {
+        println!("Added: {}", kanji(&diff.added));
+    }
+    if !diff.removed.is_empty() {
+        println!("Removed: {}", kanji(&diff.removed));
+    }
+    if !diff.readings.is_empty() {
+        println!("Readings:");
+        diff.readings.iter().for_each(|c| {
+            println!(
+                "  - {}: {} → {}",
+                c.kanji,
+                onyomi_words(&c.old, " "),
+                onyomi_words(&c.new, " ")
+            );
+        });
+    }
+    if !diff.parents.is_empty() {
+        println!("Parents:");
+        diff.parents.iter().for_each(|c| {
+            println!("  - {}: {} → {}", c.kanji, kanji(&c.old), kanji(&c.new));
+        });
+    }
+    if !diff.edges.is_empty() {
+        println!("Edges:");
+        diff.edges.iter().for_each(|e| {
+            println!(
+                "  - {} → {}: {} → {}",
+                e.oya,
+                e.child,
+                inherit(e.old),
+                inherit(e.new)
+            );
+        });
+    }
+
+    Ok(())
+}
+
+fn predict(path: &Path, p: Predict, json: bool) -> Result<(), Error> {
+    let (k, oya) = match p.kanji.concat()[..] {
+        [k, oya] => (k, oya),
+        _ => Err(Error::Other("Please give a Kanji and its proposed parent."))?,
+    };
+    let db = kn_core::open_db(path)?;
+    let prediction = db
+        .predict(
+            k,
+            oya,
+            Rules {
+                rendaku: p.rendaku,
+                ..Rules::default()
+            },
+        )
+        .ok_or(Error::Other("The parent has no entry in the database."))?;
+
+    if json {
+        return print_json(&prediction);
+    }
+
+    println!("{} ← {}", k, oya);
+    println!("Children of {}: {}", oya, prediction.children);
+    prediction.patterns.iter().for_each(|p| {
+        println!("  - {}: {} ({:.2}%)", p.inherit, p.count, p.percent);
+    });
+
+    println!("Candidates:");
+    prediction.candidates.iter().for_each(|c| {
+        let mark = if c.recorded { " ✓" } else { "" };
+        println!("  - {} ({} of {}){}", c.reading, c.inherit, c.from, mark);
+    });
+
+    if !prediction.recorded.is_empty() {
+        println!(
+            "Recorded 音読み: {}",
+            onyomi_words(&prediction.recorded, " ")
+        );
+    }
+
+    Ok(())
+}
+
+fn top(path: &Path, t: Top, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let mut ranked = db.centrality();
+    match t.by {
+        TopBy::Children => ranked.sort_by_key(|c| std::cmp::Reverse((c.children, c.descendants))),
+        TopBy::Descendants => {
+            ranked.sort_by_key(|c| std::cmp::Reverse((c.descendants, c.children)))
+        }
+    }
+    ranked.truncate(t.count);
+
+    if json {
+        return print_json(&ranked);
+    }
+
+    ranked.iter().enumerate().for_each(|(i, c)| {
+        println!(
+            "{:>3}. {}: {} descendants, {} children",
+            i + 1,
+            c.kanji,
+            c.descendants,
+            c.children
+        );
+    });
+
+    Ok(())
+}
+
+fn recent(path: &Path, r: Recent, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let since = now()?.saturating_sub(r.days.saturating_mul(DAY));
+    let added = db.added_since(since);
+
+    if json {
+        return print_json(&added);
+    }
+
+    added
+        .iter()
+        .for_each(|a| println!("{} {}", date(a.added), a.kanji));
+    Ok(())
+}
+
+fn remaining(path: &Path, r: Remaining, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let levels = kanji::level_table();
+    let mut needed: HashMap<Kanji, Vec<Kanji>> = match r.parents {
+        true => db
+            .missing_report()
+            .into_iter()
+            .map(|m| (m.kanji, m.children))
+            .collect(),
+        false => HashMap::new(),
+    };
+
+    let remaining: Vec<MissingParent> = gaps(&db)
+        .filter(|k| r.level.is_none() || levels.get(k) == r.level.as_ref())
+        .map(|kanji| MissingParent {
+            kanji,
+            children: needed.remove(&kanji).unwrap_or_default(),
+        })
+        .collect();
+
+    if json {
+        return print_json(&remaining);
+    }
+
+    remaining.iter().for_each(|m| match m.children.is_empty() {
+        true => println!("{}", m.kanji),
+        false => {
+            let children: String = m.children.iter().map(|k| k.get()).collect();
+            println!("{} ({}): {}", m.kanji, m.children.len(), children);
+        }
+    });
+
+    Ok(())
+}
+
+/// The first Kanji of the level lists, easiest first, that isn't in the
+/// database yet.
+fn next_kanji(db: &DB) -> Option<Kanji> {
+    gaps(db).next()
+}
+
+/// Every Kanji of the level lists, easiest first, that isn't in the database
+/// yet.
+fn gaps(db: &DB) -> impl Iterator<Item = Kanji> + '_ {
+    LEVEL_10
+        .chars()
+        .chain(LEVEL_09.chars())
+        .chain(LEVEL_08.chars())
+        .chain(LEVEL_07.chars())
+        .chain(LEVEL_06.chars())
+        .chain(LEVEL_05.chars())
+        .chain(LEVEL_04.chars())
+        .chain(LEVEL_03.chars())
+        .chain(LEVEL_02_PRE.chars())
+        .chain(LEVEL_02.chars())
+        .chain(LEVEL_01_PRE.chars())
+        .chain(LEVEL_01.chars())
+        .filter_map(kanji::Kanji::new)
+        .filter(|k| !db.entries.contains_key(k))
+}
+
+fn path(path: &Path, c: Chain, config: &Config) -> Result<(), Error> {
+    let (a, b) = match c.kanji.concat()[..] {
+        [a, b] => (a, b),
+        _ => Err(Error::Other("Please give exactly two Kanji."))?,
+    };
+    let db = kn_core::open_db_with(path, config.rules)?;
+
+    match db.path(a, b) {
+        None => println!("No connection between {} and {}.", a, b),
+        Some(hops) => {
+            let mut prev = a;
+            let mut line = a.to_string();
+
+            for hop in hops {
+                let (child, oya) = match hop.direction {
+                    Direction::Outgoing => (hop.kanji, prev),
+                    Direction::Incoming => (prev, hop.kanji),
+                };
+                // A pinned edge has no readings to show for it.
+                let label = match (db.entries.get(&child), db.entries.get(&oya)) {
+                    (Some(c), _) if c.oya_overrides.contains_key(&oya) => hop.inherit.to_string(),
+                    (Some(c), Some(o)) => match Inherit::explain(c, o, config.rules) {
+                        Match {
+                            inherit,
+                            child: Some(cy),
+                            oya: Some(oy),
+                        } => format!("{}({}→{})", inherit, oy, cy),
+                        m => m.inherit.to_string(),
+                    },
+                    _ => hop.inherit.to_string(),
+                };
+                line = match hop.direction {
+                    Direction::Outgoing => format!("{} ─{}→ {}", line, label, hop.kanji),
+                    Direction::Incoming => format!("{} ←{}─ {}", line, label, hop.kanji),
+                };
+                prev = hop.kanji;
+            }
+
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
+
+fn lint(path: &Path, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let cycles = db.cycles();
+    let strays = db.stray_parents(&ids::read_components(&ids::ids_path(path))?);
+    let drifted = db.drifted_daihyou();
+    let dups = db.near_duplicates();
+
+    if json {
+        return print_json(&serde_json::json!({
+            "cycles": cycles,
+            "stray_parents": strays,
+            "drifted_daihyou": drifted,
+            "near_duplicates": dups,
+        }));
+    }
+
+    if !cycles.is_empty() {
+        println!("Cycles:");
+        cycles.iter().for_each(|cycle| {
+            let line: Vec<String> = cycle.iter().map(|k| k.to_string()).collect();
+            println!("  - {}", line.join(", "));
+        });
+    }
+
+    if !strays.is_empty() {
+        println!("Parents outside the decomposition:");
+        strays.iter().for_each(|s| {
+            let parents: String = s.parents.iter().map(|k| k.get()).collect();
+            println!("  - {}: {}", s.kanji, parents);
+        });
+    }
+
+    if !drifted.is_empty() {
+        println!("代表 out of step with their entries:");
+        drifted.iter().for_each(|d| {
+            let why = match d.drift {
+                Drift::NoKanji => "doesn't contain the Kanji",
+                Drift::NoReading => "isn't read with any of its 音読み",
+            };
+            println!("  - {}: {} {}", d.kanji, d.word, why);
+        });
+    }
+
+    if !dups.is_empty() {
+        println!("Possible duplicates:");
+        dups.iter().for_each(|d| {
+            let why = match d.likeness {
+                Likeness::Compatibility => "a compatibility form",
+                Likeness::SameReadingsAndParents => "the same readings and parents",
+            };
+            println!(
+                "  - {} (U+{:04X}) and {}: {}. Merge {} into {}?",
+                d.merge,
+                d.merge.get() as u32,
+                d.keep,
+                why,
+                d.merge,
+                d.keep
+            );
+        });
+    }
+
+    Ok(())
+}
+
+fn plan(path: &Path, p: Plan) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let frequency = match p.kanjidic {
+        None => HashMap::new(),
+        Some(p) => {
+            let raw = std::fs::read_to_string(p).map_err(Error::Io)?;
+            kn_core::kanjidic::frequency_table(&raw)
+        }
+    };
+    let plan: String = db.plan(&frequency).into_iter().map(|k| k.get()).collect();
+
+    match p.per_day {
+        Some(0) => Err(Error::Other("--per-day must be at least 1."))?,
+        Some(n) => {
+            let chars: Vec<char> = plan.chars().collect();
+            chars.chunks(n).enumerate().for_each(|(i, day)| {
+                let batch: String = day.iter().collect();
+                println!("Day {}: {}", i + 1, batch);
+            });
+        }
+        None => println!("{}", plan),
+    }
+
+    Ok(())
+}
+
+fn export(path: &Path, e: Export, config: &Config) -> Result<(), Error> {
+    if let Some(ExportCommand::Levels(l)) = e.command {
+        return export_levels(path, l, config);
+    }
+
+    let mut out: Box<dyn Write> = match e.output {
+        Some(p) => Box::new(std::fs::File::create(p).map_err(Error::Io)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    match e.format {
+        Format::Graph(f) => kn_core::open_db(path)?.export(f, out)?,
+        Format::Csv => sheet::write(out, b',', &kn_core::read_entries(path)?)?,
+        Format::Tsv => sheet::write(out, b'\t', &kn_core::read_entries(path)?)?,
+        Format::Progress => {
+            let progress = progress::read_progress(&progress::progress_path(path))?;
+            serde_json::to_writer_pretty(out, &progress).map_err(Error::Json)?
+        }
+        Format::Phonology => {
+            let db = kn_core::open_db(path)?;
+            let mut entries: Vec<&Entry> = db.entries.values().collect();
+            entries.sort_by_key(|e| e.kanji);
+
+            writeln!(out, "kanji,reading,onset,glide,vowel,coda").map_err(Error::Io)?;
+            for e in entries {
+                for y in e.onyomi.iter() {
+                    // Readings that don't parse get empty columns, to be noticed.
+                    let line = match phonology::decompose(y) {
+                        Some(s) => format!(
+                            "{},{},{},{},{},{}",
+                            e.kanji, y.reading, s.onset, s.glide, s.vowel, s.coda
+                        ),
+                        None => format!("{},{},,,,", e.kanji, y.reading),
+                    };
+                    writeln!(out, "{}", line).map_err(Error::Io)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// For each level, its graph as DOT and perhaps an image, and its entries as
+/// JSON, all named after the level, like `準二級.json`.
+fn export_levels(path: &Path, l: ExportLevels, config: &Config) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let format = l.format.as_ref().unwrap_or(&config.graph.format);
+    let levels = db.levels();
+
+    let mut by_level: BTreeMap<Level, Vec<&Entry>> = BTreeMap::new();
+    levels.iter().for_each(|(k, level)| {
+        if let Some(e) = db.entries.get(k) {
+            by_level.entry(*level).or_default().push(e);
+        }
+    });
+
+    std::fs::create_dir_all(&l.out).map_err(Error::Io)?;
+
+    for (level, mut entries) in by_level {
+        entries.sort_by_key(|e| e.kanji);
+
+        let context: BTreeSet<Kanji> = entries
+            .iter()
+            .flat_map(|e| e.oya.iter())
+            .filter(|o| levels.get(o).is_some_and(|ol| *ol < level))
+            .copied()
+            .collect();
+        let graph = core::filter_nodes(&db.graph, |k| {
+            levels.get(&k) == Some(&level) || context.contains(&k)
+        });
+        let opts = DotOptions {
+            palette: config.colours.clone(),
+            context,
+            ..Default::default()
+        };
+        let dot = db.dot_custom(HashSet::new(), &graph, &opts);
+
+        let name = format!("{}級", level);
+        let json = serde_json::to_string_pretty(&entries).map_err(Error::Json)?;
+        std::fs::write(l.out.join(format!("{}.json", name)), json).map_err(Error::Io)?;
+        std::fs::write(l.out.join(format!("{}.dot", name)), &dot).map_err(Error::Io)?;
+
+        if format != "dot" {
+            let image = l.out.join(format!("{}.{}", name, format));
+            render_dot(&dot, "dot", format, &image)?;
+        }
+
+        println!("{}: {} Kanji", name, entries.len());
+    }
+
+    Ok(())
+}
+
+fn import(path: &Path, i: Import) -> Result<(), Error> {
+    let file = i
+        .file
+        .ok_or(Error::Other("Please give a file to import."))?;
+
+    match i.format {
+        Format::Progress => {
+            let local = progress::progress_path(path);
+            let raw = std::fs::read_to_string(&file).map_err(Error::Io)?;
+            let mut progress = progress::read_progress(&local)?;
+            progress.merge(serde_json::from_str(&raw).map_err(Error::Json)?);
+            progress::write_progress(&local, &progress)?;
+        }
+        Format::Csv => import_sheet(path, &file, b',')?,
+        Format::Tsv => import_sheet(path, &file, b'\t')?,
+        Format::Graph(_) => Err(Error::Other("Graph formats can only be exported."))?,
+        Format::Phonology => Err(Error::Other("phonology can only be exported."))?,
+    }
+
+    Ok(())
+}
+
+/// Update or add an entry for every row of a spreadsheet.
+fn import_sheet(path: &Path, file: &Path, delimiter: u8) -> Result<(), Error> {
+    let input = std::fs::File::open(file).map_err(Error::Io)?;
+    let rows = sheet::read(input, delimiter)?;
+    let mut entries: HashMap<Kanji, Entry> = kn_core::read_entries(path)?
+        .into_iter()
+        .map(|e| (e.kanji, e))
+        .collect();
+    let before: HashSet<Vec<Kanji>> = cycle_set(&DB::new(entries.clone())?);
+
+    let touched = rows
+        .into_iter()
+        .map(|row| sheet::apply(&mut entries, row))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Parents may well appear further down the sheet, so nothing can be
+    // checked until every row is in.
+    let db = DB::new(entries)?;
+    let cycles: Vec<Vec<Kanji>> = db
+        .cycles()
+        .into_iter()
+        .filter(|c| !before.contains(&sorted(c)))
+        .collect();
+    if !cycles.is_empty() {
+        println!("Cycles:");
+        cycles.iter().for_each(|cycle| {
+            let line: Vec<String> = cycle.iter().map(|k| k.to_string()).collect();
+            println!("  - {}", line.join(", "));
+        });
+        Err(Error::Other(
+            "The import would form cycles, so nothing was saved.",
+        ))?;
+    }
+    for k in touched.iter() {
+        check_parents(&db, &db.entries[k], false)?;
+    }
+
+    save_entries(path, db.entries.into_values().collect())?;
+    println!("Imported {} rows.", touched.len());
+
+    Ok(())
+}
+
+/// Every cycle in the graph, each in a canonical order for comparison.
+fn cycle_set(db: &DB) -> HashSet<Vec<Kanji>> {
+    db.cycles().iter().map(|c| sorted(c)).collect()
+}
+
+fn sorted(cycle: &[Kanji]) -> Vec<Kanji> {
+    let mut c = cycle.to_vec();
+    c.sort();
+    c
+}
+
+fn families(path: &Path, f: Families, json: bool) -> Result<(), Error> {
+    let ks = f.kanji.concat();
+    if ks.is_empty() {
+        Err(Error::Other("Please give at least one Kanji."))?;
+    }
+
+    let db = kn_core::open_db(path)?;
+    let family = db.to_json_graph_of(&db.filtered_graph(ks, f.depth));
+
+    if json {
+        return print_json(&family);
+    }
+
+    let kanji: String = family.nodes.iter().map(|n| n.kanji.get()).collect();
+    println!("Kanji: {}", kanji);
+    println!("Edges:");
+    family.edges.iter().for_each(|e| {
+        println!("  - {} → {}: {}", e.from, e.to, e.inherit);
+    });
+
+    Ok(())
+}
+
+fn tree(path: &Path, t: Tree) -> Result<(), Error> {
+    tree_of(&kn_core::open_db(path)?, t)
+}
+
+fn tree_of(db: &DB, t: Tree) -> Result<(), Error> {
+    let k = match t.kanji.concat()[..] {
+        [k] => k,
+        _ => Err(Error::Other("Please give exactly one Kanji."))?,
+    };
+    let nix = *db
+        .index
+        .get(&k)
+        .ok_or(Error::Other("No such Kanji in the database."))?;
+    let root = tree_label(db, nix);
+
+    println!("Ancestors:");
+    println!("{}", root);
+    tree_branch(db, nix, Direction::Incoming, "", &mut vec![nix]);
+    println!();
+    println!("Descendants:");
+    println!("{}", root);
+    tree_branch(db, nix, Direction::Outgoing, "", &mut vec![nix]);
+
+    Ok(())
+}
+
+/// Print the relatives of a node in one direction, recursively. The `seen`
+/// stack guards against infinite descent through a cycle.
+fn tree_branch(db: &DB, nix: NodeIndex, dir: Direction, prefix: &str, seen: &mut Vec<NodeIndex>) {
+    let mut relatives: Vec<_> = db.neighbours(nix, dir).collect();
+    relatives.sort_by_key(|(rix, _)| db.graph.node_weight(*rix).copied());
+    let last = relatives.len().saturating_sub(1);
+
+    for (i, (rix, inherit)) in relatives.into_iter().enumerate() {
+        let (branch, indent) = if i == last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        println!("{}{}{} [{}]", prefix, branch, tree_label(db, rix), inherit);
+
+        if !seen.contains(&rix) {
+            seen.push(rix);
+            tree_branch(db, rix, dir, &format!("{}{}", prefix, indent), seen);
+            seen.pop();
+        }
+    }
+}
+
+/// A `Kanji` and its first 音読み, if it has one.
+fn tree_label(db: &DB, nix: NodeIndex) -> String {
+    match db.entry(nix) {
+        Some(e) => match e.onyomi.first() {
+            Some(y) => format!("{} ({})", e.kanji, y),
+            None => e.kanji.to_string(),
+        },
+        None => "?".to_string(),
+    }
+}
+
+fn show(path: &Path, s: Show, json: bool) -> Result<(), Error> {
+    show_of(&kn_core::open_db(path)?, s, json)
+}
+
+fn show_of(db: &DB, s: Show, json: bool) -> Result<(), Error> {
+    let ks = s.kanji.concat();
+    if ks.is_empty() {
+        Err(Error::Other("Please give at least one Kanji."))?;
+    }
+
+    let kunyomi = match s.kanjidic {
+        Some(p) => {
+            let raw = std::fs::read_to_string(p).map_err(Error::Io)?;
+            kn_core::kanjidic::kunyomi_table(&raw)
+        }
+        None => HashMap::new(),
+    };
+
+    let reports = ks
+        .iter()
+        .map(|k| {
+            let mut r = db
+                .report(*k)
+                .ok_or(Error::Other("No such Kanji in the database."))?;
+            r.kunyomi = kunyomi.get(k).cloned().unwrap_or_default();
+            Ok(r)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    if s.json || json {
+        return print_json(&reports);
+    }
+
+    let relatives = |rs: &[core::report::Relative]| -> String {
+        let rs: Vec<String> = rs
+            .iter()
+            .map(|r| match r.inherit {
+                Some(i) => format!("{} ({})", r.kanji, i),
+                None => r.kanji.to_string(),
+            })
+            .collect();
+        rs.join(", ")
+    };
+
+    for (i, r) in reports.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        let hidden: String = r.kakushi_oya.iter().map(|k| k.get()).collect();
+        let lines = [
+            ("級", r.level.clone().unwrap_or_default()),
+            ("音読み", onyomi_words(&r.onyomi, " ")),
+            ("訓読み", r.kunyomi.join(" ")),
+            ("親", relatives(&r.oya)),
+            ("隠し親", hidden),
+            ("子", relatives(&r.children)),
+            ("異体字", r.variants.iter().map(|k| k.get()).collect()),
+            ("形近字", r.nite.iter().map(|k| k.get()).collect()),
+            ("代表", r.daihyou.join(" ")),
+            ("備考", r.notes.join(" / ")),
+            ("タグ", r.tags.join(" ")),
+        ];
+
+        println!("{}", r.kanji);
+        lines
+            .iter()
+            .filter(|(_, v)| !v.is_empty())
+            .for_each(|(l, v)| println!("  {}: {}", l, v));
+    }
+
+    Ok(())
+}
+
+fn audit_readings(path: &Path, a: Audit) -> Result<(), Error> {
+    let mut db = kn_core::open_db(path)?;
+    let raw = std::fs::read_to_string(&a.kanjidic).map_err(Error::Io)?;
+    let table = kn_core::kanjidic::onyomi_table(&raw);
+    let audits = db.audit_readings(&table);
+
+    if !a.merge {
+        audits.iter().for_each(|audit| {
+            println!("{}", audit.kanji);
+            if !audit.missing.is_empty() {
+                println!("  - Missing: {}", audit.missing.join(" "));
+            }
+            if !audit.extra.is_empty() {
+                println!("  - Extra: {}", audit.extra.join(" "));
+            }
+        });
+
+        return Ok(());
+    }
+
+    let mut rl = Prompt::tty()?;
+
+    for audit in audits {
+        let entry = match db.entries.get_mut(&audit.kanji) {
+            Some(e) => e,
+            None => continue,
+        };
+
+        for y in audit.missing {
+            if confirm(&mut rl, &format!("{}: Add {}? [y/N] ", audit.kanji, y))? {
+                entry.onyomi.push(Onyomi::from(y));
+            }
+        }
+
+        for y in audit.extra {
+            if confirm(&mut rl, &format!("{}: Remove {}? [y/N] ", audit.kanji, y))? {
+                entry.onyomi.retain(|x| *x != y);
+            }
+        }
+    }
+
+    save_db(path, db)?;
+    Ok(())
+}
+
+/// Ask a yes/no question, defaulting to no.
+fn confirm(rl: &mut Prompt, label: &str) -> Result<bool, Error> {
+    let line = get_line(rl, label)?;
+    Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+}
+
+fn reading(path: &Path, r: Reading, json: bool) -> Result<(), Error> {
+    reading_of(&kn_core::open_db(path)?, r, json)
+}
+
+fn reading_of(db: &DB, r: Reading, json: bool) -> Result<(), Error> {
+    let matches: Vec<ReadingMatch> = r
+        .readings
+        .iter()
+        .map(|y| utils::to_hiragana(&utils::romaji_to_hiragana(y)))
+        .map(|reading| ReadingMatch {
+            kanji: db.with_reading(&reading),
+            reading,
+        })
+        .collect();
+
+    if json {
+        return print_json(&matches);
+    }
+
+    matches.iter().for_each(|m| {
+        let ks: String = m.kanji.iter().map(|k| k.get()).collect();
+        println!("{}: {}", m.reading, ks);
+    });
+
+    Ok(())
+}
+
+fn search(path: &Path, s: Search, json: bool) -> Result<(), Error> {
+    if s.meaning.trim().is_empty() {
+        Err(Error::Other("Please give some text to search for."))?;
+    }
+
+    let db = kn_core::open_db(path)?;
+    let meanings = match s.kanjidic {
+        Some(p) => {
+            let raw = std::fs::read_to_string(p).map_err(Error::Io)?;
+            kn_core::kanjidic::meaning_table(&raw)
+        }
+        None => HashMap::new(),
+    };
+    let found = db.search_meaning(&s.meaning, &meanings);
+
+    if json {
+        return print_json(&found);
+    }
+
+    // Bold, but only for a person to read.
+    let bold = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    found.iter().for_each(|m| {
+        let (a, b) = m.range;
+        let text = if bold {
+            format!(
+                "{}\x1b[1m{}\x1b[0m{}",
+                &m.text[..a],
+                &m.text[a..b],
+                &m.text[b..]
+            )
+        } else {
+            m.text.clone()
+        };
+        println!("{} ({}): {}", m.kanji, m.field, text);
+    });
+
+    Ok(())
+}
+
+fn find(path: &Path, f: Find, json: bool) -> Result<(), Error> {
+    let query = f.query.join(" ");
+    if query.trim().is_empty() {
+        Err(Error::Other("Please give something to find."))?;
+    }
+
+    let db = kn_core::open_db(path)?;
+    let meanings = match f.kanjidic {
+        Some(p) => {
+            let raw = std::fs::read_to_string(p).map_err(Error::Io)?;
+            kn_core::kanjidic::meaning_table(&raw)
+        }
+        None => HashMap::new(),
+    };
+    let mut hits = db.find(&query, &meanings);
+    hits.truncate(f.count);
+
+    if json {
+        return print_json(&hits);
+    }
+
+    hits.iter().for_each(|h| match h.text.is_empty() {
+        true => println!("{} ({:?})", h.kanji, h.matched),
+        false => println!("{} ({:?}): {}", h.kanji, h.matched, h.text),
+    });
+
+    Ok(())
+}
+
+fn homophones(path: &Path, h: Homophones, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let found = db.homophones(h.count);
+
+    if json {
+        return print_json(&found);
+    }
+
+    found.iter().for_each(|h| {
+        println!("{} ({})", h.reading, h.count);
+        h.families.iter().for_each(|f| {
+            let roots: String = f.roots.iter().map(|k| k.get()).collect();
+            let ks: String = f.kanji.iter().map(|k| k.get()).collect();
+            println!("  - {}: {}", roots, ks);
+        });
+    });
+
+    Ok(())
+}
+
+fn confusables(path: &Path, c: Confusables, json: bool) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+
+    let found: Vec<Lookalikes> = c
+        .kanji
+        .concat()
+        .into_iter()
+        .map(|kanji| Lookalikes {
+            kanji,
+            lookalikes: db.lookalikes(kanji),
+        })
+        .collect();
+
+    if json {
+        return print_json(&found);
+    }
 
-    LEVEL_10
-        .chars()
-        .chain(LEVEL_09.chars())
-        .chain(LEVEL_08.chars())
-        .chain(LEVEL_07.chars())
-        .chain(LEVEL_06.chars())
-        .chain(LEVEL_05.chars())
-        .chain(LEVEL_04.chars())
-        .chain(LEVEL_03.chars())
-        .chain(LEVEL_02_PRE.chars())
-        .chain(LEVEL_02.chars())
-        .chain(LEVEL_01_PRE.chars())
-        .chain(LEVEL_01.chars())
-        .filter_map(kanji::Kanji::new)
-        .find(|k| db.entries.get(k).is_none())
+    found.iter().for_each(|l| {
+        let ks: String = l.lookalikes.iter().map(|k| k.get()).collect();
+        println!("{}: {}", l.kanji, ks);
+    });
+
+    Ok(())
+}
+
+fn consolidate(path: &Path, rl: &mut Prompt) -> Result<(), Error> {
+    let mut db = kn_core::open_db(path)?;
+    let mut changed = false;
+
+    for k in db.parentless() {
+        let candidates: Vec<_> = db.similar_readings(k).into_iter().take(8).collect();
+
+        if candidates.is_empty() {
+            continue;
+        }
+
+        let yomi = db
+            .entries
+            .get(&k)
+            .map(|e| onyomi_words(&e.onyomi, " "))
+            .unwrap_or_default();
+        println!("{} ({}) has no parents. Candidates:", k, yomi);
+        candidates
+            .iter()
+            .enumerate()
+            .for_each(|(i, (o, inherit))| println!("  {}) {} [{}]", i + 1, o, inherit));
+
+        let line = get_line(rl, "親 (number or Kanji, blank to skip, q to quit): ")?;
+
+        if line.trim() == "q" {
+            break;
+        }
+
+        let oya: Vec<Kanji> = line
+            .split_whitespace()
+            .flat_map(|w| match w.parse::<usize>() {
+                Ok(n) => n
+                    .checked_sub(1)
+                    .and_then(|i| candidates.get(i))
+                    .map(|(o, _)| *o)
+                    .into_iter()
+                    .collect(),
+                Err(_) => kanji_from_str(w),
+            })
+            .collect();
+
+        if let Some(e) = db.entries.get_mut(&k) {
+            e.oya.extend(oya.iter().filter(|o| **o != k));
+            changed |= !e.oya.is_empty();
+        }
+    }
+
+    if changed {
+        save_db(path, db)?;
+    }
+
+    Ok(())
+}
+
+fn check_cli(path: &Path, config: &Config, c: CheckCli) -> Result<(), Error> {
+    let mut rl = Prompt::script(&c.answers)?;
+
+    match c.flow.first().map(|f| f.as_str()) {
+        Some("new") => new_entry(path, config, New::default(), &mut rl),
+        Some("new-notes") => {
+            let n = New {
+                with_notes: true,
+                ..Default::default()
+            };
+            new_entry(path, config, n, &mut rl)
+        }
+        Some("new-loop") => {
+            let mut db = kn_core::open_db(path)?;
+            new_loop(path, &mut db, config, false, false, &mut rl)
+        }
+        Some("edit") => {
+            let e = Edit {
+                help: false,
+                editor: false,
+                strict: false,
+                kanji: c.flow[1..].iter().map(|s| kanji_from_str(s)).collect(),
+            };
+            edit(path, config, e, &mut rl)
+        }
+        Some("consolidate") => consolidate(path, &mut rl),
+        Some("merge") => {
+            let m = Merge {
+                help: false,
+                prefer: c.prefer,
+                file: c.flow.get(1).map(PathBuf::from),
+            };
+            merge(path, m, &mut rl)
+        }
+        Some(f) => Err(Error::Parse(format!("Unknown flow: {}", f))),
+        None => Err(Error::Other("No flow given.")),
+    }
+}
+
+fn poster(path: &Path, p: Poster) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let range = match p.level_range {
+        None => Level::Ten..=Level::One,
+        Some(r) => {
+            let (from, to) = r
+                .split_once("..")
+                .ok_or_else(|| Error::Parse(format!("Not a level range: {}", r)))?;
+            let (from, to) = (level_from_str(from)?, level_from_str(to)?);
+            // Either direction reads naturally, so `5..10` is `10..5`.
+            from.min(to)..=from.max(to)
+        }
+    };
+    let svg = poster::poster(&db, range, p.families);
+
+    match p.output.extension().and_then(|e| e.to_str()) {
+        Some("pdf") => {
+            let mut child = std::process::Command::new("rsvg-convert")
+                .arg("--format=pdf")
+                .arg("--output")
+                .arg(&p.output)
+                .stdin(std::process::Stdio::piped())
+                .spawn()
+                .map_err(Error::Io)?;
+
+            {
+                let mut stdin: std::process::ChildStdin = child.stdin.take().unwrap();
+                stdin.write_all(svg.as_bytes()).map_err(Error::Io)?;
+            }
+
+            child.wait().map_err(Error::Io)?;
+        }
+        _ => std::fs::write(&p.output, svg).map_err(Error::Io)?,
+    }
+
+    Ok(())
+}
+
+fn side_from_str(s: &str) -> Result<Side, Error> {
+    match s.trim() {
+        "ours" => Ok(Side::Ours),
+        "theirs" => Ok(Side::Theirs),
+        _ => Err(Error::Parse(format!("Not a side: {}", s))),
+    }
+}
+
+/// A `Level` as written by a human: 10 through 1, with 準2 (or p2) and 準1 (or
+/// p1) for the pre-levels.
+fn level_from_str(s: &str) -> Result<Level, Error> {
+    let level = match s.trim() {
+        "10" => Level::Ten,
+        "9" => Level::Nine,
+        "8" => Level::Eight,
+        "7" => Level::Seven,
+        "6" => Level::Six,
+        "5" => Level::Five,
+        "4" => Level::Four,
+        "3" => Level::Three,
+        "準2" | "p2" | "pre2" => Level::PreTwo,
+        "2" => Level::Two,
+        "準1" | "p1" | "pre1" => Level::PreOne,
+        "1" => Level::One,
+        _ => Err(Error::Parse(format!("Not a level: {}", s)))?,
+    };
+
+    Ok(level)
+}
+
+fn inherits_from_str(s: &str) -> Result<Vec<Inherit>, Error> {
+    s.split(',')
+        .map(|i| i.trim().parse().map_err(Error::Parse))
+        .collect()
+}
+
+fn palette_from_str(s: &str) -> Result<Vec<(Inherit, String)>, Error> {
+    s.split(',')
+        .map(|pair| match pair.split_once('=') {
+            Some((i, c)) if !c.trim().is_empty() => {
+                let inherit = i.trim().parse().map_err(Error::Parse)?;
+                Ok((inherit, c.trim().to_string()))
+            }
+            _ => Err(Error::Parse(format!("Not a kind=colour pair: {}", pair))),
+        })
+        .collect()
+}
+
+fn usage_report(path: &Path, u: UsageReport) -> Result<(), Error> {
+    let ledger = usage::read_usage(&usage::usage_path(path))?;
+
+    if ledger.commands.is_empty() {
+        println!("The ledger is empty. Set `usage = true` in the config file to start one.");
+        return Ok(());
+    }
+
+    let mut commands: Vec<_> = ledger.commands.iter().collect();
+    commands.sort_by_key(|(c, n)| (std::cmp::Reverse(**n), *c));
+    println!("Commands:");
+    commands
+        .iter()
+        .take(u.top)
+        .for_each(|(c, n)| println!("  {:>5}  {}", n, c));
+
+    let consulted = ledger.most_consulted();
+    let db = kn_core::open_db(path)?;
+    let mut families: HashMap<Kanji, u32> = HashMap::new();
+    consulted.iter().for_each(|(k, n)| {
+        db.roots(*k)
+            .into_iter()
+            .for_each(|r| *families.entry(r).or_default() += n)
+    });
+    let mut families: Vec<_> = families.into_iter().collect();
+    families.sort_by_key(|(k, n)| (std::cmp::Reverse(*n), *k));
+
+    println!("Families:");
+    families
+        .iter()
+        .take(u.top)
+        .for_each(|(k, n)| println!("  {:>5}  {}族", n, k));
+
+    println!("Kanji:");
+    consulted
+        .iter()
+        .take(u.top)
+        .for_each(|(k, n)| println!("  {:>5}  {}", n, k));
+
+    Ok(())
+}
+
+/// Back up the data file, then overwrite it with the given database.
+fn save_db(path: &Path, db: DB) -> Result<(), Error> {
+    backup::take(path, now()?)?;
+    kn_core::write_db(path, db)?;
+    Ok(())
+}
+
+/// Back up the data file, then save a single entry of the given database. A
+/// SQLite database is updated in place, but a JSON one is rewritten in full.
+fn save_entry(path: &Path, db: &DB, k: Kanji) -> Result<(), Error> {
+    backup::take(path, now()?)?;
+
+    match (core::store::is_sqlite(path), db.entries.get(&k)) {
+        (true, Some(e)) => core::store::Sqlite::open(path)?.put(e)?,
+        _ => kn_core::write_entries(path, db.entries.values().cloned().collect())?,
+    }
+
+    Ok(())
+}
+
+/// Back up the data file, then overwrite it with the given entries.
+fn save_entries(path: &Path, entries: Vec<Entry>) -> Result<(), Error> {
+    backup::take(path, now()?)?;
+    kn_core::write_entries(path, entries)?;
+    Ok(())
+}
+
+fn migrate(path: &Path, m: Migrate) -> Result<(), Error> {
+    let to_sqlite = match m.direction.as_deref() {
+        None => return upgrade(path),
+        Some("json-to-sqlite") => true,
+        Some("sqlite-to-json") => false,
+        _ => Err(Error::Other(
+            "Please give json-to-sqlite or sqlite-to-json.",
+        ))?,
+    };
+
+    if core::store::is_sqlite(path) == to_sqlite {
+        let msg = match to_sqlite {
+            true => "The data file is already a SQLite database.",
+            false => "The data file is already JSON.",
+        };
+        Err(Error::Other(msg))?;
+    }
+
+    let output = m.output.unwrap_or_else(|| match to_sqlite {
+        true => path.with_extension("sqlite"),
+        false => path.with_extension("json"),
+    });
+    if core::store::is_sqlite(&output) != to_sqlite {
+        Err(Error::Other(
+            "The output's extension doesn't match the direction.",
+        ))?;
+    }
+    if output.exists() {
+        Err(Error::Other("The output file already exists."))?;
+    }
+
+    let entries = kn_core::read_entries(path)?;
+    let count = entries.len();
+    kn_core::write_entries(&output, entries)?;
+    println!("Copied {} entries into {}.", count, output.display());
+
+    Ok(())
+}
+
+/// Rewrite a JSON data file at the newest layout version.
+fn upgrade(path: &Path) -> Result<(), Error> {
+    if core::store::is_sqlite(path) {
+        Err(Error::Other("Only JSON data files have a layout version."))?;
+    }
+
+    let from = core::migrate::file_version(path)?;
+    let to = core::migrate::CURRENT;
+
+    if from >= to {
+        println!("{} is already at version {}.", path.display(), to);
+        return Ok(());
+    }
+
+    save_entries(path, kn_core::read_entries(path)?)?;
+    println!(
+        "Upgraded {} from version {} to {}.",
+        path.display(),
+        from,
+        to
+    );
+
+    Ok(())
+}
+
+fn cache(path: &Path, c: Cache, config: &Config) -> Result<(), Error> {
+    match c.command {
+        Some(CacheCommand::Rebuild(_)) => {
+            let db = core::cache::rebuild(path, config.rules)?;
+            println!("Cached {} entries.", db.entries.len());
+        }
+        None => println!("{}", core::cache::cache_path(path).display()),
+    }
+
+    Ok(())
+}
+
+fn learn(path: &Path, l: Learn, learned: bool) -> Result<(), Error> {
+    let ks = l.kanji.concat();
+    if ks.is_empty() {
+        Err(Error::Other("Please give some Kanji."))?;
+    }
+
+    let ppath = progress::progress_path(path);
+    let mut progress = progress::read_progress(&ppath)?;
+    ks.into_iter().for_each(|k| {
+        match learned {
+            true => progress.learned.insert(k),
+            false => progress.learned.remove(&k),
+        };
+    });
+    progress::write_progress(&ppath, &progress)?;
+    println!("{} Kanji learned.", progress.learned.len());
+
+    Ok(())
+}
+
+fn wanikani(path: &Path, w: Wanikani) -> Result<(), Error> {
+    match w.command {
+        Some(WanikaniCommand::Sync(s)) => {
+            let stages = wanikani::srs_stages(&s.token)?;
+            let ppath = progress::progress_path(path);
+            let mut progress = progress::read_progress(&ppath)?;
+            let count = stages.len();
+            progress.wanikani = stages;
+            progress::write_progress(&ppath, &progress)?;
+            println!("Synced {} Kanji from WaniKani.", count);
+        }
+        None => Err(Error::Other("Usage: kin wanikani sync --token <TOKEN>"))?,
+    }
+
+    Ok(())
+}
+
+fn jmdict(path: &Path, j: Jmdict) -> Result<(), Error> {
+    match j.command {
+        Some(JmdictCommand::Import(i)) => {
+            let file = i.file.ok_or(Error::Other("Please give a JMdict file."))?;
+            let reader = std::io::BufReader::new(std::fs::File::open(file).map_err(Error::Io)?);
+            let words = jmdict::common_words(reader).map_err(Error::Io)?;
+            jmdict::write_words(&jmdict::jmdict_path(path), &words)?;
+            println!("Imported {} common words.", words.len());
+        }
+        None => Err(Error::Other("Usage: kin jmdict import <FILE>"))?,
+    }
+
+    Ok(())
+}
+
+fn ids(path: &Path, i: Ids) -> Result<(), Error> {
+    match i.command {
+        Some(IdsCommand::Import(i)) => {
+            let file = i.file.ok_or(Error::Other("Please give an IDS file."))?;
+            let reader = std::io::BufReader::new(std::fs::File::open(file).map_err(Error::Io)?);
+            let table = ids::components_table(reader).map_err(Error::Io)?;
+            ids::write_components(&ids::ids_path(path), &table)?;
+            println!("Imported decompositions of {} Kanji.", table.len());
+        }
+        None => Err(Error::Other("Usage: kin ids import <FILE>"))?,
+    }
+
+    Ok(())
+}
+
+fn unihan(path: &Path, u: Unihan) -> Result<(), Error> {
+    let file = match u.command {
+        Some(UnihanCommand::Import(i)) => {
+            i.file.ok_or(Error::Other("Please give a Unihan file."))?
+        }
+        None => Err(Error::Other("Usage: kin unihan import <FILE>"))?,
+    };
+    let raw = std::fs::read_to_string(file).map_err(Error::Io)?;
+    let table = unihan::variant_table(&raw);
+
+    let mut entries = kn_core::read_entries(path)?;
+    let mut linked = 0;
+
+    entries.iter_mut().for_each(|e| {
+        let mut variants: Vec<Kanji> = table
+            .get(&e.kanji)
+            .into_iter()
+            .flatten()
+            .chain(e.variants.iter())
+            .copied()
+            .collect();
+        variants.sort();
+        variants.dedup();
+
+        if variants != e.variants {
+            e.variants = variants;
+            linked += 1;
+        }
+    });
+
+    match linked {
+        0 => println!("No new variants."),
+        n => {
+            save_entries(path, entries)?;
+            println!("Linked variants for {} Kanji.", n);
+        }
+    }
+
+    Ok(())
+}
+
+fn undo(path: &Path) -> Result<(), Error> {
+    match backup::restore_latest(path)? {
+        None => println!("There are no backups to restore."),
+        Some(t) => {
+            let mins = now()?.saturating_sub(t) / 60;
+            println!("Restored the backup from {} minutes ago.", mins);
+        }
+    }
+
+    Ok(())
+}
+
+/// One bar per count, scaled to the largest.
+fn sparkline(counts: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().max().copied().unwrap_or_default().max(1);
+
+    counts
         .iter()
-        .for_each(|k| println!("{}", k));
+        .map(|c| BARS[c * (BARS.len() - 1) / max])
+        .collect()
+}
+
+/// A time in seconds since the Unix epoch as a UTC date, like 2024-03-09.
+fn date(secs: u64) -> String {
+    // Howard Hinnant's `civil_from_days`, for days since 1970-01-01.
+    let z = (secs / DAY) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Seconds in a day.
+const DAY: u64 = 24 * 60 * 60;
+
+/// Seconds since the Unix epoch.
+fn now() -> Result<u64, Error> {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(Error::Time)?
+        .as_secs();
+    Ok(secs)
+}
+
+fn review(path: &Path, r: Review) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let ppath = progress::progress_path(path);
+    let mut progress = progress::read_progress(&ppath)?;
+    let now = now()?;
+
+    // Kanji that keep needing to be looked up are the first to be studied.
+    let ledger = usage::read_usage(&usage::usage_path(path))?;
+    let mut seen = HashSet::new();
+    let fresh: Vec<Kanji> = ledger
+        .most_consulted()
+        .into_iter()
+        .map(|(k, _)| k)
+        .filter(|k| db.entries.contains_key(k))
+        .chain(db.plan(&HashMap::new()))
+        .filter(|k| seen.insert(*k))
+        .filter(|k| !progress.srs.contains_key(k))
+        .take(r.new)
+        .collect();
+    fresh.into_iter().for_each(|k| {
+        progress.srs.insert(k, progress::Card::new(now));
+    });
+
+    // Only take as many cards as should fit into the time budget.
+    let budget = r.minutes.map(|m| Duration::from_secs(m * 60));
+    let mut expected = Duration::ZERO;
+    let cards: Vec<Kanji> = progress
+        .due(now)
+        .into_iter()
+        .take_while(|k| {
+            let millis = progress.expected_millis(k).unwrap_or(15_000);
+            expected += Duration::from_millis(millis);
+            budget.map(|b| expected <= b).unwrap_or(true)
+        })
+        .collect();
+
+    let mut rl = Prompt::tty()?;
+    let start = Instant::now();
+    let mut reviewed = 0;
+
+    for k in cards {
+        if budget.map(|b| start.elapsed() >= b).unwrap_or(false) {
+            println!("Time's up!");
+            break;
+        }
+
+        let onyomi = db.entries.get(&k).map(|e| &e.onyomi[..]).unwrap_or(&[]);
+        let asked = Instant::now();
+        let answer = get_line(&mut rl, &format!("{}: ", k))?;
+        let millis = asked.elapsed().as_millis() as u64;
+        let answer = utils::to_hiragana(&utils::romaji_to_hiragana(answer.trim()));
+        let correct = onyomi.iter().any(|y| *y == answer);
+
+        if correct {
+            println!("正解！");
+        } else {
+            println!("✗ {}", onyomi_words(onyomi, " "));
+        }
+
+        progress.quiz.entry(k).or_default().record(correct, millis);
+        if let Some(card) = progress.srs.get_mut(&k) {
+            card.answer(correct, now);
+        }
+        reviewed += 1;
+    }
+
+    progress::write_progress(&ppath, &progress)?;
+
+    let remaining = progress.due(now).len();
+    println!("Reviewed {} Kanji. {} still due.", reviewed, remaining);
+
+    Ok(())
+}
+
+fn quiz(path: &Path, q: Quiz) -> Result<(), Error> {
+    let db = kn_core::open_db(path)?;
+    let ppath = progress::progress_path(path);
+    let mut progress = progress::read_progress(&ppath)?;
+    let now = now()?;
+
+    let mut readings: Vec<&str> = db
+        .entries
+        .values()
+        .flat_map(|e| e.onyomi.iter().map(|y| y.as_str()))
+        .collect();
+    readings.sort();
+    readings.dedup();
+
+    let mut pool: Vec<&Entry> = db
+        .entries
+        .values()
+        .filter(|e| !e.onyomi.is_empty())
+        .collect();
+    pool.sort_by_key(|e| e.kanji);
+    let entries = fastrand::choose_multiple(pool, q.count);
+
+    let mut rl = Prompt::tty()?;
+    let mut results = Vec::with_capacity(entries.len());
+
+    for e in entries.iter() {
+        let answer = &e.onyomi[0];
+
+        // Prefer readings that sound like the answer, then fill in with others.
+        let wanted = q.choices.saturating_sub(1);
+        let mut options = kn_core::quiz::distractors(&db, e.kanji, wanted);
+        let lacking = wanted.saturating_sub(options.len());
+        let others = readings
+            .iter()
+            .filter(|y| !e.onyomi.iter().any(|o| o == **y) && !options.iter().any(|o| o == *y))
+            .map(|y| y.to_string());
+        options.extend(fastrand::choose_multiple(others, lacking));
+        options.push(answer.reading.to_string());
+        fastrand::shuffle(&mut options);
+
+        let listing: Vec<String> = options
+            .iter()
+            .enumerate()
+            .map(|(i, y)| format!("{}) {}", i + 1, y))
+            .collect();
+        println!("{}  {}", e.kanji, listing.join("  "));
+
+        let asked = Instant::now();
+        let line = get_line(&mut rl, "> ")?;
+        let millis = asked.elapsed().as_millis() as u64;
+        let correct = line
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| options.get(i))
+            .map(|y| e.onyomi.iter().any(|o| o == y))
+            .unwrap_or(false);
+
+        results.push((e.kanji, correct));
+        if correct {
+            println!("正解！");
+        } else {
+            println!("✗ {}", onyomi_words(&e.onyomi, " "));
+        }
+
+        progress
+            .quiz
+            .entry(e.kanji)
+            .or_default()
+            .record(correct, millis);
+        if let Some(card) = progress.srs.get_mut(&e.kanji) {
+            card.answer(correct, now);
+        }
+    }
+
+    progress::write_progress(&ppath, &progress)?;
+    let score = results.iter().filter(|(_, correct)| *correct).count();
+    println!("Score: {}/{}", score, entries.len());
+
+    if let Some(out) = q.export {
+        site::quiz(&db, &out, &results)?;
+    }
+
+    Ok(())
+}
+
+fn edge(path: &Path, e: Edge) -> Result<(), Error> {
+    let (child, oya, kind) = match &e.args[..] {
+        [c, o, kind] => match (&kanji_from_str(c)[..], &kanji_from_str(o)[..]) {
+            ([c], [o]) => (*c, *o, kind),
+            _ => Err(Error::Other("Please give a single child and parent Kanji."))?,
+        },
+        _ => Err(Error::Other("Usage: kin edge <child> <parent> <kind>"))?,
+    };
+
+    let mut db = kn_core::open_db(path)?;
+    let entry = db
+        .entries
+        .get_mut(&child)
+        .ok_or(Error::Other("No such Kanji in the database."))?;
+
+    if !entry.oya.contains(&oya) {
+        Err(Error::NotParent(child, oya))?;
+    }
+
+    if kind == "auto" {
+        entry.oya_overrides.remove(&oya);
+    } else {
+        let inherit: Inherit = kind.parse().map_err(Error::Parse)?;
+        entry.oya_overrides.insert(oya, inherit);
+    }
+
+    save_db(path, db)?;
+    Ok(())
+}
+
+fn tag(path: &Path, t: Tag) -> Result<(), Error> {
+    let (args, adding) = match t.command {
+        Some(TagCommand::Add(a)) => (a.args, true),
+        Some(TagCommand::Remove(a)) => (a.args, false),
+        None => return tag_list(path),
+    };
+    let (k, tag) = match &args[..] {
+        [k, tag] => match (&kanji_from_str(k)[..], tag.trim()) {
+            (_, "") => Err(Error::Other("Please give a non-empty tag."))?,
+            ([k], tag) => (*k, tag.to_string()),
+            _ => Err(Error::NotKanji(k.clone()))?,
+        },
+        _ => Err(Error::Other("Usage: kin tag add|remove <kanji> <tag>"))?,
+    };
+
+    let mut db = kn_core::open_db(path)?;
+    let entry = db
+        .entries
+        .get_mut(&k)
+        .ok_or(Error::Other("No such Kanji in the database."))?;
+
+    match (adding, entry.tags.contains(&tag)) {
+        (true, false) => {
+            entry.tags.push(tag);
+            entry.tags.sort();
+        }
+        (false, true) => entry.tags.retain(|x| *x != tag),
+        // Nothing to do.
+        _ => return Ok(()),
+    }
+
+    save_entry(path, &db, k)
+}
+
+/// Every tag in use, with the Kanji that have it.
+fn tag_list(path: &Path) -> Result<(), Error> {
+    let mut tags: BTreeMap<String, Vec<Kanji>> = BTreeMap::new();
+    kn_core::read_entries(path)?.into_iter().for_each(|e| {
+        e.tags
+            .into_iter()
+            .for_each(|t| tags.entry(t).or_default().push(e.kanji))
+    });
+
+    tags.into_iter().for_each(|(t, mut ks)| {
+        ks.sort();
+        let ks: String = ks.iter().map(|k| k.get()).collect();
+        println!("{}: {}", t, ks);
+    });
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(DAY - 1), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_709_942_400), "2024-03-09");
+        assert_eq!(date(1_735_689_599), "2024-12-31");
+        assert_eq!(date(4_107_542_400), "2100-03-01");
+    }
+}
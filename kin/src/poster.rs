@@ -0,0 +1,201 @@
+//! Wall charts of the largest Kanji families.
+//!
+//! Each family is drawn as its own tile, one row per generation, with every
+//! Kanji coloured by its exam level. Tiles are packed left-to-right into a
+//! page wide enough to print at A0.
+
+use kn_core::{Direction, Kanji, Level, DB, LEVEL_COLOURS};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+/// The width of the whole poster, in SVG units.
+const PAGE_WIDTH: usize = 3200;
+/// The side length of the square given to each Kanji.
+const CELL: usize = 48;
+/// Space around tiles and around the edge of the page.
+const MARGIN: usize = 32;
+/// Height of the title above each tile.
+const TITLE: usize = 40;
+/// No generation is drawn wider than this, so that huge families stay legible.
+const MAX_ROW: usize = 24;
+
+/// A single family, already split into generations.
+struct Family {
+    root: Kanji,
+    generations: Vec<Vec<Kanji>>,
+}
+
+impl Family {
+    fn size(&self) -> usize {
+        self.generations.iter().map(|g| g.len()).sum()
+    }
+
+    fn width(&self) -> usize {
+        let widest = self.generations.iter().map(|g| g.len()).max();
+        // Leave room for the title, even for tiny families.
+        widest.unwrap_or(1).clamp(4, MAX_ROW) * CELL
+    }
+
+    fn height(&self) -> usize {
+        let rows: usize = self
+            .generations
+            .iter()
+            .map(|g| g.len().div_ceil(MAX_ROW))
+            .sum();
+        TITLE + rows * CELL
+    }
+}
+
+/// An SVG poster of the `count` largest families, considering only Kanji whose
+/// level falls in the given range.
+pub fn poster(db: &DB, range: RangeInclusive<Level>, count: usize) -> String {
+    let levels = db.levels();
+    let wanted = |k: &Kanji| levels.get(k).map(|l| range.contains(l)).unwrap_or(false);
+
+    let mut families: Vec<Family> = db
+        .parentless()
+        .into_iter()
+        .filter(&wanted)
+        .map(|root| family(db, root, &wanted))
+        .filter(|f| f.size() > 1)
+        .collect();
+    families.sort_by_key(|f| (std::cmp::Reverse(f.size()), f.root));
+    families.truncate(count);
+
+    let mut body = String::new();
+    let (mut x, mut y, mut shelf) = (MARGIN, MARGIN + key_height(), 0);
+
+    for f in families.iter() {
+        if x + f.width() + MARGIN > PAGE_WIDTH && x > MARGIN {
+            x = MARGIN;
+            y += shelf + MARGIN;
+            shelf = 0;
+        }
+
+        tile(&mut body, f, &levels, x, y);
+        x += f.width() + MARGIN;
+        shelf = shelf.max(f.height());
+    }
+
+    let height = y + shelf + MARGIN;
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = PAGE_WIDTH,
+        h = height
+    );
+    let _ = writeln!(s, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+    key(&mut s, &range);
+    s.push_str(&body);
+    s.push_str("</svg>\n");
+    s
+}
+
+/// The descendants of a root, one generation at a time. A Kanji with several
+/// parents appears only in the earliest generation that reaches it.
+fn family<F>(db: &DB, root: Kanji, wanted: &F) -> Family
+where
+    F: Fn(&Kanji) -> bool,
+{
+    let mut seen: HashSet<Kanji> = HashSet::new();
+    let mut generations = Vec::new();
+    let mut current = vec![root];
+    seen.insert(root);
+
+    while !current.is_empty() {
+        let mut next: Vec<Kanji> = current
+            .iter()
+            .filter_map(|k| db.index.get(k))
+            .flat_map(|kix| db.neighbours(*kix, Direction::Outgoing))
+            .filter_map(|(cix, _)| db.graph.node_weight(cix).copied())
+            .filter(|k| wanted(k))
+            .filter(|k| seen.insert(*k))
+            .collect();
+        next.sort();
+        generations.push(current);
+        current = next;
+    }
+
+    Family { root, generations }
+}
+
+fn key_height() -> usize {
+    TITLE + CELL
+}
+
+/// The level colour key across the top of the page.
+fn key(s: &mut String, range: &RangeInclusive<Level>) {
+    let _ = writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\" font-size=\"28\">級</text>",
+        MARGIN,
+        MARGIN + 28
+    );
+
+    LEVEL_COLOURS
+        .iter()
+        .filter(|(l, _)| range.contains(l))
+        .enumerate()
+        .for_each(|(i, (l, colour))| {
+            let x = MARGIN + (i + 1) * CELL * 2;
+            let _ = writeln!(
+                s,
+                "<rect x=\"{}\" y=\"{}\" width=\"{c}\" height=\"{c}\" fill=\"{}\" stroke=\"black\"/>",
+                x,
+                MARGIN,
+                colour,
+                c = CELL - 8
+            );
+            let _ = writeln!(
+                s,
+                "<text x=\"{}\" y=\"{}\" font-size=\"16\" text-anchor=\"middle\">{}</text>",
+                x + (CELL - 8) / 2,
+                MARGIN + CELL + 12,
+                l
+            );
+        });
+}
+
+/// A single family, with its top-left corner at the given point.
+fn tile(s: &mut String, f: &Family, levels: &HashMap<Kanji, Level>, x: usize, y: usize) {
+    let colours: BTreeMap<Level, &str> = LEVEL_COLOURS.iter().copied().collect();
+
+    let _ = writeln!(
+        s,
+        "<text x=\"{}\" y=\"{}\" font-size=\"24\">{}族 ({})</text>",
+        x,
+        y + 28,
+        f.root,
+        f.size()
+    );
+
+    let mut row = 0;
+    for generation in f.generations.iter() {
+        for line in generation.chunks(MAX_ROW) {
+            for (i, k) in line.iter().enumerate() {
+                let colour = levels
+                    .get(k)
+                    .and_then(|l| colours.get(l))
+                    .unwrap_or(&"white");
+                let cx = x + i * CELL + CELL / 2;
+                let cy = y + TITLE + row * CELL + CELL / 2;
+                let _ = writeln!(
+                    s,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"gray\"/>",
+                    cx,
+                    cy,
+                    CELL / 2 - 2,
+                    colour
+                );
+                let _ = writeln!(
+                    s,
+                    "<text x=\"{}\" y=\"{}\" font-size=\"26\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                    cx, cy, k
+                );
+            }
+            row += 1;
+        }
+    }
+}
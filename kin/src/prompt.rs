@@ -0,0 +1,214 @@
+//! Where the answers to interactive prompts come from.
+//!
+//! Normally that's the terminal, but `kin check-cli` can feed a flow its
+//! answers from a file instead, one per line, so that the interactive commands
+//! can be exercised end-to-end by scripts and their bugs reproduced exactly.
+//!
+//! At the terminal, Tab completes Kanji and readings from the database, once
+//! it's been given one via `Prompt::complete_from`. Pressing Tab again cycles
+//! through the candidates.
+
+use crate::Error;
+use kn_core::{utils, Kanji, Reading, DB};
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::Path;
+
+/// A source of answers to interactive prompts.
+pub enum Prompt {
+    /// A live terminal.
+    Tty(Box<Editor<Completion, FileHistory>>),
+    /// Prepared answers, consumed in order.
+    Script(std::vec::IntoIter<String>),
+}
+
+impl Prompt {
+    /// Read answers from the terminal.
+    pub fn tty() -> Result<Prompt, Error> {
+        let rl = Editor::new().map_err(Error::Readline)?;
+        Ok(Prompt::Tty(Box::new(rl)))
+    }
+
+    /// Read answers from a file, one per line.
+    pub fn script(path: &Path) -> Result<Prompt, Error> {
+        let raw = std::fs::read_to_string(path).map_err(Error::Io)?;
+        let lines: Vec<String> = raw.lines().map(|l| l.to_string()).collect();
+        Ok(Prompt::Script(lines.into_iter()))
+    }
+
+    /// Ask for a single line of input.
+    pub fn line(&mut self, label: &str) -> Result<String, Error> {
+        match self {
+            Prompt::Tty(rl) => match rl.readline(label) {
+                Ok(line) => {
+                    rl.add_history_entry(&line).map_err(Error::Readline)?;
+                    Ok(line)
+                }
+                Err(_) => Err(Error::Other("CLI input failed.")),
+            },
+            // Echo the exchange, so that the output reads like a terminal session.
+            Prompt::Script(lines) => match lines.next() {
+                Some(line) => {
+                    println!("{}{}", label, line);
+                    Ok(line)
+                }
+                None => Err(Error::Other("Ran out of scripted answers.")),
+            },
+        }
+    }
+
+    /// Ask for a single line of input, starting from some existing text. A
+    /// scripted answer replaces the existing text entirely.
+    pub fn line_with(&mut self, label: &str, initial: &str) -> Result<String, Error> {
+        match self {
+            Prompt::Tty(rl) => match rl.readline_with_initial(label, (initial, "")) {
+                Ok(line) => {
+                    rl.add_history_entry(&line).map_err(Error::Readline)?;
+                    Ok(line)
+                }
+                Err(_) => Err(Error::Other("CLI input failed.")),
+            },
+            Prompt::Script(_) => self.line(label),
+        }
+    }
+
+    /// Offer completions drawn from the given database.
+    pub fn complete_from(&mut self, db: &DB) {
+        if let Prompt::Tty(rl) = self {
+            rl.set_helper(Some(Completion::new(db)));
+        }
+    }
+
+    /// Set what Tab should complete for the prompts that follow.
+    pub fn completing(&mut self, target: Complete) {
+        if let Some(c) = self.completion_mut() {
+            c.target = target;
+        }
+    }
+
+    fn completion_mut(&mut self) -> Option<&mut Completion> {
+        match self {
+            Prompt::Tty(rl) => rl.helper_mut(),
+            Prompt::Script(_) => None,
+        }
+    }
+
+    /// Load previous input from a history file, if it exists.
+    pub fn load_history(&mut self, path: &Path) -> Result<(), Error> {
+        match self {
+            Prompt::Tty(rl) if path.exists() => rl.load_history(path).map_err(Error::Readline),
+            _ => Ok(()),
+        }
+    }
+
+    /// Save this session's input to a history file. Scripted answers aren't
+    /// worth remembering.
+    pub fn save_history(&mut self, path: &Path) -> Result<(), Error> {
+        match self {
+            Prompt::Tty(rl) => rl.save_history(path).map_err(Error::Readline),
+            Prompt::Script(_) => Ok(()),
+        }
+    }
+}
+
+/// What Tab completes at the current prompt.
+#[derive(Clone, Copy)]
+pub enum Complete {
+    Nothing,
+    /// Kanji in the database, by typing one of their readings.
+    Kanji,
+    /// Readings already in the database.
+    Reading,
+}
+
+/// Completion candidates, drawn from the database.
+pub struct Completion {
+    target: Complete,
+    /// Every reading in the database, and the Kanji that have it.
+    readings: BTreeMap<Reading, Vec<Kanji>>,
+}
+
+impl Completion {
+    fn new(db: &DB) -> Completion {
+        let mut readings: BTreeMap<Reading, Vec<Kanji>> = BTreeMap::new();
+        db.entries.values().for_each(|e| {
+            e.onyomi
+                .iter()
+                .for_each(|y| readings.entry(y.reading.clone()).or_default().push(e.kanji))
+        });
+        readings.values_mut().for_each(|ks| ks.sort());
+
+        Completion {
+            target: Complete::Nothing,
+            readings,
+        }
+    }
+
+    /// Known readings that start with the given one.
+    fn starting_with<'a>(
+        &'a self,
+        yomi: &'a str,
+    ) -> impl Iterator<Item = (&'a Reading, &'a Vec<Kanji>)> {
+        self.readings
+            .range::<str, _>((Bound::Included(yomi), Bound::Unbounded))
+            .take_while(move |(y, _)| y.starts_with(yomi))
+    }
+}
+
+impl Completer for Completion {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        let word = &line[start..pos];
+        let yomi = utils::to_hiragana(&utils::romaji_to_hiragana(word));
+
+        let candidates = match self.target {
+            _ if word.is_empty() => Vec::new(),
+            Complete::Nothing => Vec::new(),
+            Complete::Kanji => self
+                .starting_with(&yomi)
+                .flat_map(|(y, ks)| {
+                    ks.iter().map(move |k| Pair {
+                        display: format!("{} ({})", k, y),
+                        replacement: k.to_string(),
+                    })
+                })
+                .collect(),
+            Complete::Reading => self
+                .starting_with(&yomi)
+                .map(|(y, _)| Pair {
+                    display: y.to_string(),
+                    replacement: y.to_string(),
+                })
+                .collect(),
+        };
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
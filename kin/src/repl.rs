@@ -0,0 +1,90 @@
+//! An interactive session over a database that's read only once.
+//!
+//! Each line is a command and its arguments, taking the same flags as the
+//! `kin` command of the same name, like `show --json 語` or `reading kou`.
+//! Entries added along the way are saved straight away, and are visible to the
+//! commands that follow.
+
+use crate::config::Config;
+use crate::prompt::{Complete, Prompt};
+use crate::{Error, New, Reading, Show, Tree};
+use gumdrop::{Options, ParsingStyle};
+use std::path::Path;
+
+const HELP: &str = "Commands:
+  show KANJI...     Display entries in full.
+  tree KANJI        Print the family of a Kanji as a tree.
+  reading YOMI...   Show all Kanji with a given reading.
+  add [--kanji ...] Add a new entry to the database.
+  help              Show this message.
+  quit              Leave the session.
+
+Give --help to a command for its flags.";
+
+/// Read commands from the terminal until told to quit.
+pub fn repl(path: &Path, config: &Config) -> Result<(), Error> {
+    let mut db = kn_core::open_db_with(path, config.rules)?;
+    let mut rl = Prompt::tty()?;
+    rl.complete_from(&db);
+
+    println!(
+        "{} entries loaded. Type help for the commands.",
+        db.entries.len()
+    );
+
+    loop {
+        // Adding an entry leaves Tab completing something else.
+        rl.completing(Complete::Kanji);
+
+        // Ctrl-D or Ctrl-C ends the session, like `quit`.
+        let line = match rl.line("kin> ") {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let result = match words[..] {
+            [] => Ok(()),
+            ["quit" | "exit" | "q"] => break,
+            ["help" | "?"] => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ["show", ref args @ ..] => run(args, |s: Show| crate::show_of(&db, s, false)),
+            ["tree", ref args @ ..] => run(args, |t: Tree| crate::tree_of(&db, t)),
+            ["reading", ref args @ ..] => run(args, |r: Reading| crate::reading_of(&db, r, false)),
+            ["add" | "new", ref args @ ..] => run(args, |n: New| {
+                let result = crate::add_entry(path, &mut db, config, n, &mut rl);
+                rl.complete_from(&db);
+                result
+            }),
+            [cmd, ..] => Err(Error::Parse(format!("Unknown command: {}", cmd))),
+        };
+
+        // A mistake shouldn't cost the whole session.
+        if let Err(e) = result {
+            println!("{}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the arguments of a command and run it, or print its usage if help was
+/// asked for.
+fn run<T, F>(args: &[&str], f: F) -> Result<(), Error>
+where
+    T: Options,
+    F: FnOnce(T) -> Result<(), Error>,
+{
+    let opts =
+        T::parse_args(args, ParsingStyle::AllOptions).map_err(|e| Error::Parse(e.to_string()))?;
+
+    match opts.help_requested() {
+        true => {
+            println!("{}", T::usage());
+            Ok(())
+        }
+        false => f(opts),
+    }
+}
@@ -0,0 +1,234 @@
+//! A tiny HTTP server for browsing the Kanji Graph.
+//!
+//! The viewer's assets are baked into the binary, so `kin serve --ui` needs no
+//! other files to work. GraphQL queries are answered at `/graphql`, by GET with
+//! a `query` parameter or by POST with a JSON body.
+
+use crate::graphql::{self, Context, Schema};
+use crate::Error;
+use juniper::http::GraphQLRequest;
+use kn_core::{Kanji, DB};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+const INDEX_HTML: &str = include_str!("../assets/index.html");
+pub(crate) const APP_JS: &str = include_str!("../assets/app.js");
+pub(crate) const STYLE_CSS: &str = include_str!("../assets/style.css");
+
+/// The largest request body accepted, far more than any sensible query needs.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// How long a client may take to send its request, or to accept our response.
+/// Requests are answered one at a time, so a stalled client holds up all the
+/// others until then.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A response to send back to the browser.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: String) -> Response {
+        Response {
+            status: "200 OK",
+            content_type,
+            body,
+        }
+    }
+
+    fn bad_request(msg: String) -> Response {
+        Response {
+            status: "400 Bad Request",
+            content_type: "text/plain; charset=utf-8",
+            body: msg,
+        }
+    }
+
+    fn too_large() -> Response {
+        Response {
+            status: "413 Payload Too Large",
+            content_type: "text/plain; charset=utf-8",
+            body: format!("Request bodies are limited to {} bytes.", MAX_BODY),
+        }
+    }
+
+    fn not_found() -> Response {
+        Response {
+            status: "404 Not Found",
+            content_type: "text/plain; charset=utf-8",
+            body: "Not found.".to_string(),
+        }
+    }
+}
+
+/// Serve the database until the process is killed.
+pub fn serve(db: DB, port: u16, ui: bool) -> Result<(), Error> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(Error::Io)?;
+    println!("Serving on http://127.0.0.1:{}/", port);
+
+    let context = Context { db };
+    let schema = graphql::schema();
+
+    for stream in listener.incoming() {
+        // A single bad connection shouldn't bring down the server.
+        let handled = stream
+            .map_err(Error::Io)
+            .and_then(|s| handle(&context, &schema, ui, s));
+        if let Err(e) = handled {
+            eprintln!("{}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(
+    context: &Context,
+    schema: &Schema,
+    ui: bool,
+    mut stream: TcpStream,
+) -> Result<(), Error> {
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(Error::Io)?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(Error::Io)?;
+
+    // Only the length of any body matters among the headers. It's checked
+    // before anything is allocated for it.
+    let mut length = Ok(0);
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(Error::Io)?;
+        match header.trim_end().split_once(':') {
+            Some((k, v)) if k.eq_ignore_ascii_case("content-length") => {
+                length = match v.trim().parse() {
+                    Ok(n) if n > MAX_BODY => Err(Response::too_large()),
+                    Ok(n) => Ok(n),
+                    Err(_) => Err(Response::bad_request(format!(
+                        "Bad Content-Length: {}",
+                        v.trim()
+                    ))),
+                };
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    let response = match length {
+        Err(response) => response,
+        Ok(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).map_err(Error::Io)?;
+
+            // e.g. GET /family?k=%E8%AA%9E HTTP/1.1
+            let target = line.split_whitespace().nth(1).unwrap_or("/");
+            let (path, query) = target.split_once('?').unwrap_or((target, ""));
+            match path {
+                "/graphql" => graphql(context, schema, query, &body)?,
+                _ => route(&context.db, ui, path, query)?,
+            }
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+    .map_err(Error::Io)
+}
+
+fn route(db: &DB, ui: bool, path: &str, query: &str) -> Result<Response, Error> {
+    let response = match path {
+        "/" if ui => Response::ok("text/html; charset=utf-8", INDEX_HTML.to_string()),
+        "/app.js" if ui => Response::ok("text/javascript", APP_JS.to_string()),
+        "/style.css" if ui => Response::ok("text/css", STYLE_CSS.to_string()),
+        "/graph.json" => json(&db.node_link())?,
+        "/family" => {
+            let ks: Vec<Kanji> = param(query, "k")
+                .map(|k| k.chars().filter_map(Kanji::new).collect())
+                .unwrap_or_default();
+
+            if ks.iter().any(|k| db.entries.contains_key(k)) {
+                json(&db.node_link_of(&db.filtered_graph(ks, None)))?
+            } else {
+                Response::not_found()
+            }
+        }
+        _ => Response::not_found(),
+    };
+
+    Ok(response)
+}
+
+/// Answer a GraphQL query, given either in the URL or as a JSON body.
+fn graphql(
+    context: &Context,
+    schema: &Schema,
+    query: &str,
+    body: &[u8],
+) -> Result<Response, Error> {
+    let request = match param(query, "query") {
+        Some(q) => GraphQLRequest::new(q, None, None),
+        None => match serde_json::from_slice(body) {
+            Ok(r) => r,
+            Err(e) => return Ok(Response::bad_request(format!("Bad GraphQL request: {}", e))),
+        },
+    };
+
+    json(&request.execute_sync(schema, context))
+}
+
+fn json<T: serde::Serialize>(value: &T) -> Result<Response, Error> {
+    let body = serde_json::to_string(value).map_err(Error::Json)?;
+    Ok(Response::ok("application/json", body))
+}
+
+/// The decoded value of some query parameter.
+fn param(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| percent_decode(v))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
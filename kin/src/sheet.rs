@@ -0,0 +1,194 @@
+//! Entries as rows of a spreadsheet, for bulk editing.
+//!
+//! Every row has `kanji`, `oya`, `onyomi`, and `daihyou` columns, with several
+//! values in one cell separated by spaces. The other fields of an `Entry` have
+//! no column, and importing leaves them as they were, except that pinned edges
+//! to parents no longer listed are dropped.
+
+use crate::Error;
+use kn_core::{Entry, Kanji};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// One `Entry`, flattened.
+#[derive(Serialize, Deserialize)]
+pub struct Row {
+    pub kanji: String,
+    #[serde(default)]
+    pub oya: String,
+    #[serde(default)]
+    pub onyomi: String,
+    #[serde(default)]
+    pub daihyou: String,
+}
+
+impl Row {
+    fn new(e: &Entry) -> Row {
+        let words = |ws: &[String]| ws.join(" ");
+        let oya: Vec<String> = e.oya.iter().map(|k| k.to_string()).collect();
+
+        Row {
+            kanji: e.kanji.to_string(),
+            oya: words(&oya),
+            onyomi: crate::onyomi_words(&e.onyomi, " "),
+            daihyou: words(&e.daihyou),
+        }
+    }
+}
+
+/// Write entries as rows, by order of their `Kanji`.
+pub fn write<W: Write>(out: W, delimiter: u8, entries: &[Entry]) -> Result<(), Error> {
+    let mut entries: Vec<&Entry> = entries.iter().collect();
+    entries.sort_by_key(|e| e.kanji);
+
+    let mut w = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(out);
+
+    for e in entries {
+        w.serialize(Row::new(e)).map_err(Error::Csv)?;
+    }
+
+    w.flush().map_err(Error::Io)
+}
+
+/// Read rows, as written by `write` or saved from a spreadsheet.
+pub fn read<R: Read>(input: R, delimiter: u8) -> Result<Vec<Row>, Error> {
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(input)
+        .deserialize()
+        .collect::<Result<_, _>>()
+        .map_err(Error::Csv)
+}
+
+/// Bring a row into the entries, either updating the `Entry` of its `Kanji` or
+/// adding a new one. Yields the `Kanji`.
+pub fn apply(entries: &mut HashMap<Kanji, Entry>, row: Row) -> Result<Kanji, Error> {
+    let mut chars = row.kanji.chars();
+    let kanji = match (chars.next().and_then(Kanji::new), chars.next()) {
+        (Some(k), None) => k,
+        _ => Err(Error::NotKanji(row.kanji.clone()))?,
+    };
+
+    let entry = entries.entry(kanji).or_insert_with(|| Entry::new(kanji));
+
+    entry.oya = crate::strict_kanji(&[row.oya])?;
+    // A pinned edge goes with its parent.
+    let oya = &entry.oya;
+    entry.oya_overrides.retain(|o, _| oya.contains(o));
+    entry.onyomi = row
+        .onyomi
+        .split_whitespace()
+        .map(crate::onyomi_from_str)
+        .collect();
+    entry.daihyou = row
+        .daihyou
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect();
+
+    Ok(kanji)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kn_core::{Inherit, Onyomi, Stratum};
+
+    fn entry(k: char, oya: &str, onyomi: Vec<Onyomi>, daihyou: &[&str]) -> Entry {
+        Entry {
+            oya: oya.chars().filter_map(Kanji::new).collect(),
+            onyomi,
+            daihyou: daihyou.iter().map(|s| s.to_string()).collect(),
+            ..Entry::new(Kanji::new(k).unwrap())
+        }
+    }
+
+    fn entries() -> Vec<Entry> {
+        let tagged = |reading: &str, stratum| Onyomi {
+            reading: reading.into(),
+            stratum: Some(stratum),
+        };
+
+        vec![
+            entry(
+                '校',
+                "木交",
+                vec![
+                    tagged("こう", Stratum::Kanon),
+                    tagged("きょう", Stratum::Goon),
+                ],
+                &["学校(がっこう)", "校正(こうせい)"],
+            ),
+            entry('木', "", vec!["もく".into(), "ぼく".into()], &[]),
+            entry('畑', "火田", vec![], &[]),
+        ]
+    }
+
+    fn round_trip(delimiter: u8) {
+        let original = entries();
+        let mut out = Vec::new();
+        write(&mut out, delimiter, &original).unwrap();
+
+        let mut applied = HashMap::new();
+        for row in read(out.as_slice(), delimiter).unwrap() {
+            apply(&mut applied, row).unwrap();
+        }
+
+        assert_eq!(applied.len(), original.len());
+        for e in original.iter() {
+            assert_eq!(applied.get(&e.kanji), Some(e));
+        }
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        round_trip(b',');
+    }
+
+    #[test]
+    fn tsv_round_trip() {
+        round_trip(b'\t');
+    }
+
+    #[test]
+    fn empty_cells() {
+        let input = "kanji,oya,onyomi,daihyou\n畑,,,\n";
+        let mut applied = HashMap::new();
+        for row in read(input.as_bytes(), b',').unwrap() {
+            apply(&mut applied, row).unwrap();
+        }
+
+        assert_eq!(applied.values().next(), Some(&entry('畑', "", vec![], &[])));
+    }
+
+    #[test]
+    fn dropped_parents_lose_their_pins() {
+        let mut applied = HashMap::new();
+        let e = Entry {
+            oya_overrides: [('木', Inherit::Same), ('交', Inherit::Differ)]
+                .into_iter()
+                .map(|(k, i)| (Kanji::new(k).unwrap(), i))
+                .collect(),
+            ..entry('校', "木交", vec!["こう".into()], &[])
+        };
+        applied.insert(e.kanji, e);
+
+        let input = "kanji,oya,onyomi,daihyou\n校,木,こう,\n";
+        for row in read(input.as_bytes(), b',').unwrap() {
+            apply(&mut applied, row).unwrap();
+        }
+
+        let overrides: Vec<_> = applied
+            .values()
+            .next()
+            .unwrap()
+            .oya_overrides
+            .keys()
+            .collect();
+        assert_eq!(overrides, vec![&Kanji::new('木').unwrap()]);
+    }
+}
@@ -0,0 +1,180 @@
+//! A static website of the whole database.
+//!
+//! Every Kanji gets its own page with its entry and an interactive graph of
+//! its family, linked to the pages of its parents and children. The pages
+//! share the viewer of `kin serve`, but need no server to browse.
+
+use crate::serve::{APP_JS, STYLE_CSS};
+use crate::Error;
+use kn_core::{Direction, Kanji, Level, DB};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Write the whole site into the given directory, with each family graph
+/// reaching `depth` generations up and down.
+pub fn site(db: &DB, out: &Path, depth: usize) -> Result<(), Error> {
+    std::fs::create_dir_all(out).map_err(Error::Io)?;
+    std::fs::write(out.join("app.js"), APP_JS).map_err(Error::Io)?;
+    std::fs::write(out.join("style.css"), STYLE_CSS).map_err(Error::Io)?;
+
+    let levels = db.levels();
+    let mut ks: Vec<Kanji> = db.entries.keys().copied().collect();
+    ks.sort();
+
+    for k in ks.iter() {
+        let html = page(db, &levels, *k, depth)?;
+        std::fs::write(out.join(format!("{}.html", k)), html).map_err(Error::Io)?;
+    }
+
+    std::fs::write(out.join("index.html"), index(&ks)).map_err(Error::Io)
+}
+
+/// Links to every page.
+fn index(ks: &[Kanji]) -> String {
+    let mut body = String::new();
+    ks.iter().for_each(|k| {
+        let _ = writeln!(body, "<a href=\"{}\">{}</a>", href(*k), k);
+    });
+
+    let mut s = head("KanjiNet");
+    let _ = writeln!(s, "<main class=\"index\">\n{}</main>", body);
+    s.push_str("</body>\n</html>\n");
+    s
+}
+
+/// A page of quiz results, with the families of every Kanji asked about. The
+/// viewer is written next to it, so that it opens as is, or as part of a site.
+pub fn quiz(db: &DB, out: &Path, results: &[(Kanji, bool)]) -> Result<(), Error> {
+    let dir = out.parent().unwrap_or(Path::new(""));
+    std::fs::write(dir.join("app.js"), APP_JS).map_err(Error::Io)?;
+    std::fs::write(dir.join("style.css"), STYLE_CSS).map_err(Error::Io)?;
+
+    let mut rows = String::new();
+    results.iter().for_each(|(k, correct)| {
+        let onyomi = db
+            .entries
+            .get(k)
+            .map(|e| escape(&crate::onyomi_words(&e.onyomi, "、")))
+            .unwrap_or_default();
+        let mark = if *correct { "○" } else { "✗" };
+        let label = format!("<a href=\"{}\">{}</a>", href(*k), k);
+        row(&mut rows, &label, &format!("{} {}", mark, onyomi));
+    });
+
+    let score = results.iter().filter(|(_, correct)| *correct).count();
+    let mut s = head("小テスト");
+    let _ = writeln!(
+        s,
+        "<section class=\"entry\">\n<h2>{}/{}</h2>\n<dl>\n{}</dl>\n</section>",
+        score,
+        results.len(),
+        rows
+    );
+    let ks = results.iter().map(|(k, _)| *k).collect();
+    s.push_str(&graph(db, ks, 1, "")?);
+    s.push_str("</body>\n</html>\n");
+
+    std::fs::write(out, s).map_err(Error::Io)
+}
+
+/// The viewer, showing the family of the given Kanji.
+fn graph(db: &DB, ks: Vec<Kanji>, depth: usize, chosen: &str) -> Result<String, Error> {
+    let family = db.node_link_of(&db.filtered_graph(ks, Some(depth)));
+    // Guard against any `</script>` lurking in the data.
+    let json = serde_json::to_string(&family)
+        .map_err(Error::Json)?
+        .replace("</", "<\\/");
+
+    let mut s = String::new();
+    s.push_str("<svg id=\"graph\"></svg>\n");
+    let _ = writeln!(
+        s,
+        "<script>window.FAMILY = {}; window.CHOSEN = \"{}\";</script>",
+        json, chosen
+    );
+    s.push_str("<script src=\"app.js\"></script>\n");
+    Ok(s)
+}
+
+/// The page of a single Kanji.
+fn page(db: &DB, levels: &HashMap<Kanji, Level>, k: Kanji, depth: usize) -> Result<String, Error> {
+    let mut rows = String::new();
+    if let Some(e) = db.entries.get(&k) {
+        row(
+            &mut rows,
+            "音読み",
+            &escape(&crate::onyomi_words(&e.onyomi, "、")),
+        );
+        row(&mut rows, "代表", &escape(&e.daihyou.join("、")));
+        let notes: Vec<String> = e.notes.iter().map(|n| escape(n)).collect();
+        row(&mut rows, "備考", &notes.join("<br/>"));
+        row(&mut rows, "タグ", &escape(&e.tags.join("、")));
+        let variants: String = e.variants.iter().map(|k| k.get()).collect();
+        row(&mut rows, "異体字", &variants);
+        let nite: String = e.nite.iter().map(|k| k.get()).collect();
+        row(&mut rows, "形近字", &nite);
+    }
+    if let Some(l) = levels.get(&k) {
+        row(&mut rows, "級", &l.to_string());
+    }
+    row(&mut rows, "親", &links(db, k, Direction::Incoming));
+    row(&mut rows, "子", &links(db, k, Direction::Outgoing));
+
+    let mut s = head(&k.to_string());
+    let _ = writeln!(
+        s,
+        "<section class=\"entry\">\n<h2>{}</h2>\n<dl>\n{}</dl>\n</section>",
+        k, rows
+    );
+    s.push_str(&graph(db, vec![k], depth, &k.to_string())?);
+    s.push_str("</body>\n</html>\n");
+    Ok(s)
+}
+
+/// Everything up to and including the page header.
+fn head(title: &str) -> String {
+    let mut s = String::new();
+    s.push_str("<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n");
+    s.push_str("<meta charset=\"utf-8\">\n");
+    let _ = writeln!(s, "<title>{}</title>", title);
+    s.push_str("<link rel=\"stylesheet\" href=\"style.css\">\n");
+    s.push_str("</head>\n<body>\n");
+    s.push_str("<header><h1><a href=\"index.html\">KanjiNet</a></h1></header>\n");
+    s
+}
+
+fn row(s: &mut String, label: &str, value: &str) {
+    if !value.is_empty() {
+        let _ = writeln!(s, "<dt>{}</dt><dd>{}</dd>", label, value);
+    }
+}
+
+/// Links to the pages of the parents or children of a Kanji.
+fn links(db: &DB, k: Kanji, dir: Direction) -> String {
+    let mut relatives: Vec<Kanji> = db
+        .index
+        .get(&k)
+        .into_iter()
+        .flat_map(|kix| db.neighbours(*kix, dir))
+        .filter_map(|(rix, _)| db.graph.node_weight(rix).copied())
+        .collect();
+    relatives.sort();
+
+    relatives
+        .iter()
+        .map(|r| format!("<a href=\"{}\">{}</a>", href(*r), r))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn href(k: Kanji) -> String {
+    format!("{}.html", k)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,61 @@
+//! An opt-in ledger of which commands are run and which Kanji they're run on.
+//!
+//! Nothing here ever leaves the machine. The ledger sits next to the data file
+//! and is only written when `usage = true` is set in the config file.
+
+use crate::Error;
+use kn_core::Kanji;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the ledger file, kept next to the data file.
+pub const USAGE_FILE: &str = "usage.json";
+
+/// Running tallies of what has been consulted.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Usage {
+    /// How often each command has been run.
+    #[serde(default)]
+    pub commands: BTreeMap<String, u32>,
+    /// How often each Kanji has been asked about.
+    #[serde(default)]
+    pub kanji: BTreeMap<Kanji, u32>,
+}
+
+impl Usage {
+    /// Count one run of a command on the given Kanji.
+    pub fn record(&mut self, command: &str, ks: &[Kanji]) {
+        *self.commands.entry(command.to_string()).or_default() += 1;
+        ks.iter()
+            .for_each(|k| *self.kanji.entry(*k).or_default() += 1);
+    }
+
+    /// Consulted Kanji, most consulted first.
+    pub fn most_consulted(&self) -> Vec<(Kanji, u32)> {
+        let mut ks: Vec<_> = self.kanji.iter().map(|(k, n)| (*k, *n)).collect();
+        ks.sort_by_key(|(k, n)| (std::cmp::Reverse(*n), *k));
+        ks
+    }
+}
+
+/// The location of the ledger that accompanies a data file.
+pub fn usage_path(data: &Path) -> PathBuf {
+    data.with_file_name(USAGE_FILE)
+}
+
+/// Read the ledger, yielding an empty one if none exists yet.
+pub fn read_usage(path: &Path) -> Result<Usage, Error> {
+    match fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(Error::Json),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Usage::default()),
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
+/// Write the ledger.
+pub fn write_usage(path: &Path, usage: &Usage) -> Result<(), Error> {
+    let raw = serde_json::to_string_pretty(usage).map_err(Error::Json)?;
+    fs::write(path, raw).map_err(Error::Io)
+}
@@ -0,0 +1,90 @@
+//! Pulling study progress out of WaniKani.
+//!
+//! WaniKani knows a Kanji by a numeric subject ID, so the Kanji subjects are
+//! fetched first to learn which character each ID stands for, then the user's
+//! assignments to learn the SRS stage of each. Both are paginated.
+
+use crate::Error;
+use kn_core::Kanji;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+const API: &str = "https://api.wanikani.com/v2";
+
+/// The version of the API that these types follow.
+const REVISION: &str = "20170710";
+
+#[derive(Deserialize)]
+struct Collection<T> {
+    data: Vec<Resource<T>>,
+    pages: Pages,
+}
+
+#[derive(Deserialize)]
+struct Pages {
+    next_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Resource<T> {
+    id: u64,
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Subject {
+    characters: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Assignment {
+    subject_id: u64,
+    srs_stage: u8,
+}
+
+/// The SRS stage of every Kanji the user has started on WaniKani.
+pub fn srs_stages(token: &str) -> Result<BTreeMap<Kanji, u8>, Error> {
+    let subjects: HashMap<u64, Kanji> = fetch_all::<Subject>(token, "subjects?types=kanji")?
+        .into_iter()
+        .filter_map(|r| {
+            let characters = r.data.characters?;
+            let mut chars = characters.chars();
+            match (chars.next().and_then(Kanji::new), chars.next()) {
+                (Some(k), None) => Some((r.id, k)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let stages = fetch_all::<Assignment>(token, "assignments?subject_types=kanji")?
+        .into_iter()
+        .filter_map(|r| {
+            let k = subjects.get(&r.data.subject_id)?;
+            Some((*k, r.data.srs_stage))
+        })
+        .collect();
+
+    Ok(stages)
+}
+
+/// Every resource of a collection, following its pages to the end.
+fn fetch_all<T: DeserializeOwned>(token: &str, query: &str) -> Result<Vec<Resource<T>>, Error> {
+    let mut all = Vec::new();
+    let mut next = Some(format!("{}/{}", API, query));
+
+    while let Some(url) = next {
+        let page: Collection<T> = ureq::get(&url)
+            .set("Authorization", &format!("Bearer {}", token))
+            .set("Wanikani-Revision", REVISION)
+            .call()
+            .map_err(|e| Error::Http(Box::new(e)))?
+            .into_json()
+            .map_err(Error::Io)?;
+
+        all.extend(page.data);
+        next = page.pages.next_url;
+    }
+
+    Ok(all)
+}
@@ -0,0 +1,103 @@
+//! A binary cache of the fully built `DB`, kept beside the data file.
+//!
+//! Parsing the JSON and classifying every edge takes far longer than reading
+//! back a `DB` that was already built. The cache remembers the modification
+//! time and size of the data file it was built from, and is quietly ignored
+//! (and later replaced) as soon as either changes.
+
+use crate::{Entry, Error, KGraph, Kanji, Rules, DB};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Bumped whenever the layout of the cache changes, or edges come to be
+/// classified differently, so that old caches are ignored rather than misread.
+const VERSION: u32 = 2;
+
+/// What a data file looked like when a cache was built from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+#[derive(Serialize)]
+struct CacheRef<'a> {
+    version: u32,
+    stamp: Stamp,
+    rules: Rules,
+    entries: &'a HashMap<Kanji, Entry>,
+    graph: &'a KGraph,
+}
+
+#[derive(Deserialize)]
+struct Cache {
+    version: u32,
+    stamp: Stamp,
+    rules: Rules,
+    entries: HashMap<Kanji, Entry>,
+    graph: KGraph,
+}
+
+/// Where the cache of a given data file lives.
+pub fn cache_path(data: &Path) -> PathBuf {
+    let mut name = data.file_name().unwrap_or_default().to_os_string();
+    name.push(".cache");
+    data.with_file_name(name)
+}
+
+/// The current state of a data file, if it exists.
+pub(crate) fn stamp(data: &Path) -> Option<Stamp> {
+    let meta = fs::metadata(data).ok()?;
+    let modified = meta.modified().ok()?;
+
+    Some(Stamp {
+        modified,
+        len: meta.len(),
+    })
+}
+
+/// The cached `DB` of a data file, as long as the cache is still fresh and was
+/// built with the same rules.
+pub(crate) fn load(data: &Path, rules: Rules) -> Option<DB> {
+    let now = stamp(data)?;
+    let bytes = fs::read(cache_path(data)).ok()?;
+    let cache: Cache = rmp_serde::from_slice(&bytes).ok()?;
+
+    let fresh = cache.version == VERSION && cache.stamp == now && cache.rules == rules;
+    fresh.then(|| DB::with_graph(cache.entries, cache.graph))
+}
+
+/// Cache a `DB` that was built from a data file in the given state.
+pub(crate) fn store(data: &Path, stamp: Stamp, rules: Rules, db: &DB) -> Result<(), Error> {
+    let cache = CacheRef {
+        version: VERSION,
+        stamp,
+        rules,
+        entries: &db.entries,
+        graph: &db.graph,
+    };
+    // Named fields, since `Entry` skips empty ones when serializing.
+    let bytes = rmp_serde::to_vec_named(&cache).map_err(Error::Cache)?;
+    fs::write(cache_path(data), bytes).map_err(Error::Io)
+}
+
+/// Throw away any existing cache and build a fresh one from the data file,
+/// classifying edges by the given `Rules`.
+pub fn rebuild(data: &Path, rules: Rules) -> Result<DB, Error> {
+    match fs::remove_file(cache_path(data)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(Error::Io(e)),
+        _ => {}
+    }
+
+    let stamp = stamp(data);
+    let db = crate::json::build_db(data, rules)?;
+
+    if let Some(s) = stamp {
+        store(data, s, rules, &db)?;
+    }
+
+    Ok(db)
+}
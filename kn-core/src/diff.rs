@@ -0,0 +1,208 @@
+//! Comparing two versions of a database, entry by entry and edge by edge.
+
+use crate::{Entry, Inherit, Kanji, Onyomi, DB};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A field of an entry that differs between versions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Change<T> {
+    pub kanji: Kanji,
+    pub old: T,
+    pub new: T,
+}
+
+/// An edge that was added, removed, or reclassified.
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeChange {
+    pub oya: Kanji,
+    pub child: Kanji,
+    /// Missing if the edge is new.
+    pub old: Option<Inherit>,
+    /// Missing if the edge is gone.
+    pub new: Option<Inherit>,
+}
+
+/// Everything that differs between two versions of a database, each part in
+/// order.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diff {
+    pub added: Vec<Kanji>,
+    pub removed: Vec<Kanji>,
+    pub readings: Vec<Change<Vec<Onyomi>>>,
+    pub parents: Vec<Change<Vec<Kanji>>>,
+    pub edges: Vec<EdgeChange>,
+}
+
+impl Diff {
+    /// Are the two versions the same, as far as a `Diff` can tell?
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.readings.is_empty()
+            && self.parents.is_empty()
+            && self.edges.is_empty()
+    }
+}
+
+impl DB {
+    /// What changed to get from this database to a `new` one. Edges are
+    /// compared as computed, so a change in readings can change the edges of
+    /// entries that weren't touched themselves.
+    pub fn diff(&self, new: &DB) -> Diff {
+        let old_summary = self.summary();
+        let new_summary = new.summary();
+
+        let added = new_summary
+            .kanji
+            .iter()
+            .filter(|k| !self.entries.contains_key(k))
+            .copied()
+            .collect();
+        let removed = old_summary
+            .kanji
+            .iter()
+            .filter(|k| !new.entries.contains_key(k))
+            .copied()
+            .collect();
+
+        let both: Vec<(Kanji, &Entry, &Entry)> = old_summary
+            .kanji
+            .iter()
+            .filter_map(|k| Some((*k, self.entries.get(k)?, new.entries.get(k)?)))
+            .collect();
+        let readings = both
+            .iter()
+            .filter(|(_, a, b)| a.onyomi != b.onyomi)
+            .map(|(kanji, a, b)| Change {
+                kanji: *kanji,
+                old: a.onyomi.clone(),
+                new: b.onyomi.clone(),
+            })
+            .collect();
+        let parents = both
+            .iter()
+            .filter(|(_, a, b)| a.oya != b.oya)
+            .map(|(kanji, a, b)| Change {
+                kanji: *kanji,
+                old: a.oya.clone(),
+                new: b.oya.clone(),
+            })
+            .collect();
+
+        let mut edges: BTreeMap<(Kanji, Kanji), (Option<Inherit>, Option<Inherit>)> =
+            BTreeMap::new();
+        old_summary
+            .edges
+            .into_iter()
+            .for_each(|(o, c, i)| edges.entry((o, c)).or_default().0 = Some(i));
+        new_summary
+            .edges
+            .into_iter()
+            .for_each(|(o, c, i)| edges.entry((o, c)).or_default().1 = Some(i));
+        let edges = edges
+            .into_iter()
+            .filter(|(_, (old, new))| old != new)
+            .map(|((oya, child), (old, new))| EdgeChange {
+                oya,
+                child,
+                old,
+                new,
+            })
+            .collect();
+
+        Diff {
+            added,
+            removed,
+            readings,
+            parents,
+            edges,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn k(c: char) -> Kanji {
+        Kanji::new(c).unwrap()
+    }
+
+    fn db(entries: &[(char, &str, &[&str])]) -> DB {
+        let entries = entries
+            .iter()
+            .map(|(c, oya, onyomi)| {
+                let e = Entry {
+                    oya: oya.chars().map(k).collect(),
+                    onyomi: onyomi.iter().map(|y| Onyomi::from(*y)).collect(),
+                    ..Entry::new(k(*c))
+                };
+                (e.kanji, e)
+            })
+            .collect();
+        DB::new(entries).unwrap()
+    }
+
+    #[test]
+    fn unchanged() {
+        let old = db(&[('工', "", &["こう"]), ('江', "工", &["こう"])]);
+        let new = db(&[('工', "", &["こう"]), ('江', "工", &["こう"])]);
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn changes() {
+        let old = db(&[
+            ('工', "", &["こう"]),
+            ('江', "工", &["こう"]),
+            ('水', "", &["すい"]),
+            ('紅', "工", &["こう"]),
+        ]);
+        // 工's new reading changes the edge to 江, which itself is untouched.
+        let new = db(&[
+            ('工', "", &["く"]),
+            ('江', "工", &["こう"]),
+            ('紅', "糸", &["こう"]),
+            ('糸', "", &["し"]),
+        ]);
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.added, vec![k('糸')]);
+        assert_eq!(diff.removed, vec![k('水')]);
+        let readings: Vec<(Kanji, &[Onyomi], &[Onyomi])> = diff
+            .readings
+            .iter()
+            .map(|c| (c.kanji, c.old.as_slice(), c.new.as_slice()))
+            .collect();
+        assert_eq!(
+            readings,
+            vec![(
+                k('工'),
+                &[Onyomi::from("こう")][..],
+                &[Onyomi::from("く")][..]
+            )]
+        );
+        let parents: Vec<(Kanji, &[Kanji], &[Kanji])> = diff
+            .parents
+            .iter()
+            .map(|c| (c.kanji, c.old.as_slice(), c.new.as_slice()))
+            .collect();
+        assert_eq!(parents, vec![(k('紅'), &[k('工')][..], &[k('糸')][..])]);
+        let edges: Vec<(Kanji, Kanji, Option<Inherit>, Option<Inherit>)> = diff
+            .edges
+            .iter()
+            .map(|e| (e.oya, e.child, e.old, e.new))
+            .collect();
+        let differ = Inherit::classify(&new.entries[&k('江')], &new.entries[&k('工')]);
+        assert_ne!(differ, Inherit::Same);
+        assert_eq!(
+            edges,
+            vec![
+                (k('工'), k('江'), Some(Inherit::Same), Some(differ)),
+                (k('工'), k('紅'), Some(Inherit::Same), None),
+                (k('糸'), k('紅'), None, Some(Inherit::Differ)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Exporting the Kanji graph into formats understood by other tools.
+
+#[cfg(feature = "json")]
+use crate::Error;
+use crate::{Inherit, KGraph, Kanji, Onyomi, DB};
+use itertools::Itertools;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// The formats the whole graph can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphFormat {
+    /// Node-link JSON, as used by d3 and NetworkX.
+    NodeLink,
+    /// Plain JSON lists of nodes and of edges between Kanji.
+    JsonGraph,
+    /// GEXF, as used by Gephi.
+    Gexf,
+    /// Cytoscape's JSON elements.
+    Cytoscape,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "node-link" => Ok(GraphFormat::NodeLink),
+            "json-graph" => Ok(GraphFormat::JsonGraph),
+            "gexf" => Ok(GraphFormat::Gexf),
+            "cytoscape" => Ok(GraphFormat::Cytoscape),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+/// The graph in "node-link" form, as used by d3-force and NetworkX's
+/// `node_link_data`.
+#[derive(Serialize)]
+pub struct NodeLink {
+    pub directed: bool,
+    pub multigraph: bool,
+    pub nodes: Vec<Node>,
+    pub links: Vec<Link>,
+}
+
+/// A single `Kanji` in a `NodeLink` graph.
+#[derive(Serialize)]
+pub struct Node {
+    pub id: usize,
+    pub kanji: Kanji,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub onyomi: Vec<Onyomi>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// A parent-child relationship in a `NodeLink` graph.
+#[derive(Serialize)]
+pub struct Link {
+    pub source: usize,
+    pub target: usize,
+    pub inherit: Inherit,
+}
+
+/// The graph as plain lists of nodes and edges, with edges naming their ends
+/// by `Kanji` rather than by index.
+#[derive(Serialize)]
+pub struct JsonGraph {
+    pub nodes: Vec<JsonNode>,
+    pub edges: Vec<JsonEdge>,
+}
+
+/// A single `Kanji` in a `JsonGraph`.
+#[derive(Serialize)]
+pub struct JsonNode {
+    pub kanji: Kanji,
+    pub onyomi: Vec<Onyomi>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+}
+
+/// A parent-child relationship in a `JsonGraph`.
+#[derive(Serialize)]
+pub struct JsonEdge {
+    pub from: Kanji,
+    pub to: Kanji,
+    pub inherit: Inherit,
+}
+
+/// The graph as Cytoscape expects to find it, under `elements`.
+#[derive(Serialize)]
+pub struct Cytoscape {
+    pub elements: Elements,
+}
+
+/// The nodes and edges of a `Cytoscape` graph.
+#[derive(Serialize)]
+pub struct Elements {
+    pub nodes: Vec<Element<CyNode>>,
+    pub edges: Vec<Element<CyEdge>>,
+}
+
+/// Cytoscape keeps the attributes of each element under `data`.
+#[derive(Serialize)]
+pub struct Element<T> {
+    pub data: T,
+}
+
+/// A single `Kanji` in a `Cytoscape` graph.
+#[derive(Serialize)]
+pub struct CyNode {
+    pub id: Kanji,
+    #[serde(flatten)]
+    pub node: JsonNode,
+}
+
+/// A parent-child relationship in a `Cytoscape` graph.
+#[derive(Serialize)]
+pub struct CyEdge {
+    pub id: String,
+    pub source: Kanji,
+    pub target: Kanji,
+    pub inherit: Inherit,
+}
+
+impl DB {
+    /// Write the entire graph in the given format.
+    #[cfg(feature = "json")]
+    pub fn export<W: std::io::Write>(&self, format: GraphFormat, mut w: W) -> Result<(), Error> {
+        match format {
+            GraphFormat::NodeLink => {
+                serde_json::to_writer(w, &self.node_link()).map_err(Error::Json)
+            }
+            GraphFormat::JsonGraph => {
+                serde_json::to_writer(w, &self.to_json_graph()).map_err(Error::Json)
+            }
+            GraphFormat::Cytoscape => {
+                serde_json::to_writer(w, &self.to_cytoscape()).map_err(Error::Json)
+            }
+            GraphFormat::Gexf => w.write_all(self.to_gexf().as_bytes()).map_err(Error::Io),
+        }
+    }
+
+    /// The entire graph in node-link form.
+    pub fn node_link(&self) -> NodeLink {
+        self.node_link_of(&self.graph)
+    }
+
+    /// Same as `node_link`, but supply your own graph to consider.
+    pub fn node_link_of(&self, graph: &KGraph) -> NodeLink {
+        let levels = self.levels();
+
+        let nodes = graph
+            .node_indices()
+            .filter_map(|nix| {
+                let e = graph.node_weight(nix).and_then(|k| self.entries.get(k))?;
+                Some((nix, e))
+            })
+            .map(|(nix, e)| Node {
+                id: nix.index(),
+                kanji: e.kanji,
+                level: levels.get(&e.kanji).map(|l| l.to_string()),
+                onyomi: e.onyomi.clone(),
+                notes: e.notes.clone(),
+            })
+            .collect();
+
+        let links = graph
+            .raw_edges()
+            .iter()
+            .map(|e| Link {
+                source: e.source().index(),
+                target: e.target().index(),
+                inherit: e.weight,
+            })
+            .collect();
+
+        NodeLink {
+            directed: true,
+            multigraph: false,
+            nodes,
+            links,
+        }
+    }
+
+    /// The entire graph as lists of nodes and edges, in `Kanji` order.
+    pub fn to_json_graph(&self) -> JsonGraph {
+        self.to_json_graph_of(&self.graph)
+    }
+
+    /// Same as `to_json_graph`, but supply your own graph to consider.
+    pub fn to_json_graph_of(&self, graph: &KGraph) -> JsonGraph {
+        let levels = self.levels();
+
+        let mut nodes: Vec<JsonNode> = graph
+            .node_weights()
+            .filter_map(|k| self.entries.get(k))
+            .map(|e| JsonNode {
+                kanji: e.kanji,
+                onyomi: e.onyomi.clone(),
+                level: levels.get(&e.kanji).map(|l| l.to_string()),
+            })
+            .collect();
+        nodes.sort_by_key(|n| n.kanji);
+
+        let mut edges: Vec<JsonEdge> = graph
+            .raw_edges()
+            .iter()
+            .filter_map(|e| {
+                let from = graph.node_weight(e.source())?;
+                let to = graph.node_weight(e.target())?;
+                Some(JsonEdge {
+                    from: *from,
+                    to: *to,
+                    inherit: e.weight,
+                })
+            })
+            .collect();
+        edges.sort_by_key(|e| (e.from, e.to));
+
+        JsonGraph { nodes, edges }
+    }
+
+    /// The entire graph as Cytoscape JSON elements.
+    pub fn to_cytoscape(&self) -> Cytoscape {
+        let g = self.to_json_graph();
+
+        let nodes = g
+            .nodes
+            .into_iter()
+            .map(|n| Element {
+                data: CyNode {
+                    id: n.kanji,
+                    node: n,
+                },
+            })
+            .collect();
+
+        let edges = g
+            .edges
+            .into_iter()
+            .map(|e| Element {
+                data: CyEdge {
+                    id: format!("{}-{}", e.from, e.to),
+                    source: e.from,
+                    target: e.to,
+                    inherit: e.inherit,
+                },
+            })
+            .collect();
+
+        Cytoscape {
+            elements: Elements { nodes, edges },
+        }
+    }
+
+    /// The entire graph as a GEXF document.
+    pub fn to_gexf(&self) -> String {
+        let g = self.to_json_graph();
+        let mut s = String::new();
+
+        s.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        s.push_str("<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+        s.push_str("  <graph defaultedgetype=\"directed\">\n");
+        s.push_str("    <attributes class=\"node\">\n");
+        s.push_str("      <attribute id=\"onyomi\" title=\"onyomi\" type=\"string\"/>\n");
+        s.push_str("      <attribute id=\"level\" title=\"level\" type=\"string\"/>\n");
+        s.push_str("    </attributes>\n");
+        s.push_str("    <attributes class=\"edge\">\n");
+        s.push_str("      <attribute id=\"inherit\" title=\"inherit\" type=\"string\"/>\n");
+        s.push_str("    </attributes>\n");
+
+        s.push_str("    <nodes>\n");
+        g.nodes.iter().for_each(|n| {
+            let _ = writeln!(s, "      <node id=\"{k}\" label=\"{k}\">", k = n.kanji);
+            s.push_str("        <attvalues>\n");
+            let _ = writeln!(
+                s,
+                "          <attvalue for=\"onyomi\" value=\"{}\"/>",
+                xml_escape(&n.onyomi.iter().join(" "))
+            );
+            if let Some(l) = n.level.as_ref() {
+                let _ = writeln!(s, "          <attvalue for=\"level\" value=\"{}\"/>", l);
+            }
+            s.push_str("        </attvalues>\n");
+            s.push_str("      </node>\n");
+        });
+        s.push_str("    </nodes>\n");
+
+        s.push_str("    <edges>\n");
+        g.edges.iter().enumerate().for_each(|(i, e)| {
+            let _ = writeln!(
+                s,
+                "      <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+                i, e.from, e.to
+            );
+            let _ = writeln!(
+                s,
+                "        <attvalues><attvalue for=\"inherit\" value=\"{}\"/></attvalues>",
+                e.inherit
+            );
+            s.push_str("      </edge>\n");
+        });
+        s.push_str("    </edges>\n");
+
+        s.push_str("  </graph>\n");
+        s.push_str("</gexf>\n");
+        s
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
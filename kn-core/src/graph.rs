@@ -0,0 +1,1433 @@
+//! The Kanji graph and the in-memory database built around it.
+
+use crate::search::Index;
+use crate::{
+    Entry, Error, Inherit, InheritClassifier, Interner, Kanji, Level, MaybeSync, Palette, Reading,
+    Rules,
+};
+use itertools::Itertools;
+use petgraph::prelude::*;
+pub use petgraph::{graph::NodeIndex, Direction};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// A convenient alias. Indexed by `u32` so that databases covering all of CJK,
+/// including 表外字, fit comfortably.
+///
+/// Serializable via `serde`. Since a `DB` always lays out its graph in `Kanji`
+/// order, the same entries always serialize to the same graph.
+pub type KGraph = Graph<Kanji, Inherit, Directed, u32>;
+
+/// A copy of the graph with only those edges whose kind passes the given test.
+/// All nodes are kept.
+pub fn filter_edges<F>(graph: &KGraph, keep: F) -> KGraph
+where
+    F: Fn(Inherit) -> bool,
+{
+    graph.filter_map(|_, k| Some(*k), |_, e| keep(*e).then_some(*e))
+}
+
+/// A copy of the graph with only those nodes that pass the given test, and the
+/// edges between them.
+pub fn filter_nodes<F>(graph: &KGraph, keep: F) -> KGraph
+where
+    F: Fn(Kanji) -> bool,
+{
+    graph.filter_map(|_, k| keep(*k).then_some(*k), |_, e| Some(*e))
+}
+
+/// Have equal readings across all entries share a single allocation.
+fn share_readings(entries: &mut HashMap<Kanji, Entry>) -> Interner {
+    let mut readings = Interner::default();
+    entries
+        .values_mut()
+        .flat_map(|e| e.onyomi.iter_mut())
+        .for_each(|y| y.reading = readings.intern(&y.reading));
+    readings
+}
+
+/// Specific settings for producing the Dot graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DotMode {
+    #[default]
+    NoGroups,
+    Groups,
+}
+
+/// Everything that affects how a graph is rendered into DOT.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DotOptions {
+    pub mode: DotMode,
+    /// The colours of each kind of edge.
+    pub palette: Palette,
+    /// Draw parents that have no entry of their own as dashed grey "ghosts",
+    /// rather than dropping their edges.
+    pub ghosts: bool,
+    /// What, if anything, decides the fill colour of each node.
+    pub colour_by: ColourBy,
+    /// How much to write inside each node.
+    pub labels: Labels,
+    /// The direction in which generations are laid out.
+    pub rankdir: RankDir,
+    /// Give each node its entry's notes as a tooltip, which SVG output shows on
+    /// hover.
+    pub tooltips: bool,
+    /// WaniKani SRS stages, for `ColourBy::WaniKani`.
+    pub srs: BTreeMap<Kanji, u8>,
+    /// Kanji the learner knows, for `ColourBy::Learned`.
+    pub learned: BTreeSet<Kanji>,
+    /// Kanji drawn in grey, there only to give context to the rest. This
+    /// overrides any other fill they'd have.
+    pub context: BTreeSet<Kanji>,
+}
+
+/// The direction Graphviz lays out ranks in, i.e. which way parents point to
+/// their children.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RankDir {
+    /// Top to bottom.
+    #[default]
+    TB,
+    /// Left to right.
+    LR,
+    /// Bottom to top.
+    BT,
+    /// Right to left.
+    RL,
+}
+
+impl std::fmt::Display for RankDir {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            RankDir::TB => "TB",
+            RankDir::LR => "LR",
+            RankDir::BT => "BT",
+            RankDir::RL => "RL",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for RankDir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "TB" => Ok(RankDir::TB),
+            "LR" => Ok(RankDir::LR),
+            "BT" => Ok(RankDir::BT),
+            "RL" => Ok(RankDir::RL),
+            _ => Err(format!("Unknown rank direction: {}", s)),
+        }
+    }
+}
+
+/// What to write inside each node of a graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Labels {
+    /// Just the Kanji itself.
+    #[default]
+    Kanji,
+    /// The Kanji, its first 音読み, and its first 代表 word.
+    Full,
+}
+
+impl std::str::FromStr for Labels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kanji" => Ok(Labels::Kanji),
+            "full" => Ok(Labels::Full),
+            _ => Err(format!("Unknown labelling: {}", s)),
+        }
+    }
+}
+
+/// Ways to fill in the nodes of a graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColourBy {
+    /// Leave nodes unfilled.
+    #[default]
+    Nothing,
+    /// Fill nodes according to their exam level, and add a legend.
+    Level,
+    /// Fill nodes according to their WaniKani SRS stage, taken from
+    /// `DotOptions::srs`, and add a legend.
+    WaniKani,
+    /// Fill the nodes of Kanji in `DotOptions::learned`, and add a legend.
+    Learned,
+}
+
+impl std::str::FromStr for ColourBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nothing" => Ok(ColourBy::Nothing),
+            "level" => Ok(ColourBy::Level),
+            "wanikani" => Ok(ColourBy::WaniKani),
+            "learned" => Ok(ColourBy::Learned),
+            _ => Err(format!("Unknown colouring: {}", s)),
+        }
+    }
+}
+
+/// Every level, easiest first, with the colour it's drawn in.
+pub const LEVEL_COLOURS: [(Level, &str); 12] = [
+    (Level::Ten, "#fbb4ae"),
+    (Level::Nine, "#fdcdac"),
+    (Level::Eight, "#fff2ae"),
+    (Level::Seven, "#e6f5c9"),
+    (Level::Six, "#ccebc5"),
+    (Level::Five, "#b3e2cd"),
+    (Level::Four, "#b3cde3"),
+    (Level::Three, "#cbd5e8"),
+    (Level::PreTwo, "#decbe4"),
+    (Level::Two, "#f4cae4"),
+    (Level::PreOne, "#e5d8bd"),
+    (Level::One, "#f2f2f2"),
+];
+
+/// Every group of WaniKani SRS stages, least known first, with the colour it's
+/// drawn in.
+pub const SRS_COLOURS: [(&str, &str); 5] = [
+    ("Apprentice", "#f5b3dc"),
+    ("Guru", "#dcb3e6"),
+    ("Master", "#b3c3f2"),
+    ("Enlightened", "#b3dff5"),
+    ("Burned", "#d9d9d9"),
+];
+
+/// The colour of learned Kanji. Unlearned ones are left unfilled.
+pub const LEARNED_COLOUR: &str = "#b3e2cd";
+
+/// The colour of Kanji drawn only for context.
+pub const CONTEXT_COLOUR: &str = "#d9d9d9";
+
+/// Which of the `SRS_COLOURS` a WaniKani SRS stage belongs to. Stage 0 means
+/// the lesson hasn't been done yet, so belongs to none.
+pub fn srs_group(stage: u8) -> Option<usize> {
+    match stage {
+        1..=4 => Some(0),
+        5..=6 => Some(1),
+        7 => Some(2),
+        8 => Some(3),
+        9 => Some(4),
+        _ => None,
+    }
+}
+
+/// A single hop along a path through the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hop {
+    /// The `Kanji` arrived at.
+    pub kanji: Kanji,
+    /// The relationship of the edge that was crossed.
+    pub inherit: Inherit,
+    /// `Outgoing` if the hop went from parent to child, `Incoming` otherwise.
+    pub direction: Direction,
+}
+
+/// A deterministic overview of a `DB`'s structure, suitable for comparing two
+/// databases or snapshotting in tests. Everything is kept in sorted order, so
+/// its `Debug` output is stable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DbSummary {
+    /// Every `Kanji` in the database.
+    pub kanji: Vec<Kanji>,
+    /// Every edge, as (parent, child, relationship).
+    pub edges: Vec<(Kanji, Kanji, Inherit)>,
+}
+
+/// An in-memory database for querying `Kanji` data.
+pub struct DB {
+    pub entries: HashMap<Kanji, Entry>,
+    pub index: HashMap<Kanji, NodeIndex<u32>>,
+    pub graph: KGraph,
+    /// The readings of every entry, each kept only once.
+    pub readings: Interner,
+    /// What `DB::find` searches.
+    pub search: Index,
+}
+
+impl DB {
+    /// Create a new `DB` from a freshly read source of entries.
+    ///
+    /// Fails with `Error::TooManyEntries` if there are more entries or edges
+    /// than a `u32` can index, which in practice there never will be.
+    pub fn new(entries: HashMap<Kanji, Entry>) -> Result<DB, Error> {
+        DB::new_with(entries, Rules::default())
+    }
+
+    /// Like `new`, but classify edges with some optional rules turned on.
+    pub fn new_with(entries: HashMap<Kanji, Entry>, rules: Rules) -> Result<DB, Error> {
+        DB::new_with_classifier(entries, &rules)
+    }
+
+    /// Like `new`, but classify edges with a custom strategy. Relationships
+    /// pinned by hand in an `Entry` always take precedence.
+    ///
+    /// With the `parallel` feature, edges are classified on several threads at
+    /// once, which is why the strategy must then be `Sync`. The graph comes out
+    /// the same either way.
+    pub fn new_with_classifier<C>(
+        mut entries: HashMap<Kanji, Entry>,
+        classifier: &C,
+    ) -> Result<DB, Error>
+    where
+        C: InheritClassifier + MaybeSync + ?Sized,
+    {
+        // `petgraph` panics rather than overflow its indices, so we refuse
+        // anything that could before adding a single node. The largest index
+        // is reserved, hence `>=`.
+        let edges: usize = entries.values().map(|e| e.oya.len()).sum();
+        let most = entries.len().max(edges);
+        if most >= u32::MAX as usize {
+            return Err(Error::TooManyEntries(most));
+        }
+
+        let readings = share_readings(&mut entries);
+        let mut graph: KGraph = Graph::default();
+
+        // Add all nodes to the graph, in order, so that node indices don't
+        // depend on the whims of `HashMap`.
+        let index: HashMap<Kanji, NodeIndex<u32>> = entries
+            .keys()
+            .sorted()
+            .map(|k| (*k, graph.add_node(*k)))
+            .collect();
+
+        // Every parent and child, in order, so that edges are added in the
+        // same order however they're classified.
+        let (es, ix) = (&entries, &index);
+        let pairs: Vec<(&Entry, &Entry, NodeIndex<u32>, NodeIndex<u32>)> = es
+            .values()
+            .sorted_by_key(|e| e.kanji)
+            .flat_map(|e| {
+                e.oya.iter().filter_map(move |o| {
+                    let oya = es.get(o)?;
+                    Some((e, oya, *ix.get(o)?, *ix.get(&e.kanji)?))
+                })
+            })
+            .collect();
+        let classify = |&(e, oya, oix, cix): &(&Entry, &Entry, _, _)| {
+            let inherit = e
+                .oya_overrides
+                .get(&oya.kanji)
+                .copied()
+                .unwrap_or_else(|| classifier.classify(e, oya));
+            (oix, cix, inherit)
+        };
+
+        #[cfg(feature = "parallel")]
+        let classified: Vec<_> = pairs.par_iter().map(classify).collect();
+        #[cfg(not(feature = "parallel"))]
+        let classified: Vec<_> = pairs.iter().map(classify).collect();
+
+        // Add all edges to the graph, where parents have directed edges to
+        // their children.
+        classified.into_iter().for_each(|(oix, cix, inherit)| {
+            graph.add_edge(oix, cix, inherit);
+        });
+
+        let search = Index::new(&entries);
+
+        Ok(DB {
+            entries,
+            index,
+            graph,
+            readings,
+            search,
+        })
+    }
+
+    /// Rebuild a `DB` around a graph that was already constructed from these
+    /// entries, e.g. one deserialized from a cache, skipping classification.
+    pub fn with_graph(mut entries: HashMap<Kanji, Entry>, graph: KGraph) -> DB {
+        let readings = share_readings(&mut entries);
+        let index = graph
+            .node_indices()
+            .filter_map(|ix| graph.node_weight(ix).map(|k| (*k, ix)))
+            .collect();
+        let search = Index::new(&entries);
+
+        DB {
+            entries,
+            index,
+            graph,
+            readings,
+            search,
+        }
+    }
+
+    /// A deterministic overview of the database's structure.
+    pub fn summary(&self) -> DbSummary {
+        let kanji = self.entries.keys().copied().sorted().collect();
+        let edges = self
+            .graph
+            .raw_edges()
+            .iter()
+            .filter_map(|e| {
+                let oya = self.graph.node_weight(e.source())?;
+                let child = self.graph.node_weight(e.target())?;
+                Some((*oya, *child, e.weight))
+            })
+            .sorted_by_key(|(oya, child, _)| (*oya, *child))
+            .collect();
+
+        DbSummary { kanji, edges }
+    }
+
+    /// The full `Entry` associated with some index.
+    pub fn entry(&self, nix: NodeIndex<u32>) -> Option<&Entry> {
+        self.graph
+            .node_weight(nix)
+            .and_then(|k| self.entries.get(k))
+    }
+
+    /// The immediate parents (`Incoming`) or children (`Outgoing`) of some
+    /// index, along with the relationship to each.
+    pub fn neighbours(
+        &self,
+        nix: NodeIndex<u32>,
+        dir: Direction,
+    ) -> impl Iterator<Item = (NodeIndex<u32>, Inherit)> + '_ {
+        self.graph.edges_directed(nix, dir).map(move |e| match dir {
+            Direction::Outgoing => (e.target(), *e.weight()),
+            Direction::Incoming => (e.source(), *e.weight()),
+        })
+    }
+
+    /// Every 音読み in the database, with the `Kanji` that have it, in order.
+    pub fn reading_index(&self) -> BTreeMap<&str, Vec<Kanji>> {
+        let mut index: BTreeMap<&str, Vec<Kanji>> = BTreeMap::new();
+        self.entries.values().for_each(|e| {
+            e.onyomi
+                .iter()
+                .for_each(|y| index.entry(y.as_str()).or_default().push(e.kanji))
+        });
+        index.values_mut().for_each(|ks| ks.sort());
+        index
+    }
+
+    /// Every `Kanji` with the given 音読み, in order.
+    pub fn with_reading(&self, yomi: &str) -> Vec<Kanji> {
+        self.entries
+            .values()
+            .filter(|e| e.onyomi.iter().any(|y| y == yomi))
+            .map(|e| e.kanji)
+            .sorted()
+            .collect()
+    }
+
+    /// Every `Kanji` linked to the given one by looking alike, whether
+    /// directly or through others, in order. It doesn't matter which side of
+    /// a pair recorded the link.
+    pub fn lookalikes(&self, k: Kanji) -> Vec<Kanji> {
+        let mut links: HashMap<Kanji, Vec<Kanji>> = HashMap::new();
+        self.entries.values().for_each(|e| {
+            e.nite.iter().for_each(|n| {
+                links.entry(e.kanji).or_default().push(*n);
+                links.entry(*n).or_default().push(e.kanji);
+            })
+        });
+
+        let mut seen: HashSet<Kanji> = HashSet::from([k]);
+        let mut todo = vec![k];
+        while let Some(k) = todo.pop() {
+            links.get(&k).into_iter().flatten().for_each(|n| {
+                if seen.insert(*n) {
+                    todo.push(*n);
+                }
+            });
+        }
+
+        seen.remove(&k);
+        seen.into_iter().sorted().collect()
+    }
+
+    /// Every `Kanji` with no recorded parents, in order.
+    pub fn parentless(&self) -> Vec<Kanji> {
+        self.entries
+            .values()
+            .filter(|e| e.oya.is_empty())
+            .map(|e| e.kanji)
+            .sorted()
+            .collect()
+    }
+
+    /// Every `Kanji` with neither parents nor children in the graph.
+    pub fn orphans(&self) -> Vec<Kanji> {
+        self.graph
+            .node_indices()
+            .filter(|kix| self.graph.neighbors_undirected(*kix).next().is_none())
+            .filter_map(|kix| self.graph.node_weight(kix).copied())
+            .sorted()
+            .collect()
+    }
+
+    /// Every `Kanji` that appears as a parent without having an entry of its
+    /// own, those referenced by the most children first.
+    pub fn missing_parents(&self) -> Vec<Kanji> {
+        self.entries
+            .values()
+            .flat_map(|e| e.oya.iter())
+            .filter(|o| !self.entries.contains_key(o))
+            .counts()
+            .into_iter()
+            .sorted_by_key(|(o, n)| (Reverse(*n), **o))
+            .map(|(o, _)| *o)
+            .collect()
+    }
+
+    /// Every set of `Kanji` connected to one another by any chain of edges,
+    /// each as its own graph, largest first.
+    pub fn components(&self) -> Vec<KGraph> {
+        let mut seen: HashSet<NodeIndex<u32>> = HashSet::new();
+        let mut components: Vec<HashSet<NodeIndex<u32>>> = Vec::new();
+
+        for start in self.graph.node_indices() {
+            if !seen.insert(start) {
+                continue;
+            }
+
+            let mut component = HashSet::new();
+            let mut todo = vec![start];
+
+            while let Some(nix) = todo.pop() {
+                component.insert(nix);
+                let next = self
+                    .graph
+                    .neighbors_undirected(nix)
+                    .filter(|n| seen.insert(*n))
+                    .collect::<Vec<_>>();
+                todo.extend(next);
+            }
+
+            components.push(component);
+        }
+
+        components.sort_by_key(|c| Reverse(c.len()));
+        components
+            .into_iter()
+            .map(|c| {
+                self.graph
+                    .filter_map(|ix, k| c.get(&ix).map(|_| *k), |_, e| Some(*e))
+            })
+            .collect()
+    }
+
+    /// The parentless ancestors of a `Kanji`, which name the families it
+    /// belongs to. A parentless `Kanji` is its own root.
+    pub fn roots(&self, k: Kanji) -> Vec<Kanji> {
+        // Walked by hand rather than via `all_parents`, to survive cycles.
+        let mut seen = HashSet::new();
+        let mut todo = vec![k];
+        let mut roots = Vec::new();
+
+        while let Some(k) = todo.pop() {
+            match self.entries.get(&k) {
+                Some(e) if seen.insert(k) => {
+                    if e.oya.is_empty() {
+                        roots.push(k);
+                    }
+                    todo.extend(e.oya.iter().copied());
+                }
+                _ => {}
+            }
+        }
+
+        roots.sort();
+        roots
+    }
+
+    /// Other `Kanji` whose readings would make them a phonetically sensible
+    /// parent of the given one, most productive first.
+    pub fn similar_readings(&self, k: Kanji) -> Vec<(Kanji, Inherit)> {
+        let child = match self.entries.get(&k) {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+
+        self.entries
+            .values()
+            .filter(|oya| oya.kanji != k)
+            .map(|oya| (oya.kanji, Inherit::classify(child, oya)))
+            .filter(|(_, i)| {
+                matches!(
+                    i,
+                    Inherit::Same
+                        | Inherit::Second
+                        | Inherit::Voicing
+                        | Inherit::Rendaku
+                        | Inherit::Rhyme
+                        | Inherit::Clipped
+                )
+            })
+            .sorted_by_key(|(o, _)| {
+                let children = self
+                    .index
+                    .get(o)
+                    .map(|ix| self.graph.neighbors_directed(*ix, Outgoing).count())
+                    .unwrap_or(0);
+                (Reverse(children), *o)
+            })
+            .collect()
+    }
+
+    /// Fetch the Exam levels of all `Kanji` in the database.
+    pub fn levels(&self) -> HashMap<Kanji, Level> {
+        let table = kanji::level_table();
+        let level = |k: &Kanji| table.get(k).map(|l| (*k, *l));
+
+        #[cfg(feature = "parallel")]
+        let levels = self
+            .entries
+            .par_iter()
+            .filter_map(|(k, _)| level(k))
+            .collect();
+        #[cfg(not(feature = "parallel"))]
+        let levels = self.entries.keys().filter_map(level).collect();
+
+        levels
+    }
+
+    /// Custom DOT output for a `KGraph`.
+    pub fn dot(&self) -> String {
+        self.dot_custom(HashSet::new(), &self.graph, &DotOptions::default())
+    }
+
+    /// Same as `dot`, but supply your own graph and options to consider.
+    pub fn dot_custom(&self, chosen: HashSet<Kanji>, graph: &KGraph, opts: &DotOptions) -> String {
+        let levels = kanji::level_table();
+        let mut s = String::new();
+        s.push_str("digraph {\n");
+        s.push_str(&format!("    rankdir={};\n\n", opts.rankdir));
+
+        let filtered = graph.node_indices().filter_map(|kix| {
+            graph
+                .node_weight(kix)
+                .and_then(|k| self.entries.get(k))
+                .map(|e| {
+                    (
+                        kix,
+                        e,
+                        e.onyomi.first().map(|y| &y.reading),
+                        levels.get(&e.kanji),
+                    )
+                })
+        });
+
+        // Fill colours of each node, if we're colouring them at all.
+        let mut fills: HashMap<Kanji, &str> = match opts.colour_by {
+            ColourBy::Nothing => HashMap::new(),
+            ColourBy::Level => {
+                let colours: HashMap<Level, &str> = LEVEL_COLOURS.iter().copied().collect();
+                self.levels()
+                    .into_iter()
+                    .filter_map(|(k, l)| colours.get(&l).map(|c| (k, *c)))
+                    .collect()
+            }
+            ColourBy::WaniKani => opts
+                .srs
+                .iter()
+                .filter_map(|(k, s)| srs_group(*s).map(|i| (*k, SRS_COLOURS[i].1)))
+                .collect(),
+            ColourBy::Learned => opts.learned.iter().map(|k| (*k, LEARNED_COLOUR)).collect(),
+        };
+        fills.extend(opts.context.iter().map(|k| (*k, CONTEXT_COLOUR)));
+
+        match opts.mode {
+            DotMode::Groups => DB::with_groups(
+                &chosen,
+                &fills,
+                opts.labels,
+                opts.tooltips,
+                &mut s,
+                filtered,
+            ),
+            DotMode::NoGroups => filtered.for_each(|(kix, e, _, _)| {
+                let shape = DB::shape(&chosen, &e.kanji);
+                let line = format!(
+                    "    {} [ label=<{}{}>, shape={}{}{} ]\n",
+                    kix.index(),
+                    e.kanji,
+                    DB::details(opts.labels, e),
+                    shape,
+                    DB::fill(&fills, &e.kanji),
+                    DB::tooltip(opts.tooltips, e)
+                );
+                s.push_str(&line);
+            }),
+        }
+
+        match opts.colour_by {
+            ColourBy::Nothing => {}
+            ColourBy::Level => DB::legend("級", &DB::level_key(graph), &mut s),
+            ColourBy::WaniKani => DB::legend("WaniKani", &DB::srs_key(graph, &opts.srs), &mut s),
+            ColourBy::Learned => {
+                DB::legend("学習", &[("Learned".to_string(), LEARNED_COLOUR)], &mut s)
+            }
+        }
+
+        // Parents without entries, and the children that reference them.
+        let ghosts: Vec<(Kanji, NodeIndex<u32>)> = match opts.ghosts {
+            false => Vec::new(),
+            true => graph
+                .node_indices()
+                .filter_map(|kix| {
+                    graph
+                        .node_weight(kix)
+                        .and_then(|k| self.entries.get(k))
+                        .map(|e| (kix, e))
+                })
+                .flat_map(|(kix, e)| {
+                    e.oya
+                        .iter()
+                        .filter(|o| !self.entries.contains_key(o))
+                        .map(move |o| (*o, kix))
+                })
+                .sorted()
+                .collect(),
+        };
+
+        ghosts.iter().map(|(o, _)| o).dedup().for_each(|o| {
+            let line = format!(
+                "    \"ghost_{}\" [ label=\"{}\", shape=circle, style=dashed, color=gray, fontcolor=gray ]\n",
+                o, o
+            );
+            s.push_str(&line);
+        });
+
+        // Gap between nodes and edges.
+        s.push('\n');
+
+        // Write all the edges.
+        graph.raw_edges().iter().for_each(|e| {
+            let line = format!(
+                "    {} -> {} [ {} ]\n",
+                e.source().index(),
+                e.target().index(),
+                opts.palette.dot_attr(e.weight),
+            );
+            s.push_str(&line);
+        });
+
+        ghosts.iter().for_each(|(o, kix)| {
+            let line = format!(
+                "    \"ghost_{}\" -> {} [ style=dashed, color=gray ]\n",
+                o,
+                kix.index()
+            );
+            s.push_str(&line);
+        });
+
+        // Variants and lookalikes have no direction, and shouldn't affect the
+        // ranking of generations.
+        let nodes: HashMap<Kanji, NodeIndex<u32>> = graph
+            .node_indices()
+            .filter_map(|ix| graph.node_weight(ix).map(|k| (*k, ix)))
+            .collect();
+        let undirected = [
+            (
+                (|e: &Entry| &e.variants) as fn(&Entry) -> &Vec<Kanji>,
+                "dir=none, style=dotted, color=gray40, constraint=false",
+            ),
+            (
+                |e: &Entry| &e.nite,
+                "dir=none, style=dotted, color=gray, constraint=false",
+            ),
+        ];
+        undirected.iter().for_each(|(field, attrs)| {
+            let pairs: HashSet<(NodeIndex<u32>, NodeIndex<u32>)> = nodes
+                .iter()
+                .filter_map(|(k, ix)| self.entries.get(k).map(|e| (ix, e)))
+                .flat_map(|(ix, e)| {
+                    field(e)
+                        .iter()
+                        .filter_map(|v| nodes.get(v))
+                        .map(move |vix| (*ix.min(vix), *ix.max(vix)))
+                })
+                .collect();
+            pairs.into_iter().sorted().for_each(|(a, b)| {
+                let line = format!("    {} -> {} [ {} ]\n", a.index(), b.index(), attrs);
+                s.push_str(&line);
+            });
+        });
+
+        s.push_str("}\n");
+        s
+    }
+
+    fn shape(chosen: &HashSet<Kanji>, k: &Kanji) -> &'static str {
+        if chosen.contains(k) {
+            "doublecircle"
+        } else {
+            "circle"
+        }
+    }
+
+    /// Extra node attributes to fill it in, if it has a colour.
+    fn fill(fills: &HashMap<Kanji, &str>, k: &Kanji) -> String {
+        fills
+            .get(k)
+            .map(|c| format!(", style=filled, fillcolor=\"{}\"", c))
+            .unwrap_or_default()
+    }
+
+    /// An extra node attribute holding its notes, if wanted and there are any.
+    fn tooltip(tooltips: bool, e: &Entry) -> String {
+        match tooltips && !e.notes.is_empty() {
+            false => String::new(),
+            true => {
+                let notes: Vec<String> = e
+                    .notes
+                    .iter()
+                    .map(|n| n.replace('\\', "\\\\").replace('"', "\\\""))
+                    .collect();
+                format!(", tooltip=\"{}\"", notes.join("\\n"))
+            }
+        }
+    }
+
+    /// Extra lines of a node's label beneath the Kanji itself. These are HTML,
+    /// so the text is escaped.
+    fn details(labels: Labels, e: &Entry) -> String {
+        match labels {
+            Labels::Kanji => String::new(),
+            Labels::Full => e
+                .onyomi
+                .first()
+                .map(|y| y.as_str())
+                .into_iter()
+                .chain(e.daihyou.first().map(|d| d.as_str()))
+                .map(|x| {
+                    x.replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;")
+                })
+                .map(|x| format!("<br/><font point-size=\"10\">{}</font>", x))
+                .collect(),
+        }
+    }
+
+    /// A key to what the fill colours of the nodes mean.
+    fn legend(title: &str, key: &[(String, &str)], s: &mut String) {
+        s.push('\n');
+        s.push_str("    subgraph cluster_legend {\n");
+        s.push_str(&format!("        label=\"{}\";\n", title));
+        s.push_str("        style=dashed;\n");
+        s.push('\n');
+        key.iter().enumerate().for_each(|(i, (l, c))| {
+            let line = format!(
+                "        \"legend_{}\" [ label=\"{}\", shape=box, style=filled, fillcolor=\"{}\" ];\n",
+                i, l, c
+            );
+            s.push_str(&line);
+        });
+        s.push_str("    }\n");
+    }
+
+    /// The colours of whichever levels appear in the graph.
+    fn level_key(graph: &KGraph) -> Vec<(String, &'static str)> {
+        let levels = kanji::level_table();
+        let present: HashSet<Level> = graph
+            .node_weights()
+            .filter_map(|k| levels.get(k).copied())
+            .collect();
+
+        LEVEL_COLOURS
+            .iter()
+            .filter(|(l, _)| present.contains(l))
+            .map(|(l, c)| (l.to_string(), *c))
+            .collect()
+    }
+
+    /// The colours of whichever SRS groups appear in the graph.
+    fn srs_key(graph: &KGraph, srs: &BTreeMap<Kanji, u8>) -> Vec<(String, &'static str)> {
+        let present: HashSet<usize> = graph
+            .node_weights()
+            .filter_map(|k| srs.get(k).and_then(|s| srs_group(*s)))
+            .collect();
+
+        SRS_COLOURS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| present.contains(i))
+            .map(|(_, (l, c))| (l.to_string(), *c))
+            .collect()
+    }
+
+    fn with_groups<'a, F>(
+        chosen: &HashSet<Kanji>,
+        fills: &HashMap<Kanji, &str>,
+        labels: Labels,
+        tooltips: bool,
+        s: &mut String,
+        filtered: F,
+    ) where
+        F: Iterator<
+            Item = (
+                NodeIndex<u32>,
+                &'a Entry,
+                Option<&'a Reading>,
+                Option<&'a Level>,
+            ),
+        >,
+    {
+        filtered
+            .sorted_by(|a, b| a.2.cmp(&b.2))
+            .group_by(|pair| pair.2)
+            .into_iter()
+            .for_each(|(yomi, group)| {
+                // An unfortunate `collect` to know the number of elements with certainty.
+                let g: Vec<_> = group.collect();
+
+                match yomi {
+                    // Only bother grouping if there is more than one node in the group.
+                    Some(y) if g.len() > 1 => {
+                        s.push('\n');
+                        s.push_str(&format!("    subgraph cluster_{} {{\n", y));
+                        s.push_str(&format!("        label=\"{}\";\n", y));
+                        s.push_str("        style=dashed;\n");
+                        s.push_str("        color=brown;\n");
+                        s.push('\n');
+                        g.into_iter().for_each(|(kix, e, _, l)| {
+                            let shape = DB::shape(chosen, &e.kanji);
+                            let line = format!(
+                                "        {} [ label=<{}{}{}>, shape={}{}{} ];\n",
+                                kix.index(),
+                                e.kanji,
+                                DB::details(labels, e),
+                                l.map(|x| format!("<br/><font point-size=\"8\">{}</font>", x))
+                                    .unwrap_or_else(|| "".to_string()),
+                                shape,
+                                DB::fill(fills, &e.kanji),
+                                DB::tooltip(tooltips, e)
+                            );
+                            s.push_str(&line);
+                        });
+                        s.push_str("    }\n\n");
+                    }
+                    _ => g.into_iter().for_each(|(kix, e, _, l)| {
+                        let shape = DB::shape(chosen, &e.kanji);
+                        let line = format!(
+                            "    {} [ label=<{}{}{}>, shape={}{}{} ]\n",
+                            kix.index(),
+                            e.kanji,
+                            DB::details(labels, e),
+                            l.map(|x| format!("<br/><font point-size=\"8\">{}</font>", x))
+                                .unwrap_or_else(|| "".to_string()),
+                            shape,
+                            DB::fill(fills, &e.kanji),
+                            DB::tooltip(tooltips, e)
+                        );
+                        s.push_str(&line);
+                    }),
+                }
+            })
+    }
+
+    /// The shortest chain of relations between two `Kanji`, regardless of the
+    /// direction of each edge. The starting `Kanji` itself is not included.
+    pub fn path(&self, a: Kanji, b: Kanji) -> Option<Vec<Hop>> {
+        let aix = *self.index.get(&a)?;
+        let bix = *self.index.get(&b)?;
+        let undirected = self.graph.clone().into_edge_type::<Undirected>();
+        let (_, nixs) = petgraph::algo::astar(&undirected, aix, |n| n == bix, |_| 1, |_| 0)?;
+
+        nixs.iter()
+            .tuple_windows()
+            .map(|(x, y)| {
+                let (eix, direction) = match self.graph.find_edge(*x, *y) {
+                    Some(eix) => (eix, Direction::Outgoing),
+                    None => (self.graph.find_edge(*y, *x)?, Direction::Incoming),
+                };
+
+                Some(Hop {
+                    kanji: *self.graph.node_weight(*y)?,
+                    inherit: *self.graph.edge_weight(eix)?,
+                    direction,
+                })
+            })
+            .collect()
+    }
+
+    /// All groups of `Kanji` that are their own ancestors. The parent relation
+    /// should be a DAG, so ideally this is empty.
+    pub fn cycles(&self) -> Vec<Vec<Kanji>> {
+        petgraph::algo::tarjan_scc(&self.graph)
+            .into_iter()
+            .filter(|scc| match scc[..] {
+                [nix] => self.graph.contains_edge(nix, nix),
+                _ => true,
+            })
+            .map(|scc| {
+                scc.into_iter()
+                    .filter_map(|nix| self.graph.node_weight(nix))
+                    .copied()
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Parents of a prospective `Entry` that would immediately form a cycle
+    /// with it, either because the `Entry` lists itself or because the parent
+    /// already lists the `Entry` as its own parent.
+    pub fn cyclic_parents(&self, entry: &Entry) -> Vec<Kanji> {
+        entry
+            .oya
+            .iter()
+            .filter(|o| {
+                **o == entry.kanji
+                    || self
+                        .entries
+                        .get(o)
+                        .map(|oya| oya.oya.contains(&entry.kanji))
+                        .unwrap_or(false)
+            })
+            .copied()
+            .collect()
+    }
+
+    /// A learning order for every `Kanji` in the database, where parents always
+    /// come before their children. Ties are broken by Kanken level, then by
+    /// `frequency` (a usage rank, where 1 is the most common), then by the
+    /// number of children, since productive components pay off soonest. Any
+    /// `Kanji` caught in a cycle are placed at the very end.
+    pub fn plan(&self, frequency: &HashMap<Kanji, u32>) -> Vec<Kanji> {
+        let levels = self.levels();
+        let key = |nix: NodeIndex<u32>| {
+            let k = self.graph[nix];
+            let level = levels.get(&k).copied();
+            let rank = frequency.get(&k).copied();
+            let children = self.graph.neighbors_directed(nix, Outgoing).count();
+            Reverse((
+                (level.is_none(), level),
+                (rank.is_none(), rank),
+                Reverse(children),
+                k,
+                nix,
+            ))
+        };
+
+        // The number of unlearned parents of each node, ignoring self-loops.
+        let mut waiting: HashMap<NodeIndex<u32>, usize> = self
+            .graph
+            .node_indices()
+            .map(|nix| {
+                let n = self
+                    .graph
+                    .neighbors_directed(nix, Incoming)
+                    .filter(|p| *p != nix)
+                    .count();
+                (nix, n)
+            })
+            .collect();
+
+        let mut ready: BinaryHeap<_> = waiting
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(nix, _)| key(*nix))
+            .collect();
+
+        let mut plan = Vec::with_capacity(self.graph.node_count());
+
+        while let Some(Reverse((_, _, _, k, nix))) = ready.pop() {
+            plan.push(k);
+            waiting.remove(&nix);
+
+            for child in self.graph.neighbors_directed(nix, Outgoing) {
+                if let Some(n) = waiting.get_mut(&child).filter(|n| **n > 0) {
+                    *n -= 1;
+                    if *n == 0 {
+                        ready.push(key(child));
+                    }
+                }
+            }
+        }
+
+        let stuck = waiting
+            .into_keys()
+            .map(|nix| key(nix).0)
+            .sorted()
+            .map(|(_, _, _, k, _)| k);
+        plan.extend(stuck);
+        plan
+    }
+
+    /// Every `Kanji` with any of the given tags, in order. These are the seeds
+    /// that `filtered_graph_via` adds for its tags.
+    pub fn tagged(&self, tags: &[String]) -> Vec<Kanji> {
+        let mut ks: Vec<Kanji> = self
+            .entries
+            .values()
+            .filter(|e| e.tags.iter().any(|t| tags.contains(t)))
+            .map(|e| e.kanji)
+            .collect();
+        ks.sort();
+        ks
+    }
+
+    /// Hone in on specific Kanji families, optionally only as far as `depth`
+    /// hops up and down from each seed.
+    pub fn filtered_graph(&self, ks: Vec<Kanji>, depth: Option<usize>) -> KGraph {
+        self.filtered_graph_via(ks, &[], &HashMap::new(), depth)
+    }
+
+    /// Like `filtered_graph`, but also seeded by every `Kanji` with any of the
+    /// given tags, and only following the given parents when walking up from a
+    /// seed. Seeds missing from `lineages` follow all of their parents as
+    /// usual.
+    pub fn filtered_graph_via(
+        &self,
+        mut ks: Vec<Kanji>,
+        tags: &[String],
+        lineages: &HashMap<Kanji, Vec<Kanji>>,
+        depth: Option<usize>,
+    ) -> KGraph {
+        if !tags.is_empty() {
+            ks.extend(self.tagged(tags));
+        }
+
+        let children: HashSet<_> = ks
+            .iter()
+            .filter_map(|k| self.index.get(k))
+            .flat_map(|kix| self.all_children(*kix, depth))
+            .collect();
+        let parents: HashSet<_> = ks
+            .into_iter()
+            .flat_map(|k| match lineages.get(&k) {
+                None => self.all_parents(k, depth),
+                Some(_) if depth == Some(0) => HashSet::new(),
+                Some(os) => os
+                    .iter()
+                    .filter_map(|o| {
+                        let ix = self.index.get(o)?;
+                        let mut parents = self.all_parents(*o, depth.map(|d| d - 1));
+                        parents.insert(*ix);
+                        Some(parents)
+                    })
+                    .flatten()
+                    .collect(),
+            })
+            .collect();
+        let indices: HashSet<NodeIndex<u32>> = children.union(&parents).copied().collect();
+
+        self.graph
+            .filter_map(|ix, k| indices.get(&ix).map(|_| *k), |_, e| Some(*e))
+    }
+
+    /// Walk down the graph to find the descendants of the given `Kanji`, at
+    /// most `depth` generations down if given, along with the other parents
+    /// of each and their ancestors. The parents of the node a child was reached
+    /// from are left to the walk up from it, so that a lineage left out of
+    /// `filtered_graph_via` doesn't sneak back in through a child. Each node is
+    /// visited once, so cycles in the data end the walk rather than the
+    /// program.
+    fn all_children(&self, kix: NodeIndex<u32>, depth: Option<usize>) -> HashSet<NodeIndex<u32>> {
+        let mut ixs: HashSet<NodeIndex<u32>> = HashSet::from([kix]);
+        let mut seen: HashSet<NodeIndex<u32>> = HashSet::from([kix]);
+        let mut todo: VecDeque<(NodeIndex<u32>, usize)> = VecDeque::from([(kix, 0)]);
+
+        while let Some((ix, d)) = todo.pop_front() {
+            if depth.is_some_and(|max| d >= max) {
+                continue;
+            }
+
+            // How far up the other parents of the next generation may go.
+            let up = depth.map(|max| max - d - 1);
+            for child in self.graph.neighbors_directed(ix, Direction::Outgoing) {
+                ixs.insert(child);
+
+                let others = self
+                    .entry(child)
+                    .into_iter()
+                    .flat_map(|e| &e.oya)
+                    .filter(|o| self.index.get(o) != Some(&ix));
+                for o in others {
+                    if let Some(oix) = self.index.get(o) {
+                        ixs.insert(*oix);
+                        ixs.extend(self.all_parents(*o, up));
+                    }
+                }
+
+                if seen.insert(child) {
+                    todo.push_back((child, d + 1));
+                }
+            }
+        }
+
+        ixs
+    }
+
+    /// Walk up the graph to find the ancestors of the given `Kanji`, at most
+    /// `depth` generations up if given. As with `all_children`, each node is
+    /// visited once.
+    fn all_parents(&self, k: Kanji, depth: Option<usize>) -> HashSet<NodeIndex<u32>> {
+        let mut ixs: HashSet<NodeIndex<u32>> = HashSet::new();
+        let mut seen: HashSet<Kanji> = HashSet::from([k]);
+        let mut todo: VecDeque<(Kanji, usize)> = VecDeque::from([(k, 0)]);
+
+        while let Some((k, d)) = todo.pop_front() {
+            if depth.is_some_and(|max| d >= max) {
+                continue;
+            }
+
+            let oya = self.entries.get(&k).into_iter().flat_map(|e| &e.oya);
+            for o in oya {
+                if let Some(ix) = self.index.get(o) {
+                    ixs.insert(*ix);
+                    if seen.insert(*o) {
+                        todo.push_back((*o, d + 1));
+                    }
+                }
+            }
+        }
+
+        ixs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Onyomi;
+
+    fn k(c: char) -> Kanji {
+        Kanji::new(c).unwrap()
+    }
+
+    fn entry(c: char, oya: &str, onyomi: &[&str]) -> Entry {
+        Entry {
+            oya: oya.chars().map(k).collect(),
+            onyomi: onyomi.iter().map(|y| Onyomi::from(*y)).collect(),
+            ..Entry::new(k(c))
+        }
+    }
+
+    fn db(entries: Vec<Entry>) -> DB {
+        DB::new(entries.into_iter().map(|e| (e.kanji, e)).collect()).unwrap()
+    }
+
+    #[test]
+    fn lookalikes() {
+        let db = db(vec![
+            Entry {
+                nite: vec![k('末')],
+                ..entry('未', "", &["み"])
+            },
+            Entry {
+                nite: vec![k('末')],
+                ..entry('本', "", &["ほん"])
+            },
+            entry('末', "", &["まつ"]),
+            entry('木', "", &["もく"]),
+        ]);
+
+        // Links count from either side, and through one another.
+        assert_eq!(db.lookalikes(k('末')), vec![k('未'), k('本')]);
+        assert_eq!(db.lookalikes(k('未')), vec![k('末'), k('本')]);
+        assert_eq!(db.lookalikes(k('木')), vec![]);
+    }
+
+    fn family(db: &DB, c: char, depth: Option<usize>) -> Vec<Kanji> {
+        let graph = db.filtered_graph(vec![k(c)], depth);
+        graph.node_weights().copied().sorted().collect()
+    }
+
+    #[test]
+    fn families_survive_cycles() {
+        // A self-loop, as 坦 has in the real data, and a 2-cycle.
+        let db = db(vec![
+            entry('旦', "", &["たん"]),
+            entry('坦', "坦旦", &["たん"]),
+            entry('甲', "乙", &["こう"]),
+            entry('乙', "甲", &["おつ"]),
+        ]);
+
+        assert_eq!(family(&db, '坦', None), vec![k('坦'), k('旦')]);
+        assert_eq!(family(&db, '旦', None), vec![k('坦'), k('旦')]);
+        assert_eq!(family(&db, '甲', None), vec![k('乙'), k('甲')]);
+        assert_eq!(family(&db, '甲', Some(1)), vec![k('乙'), k('甲')]);
+    }
+
+    #[test]
+    fn chosen_lineages() {
+        // 江 has two parents, and a child of its own.
+        let db = db(vec![
+            entry('工', "", &["こう"]),
+            entry('水', "", &["すい"]),
+            entry('江', "工水", &["こう"]),
+            entry('鴻', "江", &["こう"]),
+        ]);
+        let only = |o: char| HashMap::from([(k('江'), vec![k(o)])]);
+        let family = |lineages: &HashMap<Kanji, Vec<Kanji>>| -> Vec<Kanji> {
+            let graph = db.filtered_graph_via(vec![k('江')], &[], lineages, None);
+            graph.node_weights().copied().sorted().collect()
+        };
+
+        assert_eq!(family(&HashMap::new()).len(), 4);
+        assert_eq!(family(&only('工')), vec![k('工'), k('江'), k('鴻')]);
+        assert_eq!(family(&only('水')), vec![k('水'), k('江'), k('鴻')]);
+    }
+
+    #[test]
+    fn family_depth() {
+        // 一 → 二 → 三 → 四, with 五 a second parent of 四.
+        let db = db(vec![
+            entry('一', "", &[]),
+            entry('二', "一", &[]),
+            entry('三', "二", &[]),
+            entry('四', "三五", &[]),
+            entry('五', "", &[]),
+        ]);
+
+        assert_eq!(family(&db, '二', Some(0)), vec![k('二')]);
+        assert_eq!(family(&db, '二', Some(1)), vec![k('一'), k('三'), k('二')]);
+        assert_eq!(family(&db, '三', None).len(), 5);
+        assert_eq!(family(&db, '一', Some(2)), vec![k('一'), k('三'), k('二')]);
+        assert_eq!(family(&db, '一', Some(3)).len(), 5);
+    }
+
+    #[test]
+    fn tagged_seeds() {
+        let db = db(vec![
+            Entry {
+                tags: vec!["地名".to_string()],
+                ..entry('江', "工", &["こう"])
+            },
+            entry('工', "", &["こう"]),
+            entry('木', "", &["もく"]),
+            entry('林', "木", &["りん"]),
+        ]);
+        let family = |ks: Vec<Kanji>, tags: &[String]| -> Vec<Kanji> {
+            let graph = db.filtered_graph_via(ks, tags, &HashMap::new(), None);
+            graph.node_weights().copied().sorted().collect()
+        };
+        let tags = ["地名".to_string()];
+
+        assert_eq!(family(vec![], &tags), vec![k('工'), k('江')]);
+        assert_eq!(
+            family(vec![k('林')], &tags),
+            vec![k('工'), k('木'), k('林'), k('江')]
+        );
+        assert!(family(vec![], &["化学".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn readings_are_shared() {
+        let entries = vec![
+            entry('工', "", &["こう"]),
+            entry('江', "工", &["こう"]),
+            entry('鴻', "江", &["こう", "ぐ"]),
+        ];
+        let same = |db: &DB, a: char, b: char| {
+            let ya = db.entries[&k(a)].onyomi[0].reading.as_str();
+            let yb = db.entries[&k(b)].onyomi[0].reading.as_str();
+            std::ptr::eq(ya, yb)
+        };
+
+        let fresh = db(entries.clone());
+        assert!(same(&fresh, '工', '江'));
+        assert!(same(&fresh, '江', '鴻'));
+        assert_eq!(fresh.readings.len(), 2);
+
+        // As when loading from the cache, where entries arrive unshared.
+        let map = entries.into_iter().map(|e| (e.kanji, e)).collect();
+        let cached = DB::with_graph(map, fresh.graph.clone());
+        assert!(same(&cached, '工', '江'));
+        assert!(same(&cached, '江', '鴻'));
+        assert_eq!(cached.readings.len(), 2);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_sequential() {
+        // Enough entries that the work is really split between threads.
+        let readings = ["こう", "ごう", "きょう", "せい", "しょう", "じょう"];
+        let kanji: Vec<Kanji> = (0x4E00..0x4E00 + 3000)
+            .filter_map(char::from_u32)
+            .filter_map(Kanji::new)
+            .collect();
+        let entries: HashMap<Kanji, Entry> = kanji
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let oya = [i / 2, i / 3, i.saturating_sub(7)]
+                    .into_iter()
+                    .filter(|o| *o != i)
+                    .unique()
+                    .map(|o| kanji[o])
+                    .collect();
+                let onyomi = vec![Onyomi::from(readings[i % readings.len()])];
+                let e = Entry {
+                    oya,
+                    onyomi,
+                    ..Entry::new(*k)
+                };
+                (*k, e)
+            })
+            .collect();
+        let rules = Rules::default();
+        let db = DB::new_with(entries.clone(), rules).unwrap();
+
+        // The same edges, classified one at a time, in the order `DB` promises.
+        let expected: Vec<_> = entries
+            .values()
+            .sorted_by_key(|e| e.kanji)
+            .flat_map(|e| {
+                e.oya.iter().map(|o| {
+                    let inherit = rules.classify(e, &entries[o]);
+                    (db.index[o], db.index[&e.kanji], inherit)
+                })
+            })
+            .collect();
+        let actual: Vec<_> = db
+            .graph
+            .raw_edges()
+            .iter()
+            .map(|e| (e.source(), e.target(), e.weight))
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn plan_ties() {
+        let db = db(vec![
+            entry('火', "", &["か"]),
+            entry('水', "", &["すい"]),
+            entry('木', "", &["もく"]),
+            entry('炎', "火", &["えん"]),
+        ]);
+        let plan = |frequency: &HashMap<Kanji, u32>| -> String {
+            db.plan(frequency).into_iter().map(|k| k.get()).collect()
+        };
+
+        // Without frequencies, the Kanji with children go first.
+        assert_eq!("火木水炎", plan(&HashMap::new()));
+
+        let frequency = HashMap::from([(k('水'), 1), (k('木'), 2)]);
+        assert_eq!("水木火炎", plan(&frequency));
+    }
+
+    #[test]
+    fn escaped_labels() {
+        let db = db(vec![Entry {
+            daihyou: vec!["<a> & b".to_string()],
+            ..entry('木', "", &["もく"])
+        }]);
+        let opts = DotOptions {
+            labels: Labels::Full,
+            ..DotOptions::default()
+        };
+        let dot = db.dot_custom(HashSet::new(), &db.graph, &opts);
+
+        assert!(dot.contains("&lt;a&gt; &amp; b"));
+        assert!(!dot.contains("<a>"));
+    }
+}
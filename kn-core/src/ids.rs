@@ -0,0 +1,160 @@
+//! Reading the structure of Kanji from CHISE's IDS (Ideographic Description
+//! Sequence) files, for suggesting and checking 親.
+//!
+//! Each tab-separated line holds a code point, the character itself, and its
+//! decomposition, like `U+8A9E 語 ⿰言吾`. Lines starting with `;;` are
+//! comments. Components with no code point of their own are written as
+//! entities like `&CDP-8B7C;`, and are skipped.
+
+use crate::Kanji;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufRead;
+
+#[cfg(feature = "fs")]
+use crate::Error;
+#[cfg(feature = "graph")]
+use crate::DB;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+/// The name of the file that imported decompositions are kept in, next to the
+/// data file.
+pub const IDS_FILE: &str = "ids.json";
+
+/// The immediate components of every Kanji that has any.
+pub type Components = BTreeMap<Kanji, Vec<Kanji>>;
+
+/// An entry with parents that its decomposition doesn't account for.
+#[derive(Debug, Serialize)]
+pub struct StrayParents {
+    pub kanji: Kanji,
+    pub parents: Vec<Kanji>,
+}
+
+/// The immediate components of every Kanji in an IDS file.
+pub fn components_table<R: BufRead>(reader: R) -> Result<Components, std::io::Error> {
+    let mut table = Components::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with(";;") {
+            continue;
+        }
+
+        let mut fields = line.split('\t').skip(1);
+        let kanji = match fields
+            .next()
+            .and_then(|s| s.chars().next())
+            .and_then(Kanji::new)
+        {
+            Some(k) => k,
+            None => continue,
+        };
+        let mut components: Vec<Kanji> = Vec::new();
+        fields
+            .next()
+            .map(without_entities)
+            .unwrap_or_default()
+            .chars()
+            .filter_map(Kanji::new)
+            .filter(|c| *c != kanji)
+            .for_each(|c| {
+                if !components.contains(&c) {
+                    components.push(c);
+                }
+            });
+
+        if !components.is_empty() {
+            table.insert(kanji, components);
+        }
+    }
+
+    Ok(table)
+}
+
+/// A decomposition with any `&...;` entities removed.
+fn without_entities(ids: &str) -> String {
+    let mut s = String::new();
+    let mut rest = ids;
+
+    while let Some(i) = rest.find('&') {
+        s.push_str(&rest[..i]);
+        rest = match rest[i..].find(';') {
+            Some(j) => &rest[i + j + 1..],
+            None => "",
+        };
+    }
+
+    s.push_str(rest);
+    s
+}
+
+/// Every component of a Kanji, however deeply nested.
+pub fn all_components(table: &Components, k: Kanji) -> BTreeSet<Kanji> {
+    let mut seen = BTreeSet::new();
+    let mut todo = vec![k];
+
+    while let Some(k) = todo.pop() {
+        table.get(&k).into_iter().flatten().for_each(|c| {
+            if seen.insert(*c) {
+                todo.push(*c);
+            }
+        });
+    }
+
+    seen
+}
+
+#[cfg(feature = "graph")]
+impl DB {
+    /// Entries whose parents don't all appear somewhere in their
+    /// decomposition. Kanji with no known decomposition are passed over.
+    pub fn stray_parents(&self, table: &Components) -> Vec<StrayParents> {
+        let mut strays: Vec<StrayParents> = self
+            .entries
+            .values()
+            .filter(|e| table.contains_key(&e.kanji))
+            .filter_map(|e| {
+                let components = all_components(table, e.kanji);
+                let parents: Vec<Kanji> = e
+                    .oya
+                    .iter()
+                    .filter(|o| !components.contains(o))
+                    .copied()
+                    .collect();
+
+                (!parents.is_empty()).then_some(StrayParents {
+                    kanji: e.kanji,
+                    parents,
+                })
+            })
+            .collect();
+
+        strays.sort_by_key(|s| s.kanji);
+        strays
+    }
+}
+
+/// The location of the imported decompositions that accompany a data file.
+#[cfg(feature = "fs")]
+pub fn ids_path(data: &Path) -> PathBuf {
+    data.with_file_name(IDS_FILE)
+}
+
+/// Read imported decompositions, yielding none if they've never been imported.
+#[cfg(feature = "fs")]
+pub fn read_components(path: &Path) -> Result<Components, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(Error::Json),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Components::new()),
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
+/// Write imported decompositions.
+#[cfg(feature = "fs")]
+pub fn write_components(path: &Path, table: &Components) -> Result<(), Error> {
+    let raw = serde_json::to_string(table).map_err(Error::Json)?;
+    std::fs::write(path, raw).map_err(Error::Io)
+}
@@ -0,0 +1,379 @@
+//! How the readings of a child relate to those of its parents.
+
+#[cfg(feature = "graph")]
+use crate::Onyomi;
+use crate::{phonology, Entry};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The relationship between parents and children, in terms of their readings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Inherit {
+    /// The child is the exact same as the parent. (e.g. こく→こく)
+    Same,
+    /// A secondary reading of the child is the same as the parent.
+    Second,
+    /// The child is a voicing variant of the parent. (e.g. こく→ごく)
+    Voicing,
+    /// The child is a は行 voicing variant of the parent. (e.g. ふ→ぶ)
+    Rendaku,
+    /// The child is a rhyme of the parent. (e.g. こく→よく)
+    Rhyme,
+    /// The final mora of the child has mutated to っ or dropped. (e.g. がく→がっ, かん→か)
+    Clipped,
+    /// The first consonant of the child is at least of the same class as the
+    /// parent's. (e.g. こく→けい)
+    Consonant,
+    /// The child bares no resemblance to the parent. (e.g. こく→よう)
+    Differ,
+    /// The child has no 音読み, which occurs often with 国字.
+    None,
+}
+
+/// A strategy for deciding how a child's readings relate to its parent's.
+///
+/// The heuristics in `Rules` are used by default, but other strategies (say,
+/// one based on Middle Chinese reconstructions) can be swapped in via
+/// `DB::new_with_classifier`. Plain functions and closures also qualify.
+pub trait InheritClassifier {
+    fn classify(&self, child: &Entry, oya: &Entry) -> Inherit;
+}
+
+impl InheritClassifier for Rules {
+    fn classify(&self, child: &Entry, oya: &Entry) -> Inherit {
+        Inherit::classify_with(child, oya, *self)
+    }
+}
+
+impl<F> InheritClassifier for F
+where
+    F: Fn(&Entry, &Entry) -> Inherit,
+{
+    fn classify(&self, child: &Entry, oya: &Entry) -> Inherit {
+        self(child, oya)
+    }
+}
+
+/// `Sync` with the `parallel` feature, where classifiers are shared between
+/// threads, and no requirement at all without it.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// `Sync` with the `parallel` feature, where classifiers are shared between
+/// threads, and no requirement at all without it.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// The result of classifying an edge, along with the readings responsible.
+pub struct Match<'a> {
+    pub inherit: Inherit,
+    /// The child's reading that matched, if any.
+    pub child: Option<&'a str>,
+    /// The parent's reading that matched, if any.
+    pub oya: Option<&'a str>,
+}
+
+/// Optional rules to apply when classifying edges.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    /// Recognize は行 voicing (h→b/p) as `Inherit::Rendaku`.
+    pub rendaku: bool,
+    /// Only compare readings of the same `Stratum`, when both are known.
+    pub strata: bool,
+}
+
+impl Inherit {
+    /// Every kind of relationship, from most to least regular.
+    pub const ALL: [Inherit; 9] = [
+        Inherit::Same,
+        Inherit::Second,
+        Inherit::Voicing,
+        Inherit::Rendaku,
+        Inherit::Rhyme,
+        Inherit::Clipped,
+        Inherit::Consonant,
+        Inherit::Differ,
+        Inherit::None,
+    ];
+
+    /// Determine how a child's readings relate to those of its parent.
+    pub fn classify(child: &Entry, oya: &Entry) -> Inherit {
+        Inherit::classify_with(child, oya, Rules::default())
+    }
+
+    /// Like `classify`, but with some optional rules turned on.
+    pub fn classify_with(child: &Entry, oya: &Entry, rules: Rules) -> Inherit {
+        Inherit::explain(child, oya, rules).inherit
+    }
+
+    /// Classify an edge by comparing every pair of child and parent readings,
+    /// keeping the strongest relationship and the readings responsible for it.
+    /// An exact match beats a voicing, a voicing beats a rhyme, and so on, no
+    /// matter which readings are involved. Only between equally strong pairs
+    /// do the first readings of each win, and an exact match elsewhere is
+    /// `Second` rather than `Same`.
+    pub fn explain<'a>(child: &'a Entry, oya: &'a Entry, rules: Rules) -> Match<'a> {
+        if child.onyomi.is_empty() || oya.onyomi.is_empty() {
+            return Match {
+                inherit: Inherit::None,
+                child: None,
+                oya: None,
+            };
+        }
+
+        child
+            .onyomi
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| oya.onyomi.iter().enumerate().map(move |jb| ((i, a), jb)))
+            .filter(|((_, a), (_, b))| !rules.strata || a.comparable(b))
+            .filter_map(|((i, a), (j, b))| {
+                let (strength, inherit) = Inherit::relate(a, b, rules)?;
+                let first = i == 0 && j == 0;
+                let inherit = match inherit {
+                    Inherit::Same if !first => Inherit::Second,
+                    _ => inherit,
+                };
+                let found = Match {
+                    inherit,
+                    child: Some(a.as_str()),
+                    oya: Some(b.as_str()),
+                };
+
+                Some(((strength, !first), found))
+            })
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, found)| found)
+            .unwrap_or(Match {
+                inherit: Inherit::Differ,
+                child: None,
+                oya: None,
+            })
+    }
+
+    /// How a reading that some child might have follows from its parent's
+    /// readings, if at all: the strongest relationship, the first of the
+    /// parent's readings winning between equals, and the reading responsible.
+    /// Ranked as in `explain`, lower being stronger.
+    #[cfg(feature = "graph")]
+    pub(crate) fn follow<'a>(
+        reading: &str,
+        oya: &'a [Onyomi],
+        rules: Rules,
+    ) -> Option<((u8, bool), Inherit, &'a Onyomi)> {
+        oya.iter()
+            .enumerate()
+            .filter_map(|(j, b)| {
+                let (strength, inherit) = Inherit::relate(reading, b, rules)?;
+                let inherit = match inherit {
+                    Inherit::Same if j > 0 => Inherit::Second,
+                    _ => inherit,
+                };
+                Some(((strength, j > 0), inherit, b))
+            })
+            .min_by_key(|(rank, _, _)| *rank)
+    }
+
+    /// How a single pair of readings relate, if at all, along with the strength
+    /// of that relationship (lower is stronger).
+    fn relate(a: &str, b: &str, rules: Rules) -> Option<(u8, Inherit)> {
+        if a == b {
+            Some((0, Inherit::Same))
+        } else if phonology::is_voiced_pair(a, b) {
+            Some((1, Inherit::Voicing))
+        } else if rules.rendaku && phonology::is_rendaku_pair(a, b) {
+            Some((2, Inherit::Rendaku))
+        } else if phonology::is_rhyme(a, b) {
+            Some((3, Inherit::Rhyme))
+        } else if phonology::is_clipped(a, b) {
+            Some((4, Inherit::Clipped))
+        } else if phonology::is_same_consonant(a, b) {
+            Some((5, Inherit::Consonant))
+        } else {
+            None
+        }
+    }
+}
+
+/// The colours of each kind of edge when rendering a graph.
+///
+/// Any colour name or `#rrggbb` value understood by Graphviz will do. Fields
+/// left out when deserializing keep their default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub same: String,
+    pub second: String,
+    pub voicing: String,
+    pub rendaku: String,
+    pub rhyme: String,
+    pub clipped: String,
+    pub consonant: String,
+    pub differ: String,
+    pub none: String,
+}
+
+impl Default for Palette {
+    /// Soft pastels, chosen to stay distinguishable under the common forms of
+    /// colour blindness.
+    fn default() -> Self {
+        Palette {
+            same: "#66c2a5".to_string(),
+            second: "#a6d854".to_string(),
+            voicing: "#8da0cb".to_string(),
+            rendaku: "#e78ac3".to_string(),
+            rhyme: "#ffd92f".to_string(),
+            clipped: "#80b1d3".to_string(),
+            consonant: "#e5c494".to_string(),
+            differ: "#fc8d62".to_string(),
+            none: "#b3b3b3".to_string(),
+        }
+    }
+}
+
+impl Palette {
+    /// The colour of a given kind of edge.
+    pub fn colour(&self, inherit: Inherit) -> &str {
+        match inherit {
+            Inherit::Same => &self.same,
+            Inherit::Second => &self.second,
+            Inherit::Voicing => &self.voicing,
+            Inherit::Rendaku => &self.rendaku,
+            Inherit::Rhyme => &self.rhyme,
+            Inherit::Clipped => &self.clipped,
+            Inherit::Consonant => &self.consonant,
+            Inherit::Differ => &self.differ,
+            Inherit::None => &self.none,
+        }
+    }
+
+    /// Change the colour of a given kind of edge.
+    pub fn set(&mut self, inherit: Inherit, colour: String) {
+        let field = match inherit {
+            Inherit::Same => &mut self.same,
+            Inherit::Second => &mut self.second,
+            Inherit::Voicing => &mut self.voicing,
+            Inherit::Rendaku => &mut self.rendaku,
+            Inherit::Rhyme => &mut self.rhyme,
+            Inherit::Clipped => &mut self.clipped,
+            Inherit::Consonant => &mut self.consonant,
+            Inherit::Differ => &mut self.differ,
+            Inherit::None => &mut self.none,
+        };
+        *field = colour;
+    }
+
+    /// The DOT attributes of a given kind of edge.
+    pub fn dot_attr(&self, inherit: Inherit) -> String {
+        format!("color=\"{}\"", self.colour(inherit))
+    }
+}
+
+impl std::str::FromStr for Inherit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "same" => Ok(Inherit::Same),
+            "second" => Ok(Inherit::Second),
+            "voicing" => Ok(Inherit::Voicing),
+            "rendaku" => Ok(Inherit::Rendaku),
+            "rhyme" => Ok(Inherit::Rhyme),
+            "clipped" => Ok(Inherit::Clipped),
+            "consonant" => Ok(Inherit::Consonant),
+            "differ" => Ok(Inherit::Differ),
+            "none" => Ok(Inherit::None),
+            _ => Err(format!("Unknown relationship: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Inherit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inherit::Same => write!(f, "Same"),
+            Inherit::Second => write!(f, "Second"),
+            Inherit::Voicing => write!(f, "Voicing"),
+            Inherit::Rendaku => write!(f, "Rendaku"),
+            Inherit::Rhyme => write!(f, "Rhyme"),
+            Inherit::Clipped => write!(f, "Clipped"),
+            Inherit::Consonant => write!(f, "Consonant"),
+            Inherit::Differ => write!(f, "Differ"),
+            Inherit::None => write!(f, "None"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kanji::Kanji;
+
+    fn entry(k: char, onyomi: &[&str]) -> Entry {
+        Entry {
+            onyomi: onyomi.iter().map(|y| crate::Onyomi::from(*y)).collect(),
+            ..Entry::new(Kanji::new(k).unwrap())
+        }
+    }
+
+    fn relate(a: &str, b: &str) -> Option<Inherit> {
+        Inherit::relate(a, b, Rules::default()).map(|(_, i)| i)
+    }
+
+    #[test]
+    fn consonant() {
+        assert_eq!(relate("けい", "こく"), Some(Inherit::Consonant));
+        assert_eq!(relate("ぎん", "こく"), Some(Inherit::Consonant));
+        assert_eq!(relate("ほう", "ばく"), Some(Inherit::Consonant));
+        assert_eq!(relate("よう", "こく"), None);
+        assert_eq!(relate("えい", "おく"), None);
+    }
+
+    #[test]
+    fn stronger_relations_win() {
+        assert_eq!(relate("こく", "こく"), Some(Inherit::Same));
+        assert_eq!(relate("ごく", "こく"), Some(Inherit::Voicing));
+        assert_eq!(relate("よく", "こく"), Some(Inherit::Rhyme));
+        assert_eq!(relate("かっ", "かく"), Some(Inherit::Clipped));
+    }
+
+    #[test]
+    fn classify() {
+        let oya = entry('工', &["こう", "く"]);
+        let child = |ys| Inherit::classify(&entry('江', ys), &oya);
+
+        assert_eq!(child(&["けい"]), Inherit::Consonant);
+        assert_eq!(child(&["しん"]), Inherit::Differ);
+        assert_eq!(child(&["しん", "く"]), Inherit::Second);
+        assert_eq!(child(&[]), Inherit::None);
+    }
+
+    #[test]
+    fn strongest_pair_wins() {
+        let oya = entry('工', &["こう"]);
+        let child = entry('江', &["かん", "こう"]);
+        let found = Inherit::explain(&child, &oya, Rules::default());
+
+        assert_eq!(found.inherit, Inherit::Second);
+        assert_eq!(found.child, Some("こう"));
+        assert_eq!(found.oya, Some("こう"));
+
+        // A voicing elsewhere still beats a weaker match on the first pair.
+        let child = entry('江', &["かん", "ごう"]);
+        let found = Inherit::explain(&child, &oya, Rules::default());
+        assert_eq!(found.inherit, Inherit::Voicing);
+        assert_eq!(found.child, Some("ごう"));
+
+        // Between equals, the first readings win.
+        let oya = entry('工', &["こう", "く"]);
+        let child = entry('江', &["こう", "く"]);
+        assert_eq!(Inherit::classify(&child, &oya), Inherit::Same);
+    }
+}
@@ -0,0 +1,108 @@
+//! Reading vocabulary from the JMdict dictionary file, for suggesting 代表
+//! words.
+//!
+//! JMdict is XML, but with one element per line, so it's read line by line
+//! rather than with a full XML parser. Only common words are kept: those whose
+//! written form carries one of the `news1`, `ichi1`, `spec1`, `spec2`, or
+//! `gai1` priority markers.
+
+use crate::Kanji;
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+
+#[cfg(feature = "fs")]
+use crate::Error;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+/// The name of the file that imported words are kept in, next to the data file.
+pub const JMDICT_FILE: &str = "jmdict.json";
+
+/// The priority markers of common words.
+const COMMON: [&str; 5] = ["news1", "ichi1", "spec1", "spec2", "gai1"];
+
+/// A written form of a word and how it's read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Word {
+    pub word: String,
+    pub reading: String,
+}
+
+/// Every common word in a JMdict file, paired with its first reading.
+pub fn common_words<R: BufRead>(reader: R) -> Result<Vec<Word>, std::io::Error> {
+    let mut words = Vec::new();
+    // The written forms of the current entry, and whether each is common.
+    let mut kebs: Vec<(String, bool)> = Vec::new();
+    let mut reading: Option<String> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if let Some(keb) = inner(line, "keb") {
+            kebs.push((keb.to_string(), false));
+        } else if let Some(pri) = inner(line, "ke_pri") {
+            if let Some(last) = kebs.last_mut() {
+                last.1 |= COMMON.contains(&pri);
+            }
+        } else if let Some(reb) = inner(line, "reb") {
+            reading.get_or_insert_with(|| reb.to_string());
+        } else if line == "</entry>" {
+            if let Some(r) = reading.take() {
+                kebs.drain(..)
+                    .filter(|(_, common)| *common)
+                    .for_each(|(word, _)| {
+                        words.push(Word {
+                            word,
+                            reading: r.clone(),
+                        })
+                    });
+            }
+            kebs.clear();
+        }
+    }
+
+    Ok(words)
+}
+
+/// The text within a single-line element, like `<keb>意欲</keb>`.
+fn inner<'a>(line: &'a str, tag: &str) -> Option<&'a str> {
+    line.strip_prefix(&format!("<{}>", tag))?
+        .strip_suffix(&format!("</{}>", tag))
+}
+
+/// Compounds that use the given `Kanji` with the given reading, shortest first.
+/// A word is judged to use the reading if it appears anywhere in the word's
+/// own reading, so this is a rough cut to choose from, not an authority.
+pub fn suggest<'a>(words: &'a [Word], k: Kanji, yomi: &str) -> Vec<&'a Word> {
+    let mut found: Vec<&Word> = words
+        .iter()
+        .filter(|w| w.word.chars().count() > 1)
+        .filter(|w| w.word.contains(k.get()) && w.reading.contains(yomi))
+        .collect();
+    found.sort_by_key(|w| w.word.chars().count());
+    found
+}
+
+/// The location of the imported words that accompany a data file.
+#[cfg(feature = "fs")]
+pub fn jmdict_path(data: &Path) -> PathBuf {
+    data.with_file_name(JMDICT_FILE)
+}
+
+/// Read imported words, yielding none if they've never been imported.
+#[cfg(feature = "fs")]
+pub fn read_words(path: &Path) -> Result<Vec<Word>, Error> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(Error::Json),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
+/// Write imported words.
+#[cfg(feature = "fs")]
+pub fn write_words(path: &Path, words: &[Word]) -> Result<(), Error> {
+    let raw = serde_json::to_string(words).map_err(Error::Json)?;
+    std::fs::write(path, raw).map_err(Error::Io)
+}
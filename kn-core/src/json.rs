@@ -0,0 +1,357 @@
+//! Reading and writing the JSON data file.
+
+use crate::{Entry, Error};
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter};
+use std::io::{BufReader, BufWriter, Read, Write};
+
+#[cfg(all(feature = "graph", feature = "fs"))]
+use crate::Rules;
+#[cfg(feature = "graph")]
+use crate::DB;
+#[cfg(feature = "fs")]
+use std::fs::{self, File};
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+/// Databases with more entries than this are written in "large mode", without
+/// even the spaces that smaller files have within each line.
+pub const LARGE_DB: usize = 10_000;
+
+/// Read every `Entry` from a data file, in the order they were written, with
+/// readings normalized to Hiragana.
+#[cfg(feature = "fs")]
+pub fn read_entries(path: &Path) -> Result<Vec<Entry>, Error> {
+    #[cfg(feature = "sqlite")]
+    if crate::store::is_sqlite(path) {
+        return crate::store::Sqlite::open(path)?.read_entries();
+    }
+
+    if path.is_dir() {
+        return crate::shard::read_entries_dir(path);
+    }
+
+    let file = File::open(path).map_err(Error::Io)?;
+    read_entries_from(file)
+}
+
+/// Like `read_entries`, but from any source of JSON, e.g. stdin. Older layouts
+/// are upgraded along the way.
+pub fn read_entries_from<R: Read>(reader: R) -> Result<Vec<Entry>, Error> {
+    let mut entries = crate::migrate::read(BufReader::new(reader))?;
+    entries.iter_mut().for_each(|e| e.normalize());
+    Ok(entries)
+}
+
+/// Write entries into a file by order of their `Kanji`.
+///
+/// The entries are first written in full to a temporary file beside the real
+/// one, which then replaces it. A crash partway through thus never leaves the
+/// data file half-written.
+#[cfg(feature = "fs")]
+pub fn write_entries(path: &Path, entries: Vec<Entry>) -> Result<(), Error> {
+    // A transaction gives the same all-or-nothing guarantee.
+    #[cfg(feature = "sqlite")]
+    if crate::store::is_sqlite(path) {
+        return crate::store::Sqlite::create(path)?.write_entries(entries);
+    }
+
+    if path.is_dir() {
+        return crate::shard::write_entries_dir(path, entries);
+    }
+
+    let tmp = temp_path(path);
+    let result = write_temp(&tmp, entries)
+        .and_then(|_| fs::rename(&tmp, path).map_err(|e| Error::Persist(path.to_path_buf(), e)));
+
+    if result.is_err() {
+        // Don't leave a stray copy lying around. The original is untouched.
+        let _ = fs::remove_file(&tmp);
+    }
+
+    result
+}
+
+/// A sibling of the given file, so that renaming stays on one filesystem.
+#[cfg(feature = "fs")]
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Write and sync the entries to a fresh file.
+#[cfg(feature = "fs")]
+fn write_temp(tmp: &Path, entries: Vec<Entry>) -> Result<(), Error> {
+    let mut file = File::create(tmp).map_err(Error::Io)?;
+    write_entries_to(&mut file, entries)?;
+    file.sync_all()
+        .map_err(|e| Error::Persist(tmp.to_path_buf(), e))
+}
+
+/// Like `write_entries`, but to any destination, e.g. stdout. The JSON is
+/// exactly what would have been written to a data file.
+///
+/// Each `Entry` is written on a line of its own, with its fields always in the
+/// same order, so that adding or changing one touches only its own line in a
+/// diff.
+pub fn write_entries_to<W: Write>(writer: W, mut entries: Vec<Entry>) -> Result<(), Error> {
+    entries.sort_by_key(|e| e.kanji);
+    entries.iter_mut().for_each(|e| {
+        e.oya.sort();
+        e.normalize();
+    });
+
+    let mut w = BufWriter::new(writer);
+
+    if entries.len() > LARGE_DB {
+        write_lines(&mut w, &entries, CompactFormatter)?;
+    } else {
+        write_lines(&mut w, &entries, Spaced)?;
+    }
+
+    w.flush().map_err(Error::Io)
+}
+
+/// Stream entries out one line at a time, at the newest version.
+fn write_lines<W, F>(mut w: W, entries: &[Entry], formatter: F) -> Result<(), Error>
+where
+    W: Write,
+    F: Formatter + Clone,
+{
+    writeln!(w, "{{\"version\":{},\"entries\":[", crate::migrate::CURRENT)?;
+
+    for (i, e) in entries.iter().enumerate() {
+        if i > 0 {
+            w.write_all(b",\n")?;
+        }
+        let mut ser = serde_json::Serializer::with_formatter(&mut w, formatter.clone());
+        e.serialize(&mut ser).map_err(Error::Json)?;
+    }
+
+    w.write_all(b"\n]}\n")?;
+    Ok(())
+}
+
+/// Compact JSON, but with a space after every `:` and `,` to be easier on the
+/// eye.
+#[derive(Clone)]
+struct Spaced;
+
+impl Formatter for Spaced {
+    fn begin_array_value<W>(&mut self, w: &mut W, first: bool) -> std::io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            w.write_all(b", ")
+        }
+    }
+
+    fn begin_object_key<W>(&mut self, w: &mut W, first: bool) -> std::io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            w.write_all(b", ")
+        }
+    }
+
+    fn begin_object_value<W>(&mut self, w: &mut W) -> std::io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        w.write_all(b": ")
+    }
+}
+
+/// Open a data file and bring the whole "database" into memory.
+#[cfg(all(feature = "graph", feature = "fs"))]
+pub fn open_db(path: &Path) -> Result<DB, Error> {
+    open_db_with(path, Rules::default())
+}
+
+/// Open a directory of JSON shards, merging them into one "database".
+#[cfg(all(feature = "graph", feature = "fs"))]
+pub fn open_db_dir(dir: &Path) -> Result<DB, Error> {
+    let hm = crate::shard::read_entries_dir(dir)?
+        .into_iter()
+        .map(|e| (e.kanji, e))
+        .collect();
+
+    DB::new(hm)
+}
+
+/// Like `open_db`, but from any source of JSON, e.g. stdin. No cache is
+/// involved.
+#[cfg(feature = "graph")]
+pub fn open_db_from_reader<R: Read>(reader: R) -> Result<DB, Error> {
+    let hm = read_entries_from(reader)?
+        .into_iter()
+        .map(|e| (e.kanji, e))
+        .collect();
+
+    DB::new(hm)
+}
+
+/// Like `open_db`, but classify edges with some optional rules turned on.
+///
+/// With the `cache` feature, a fresh cache beside the data file is used
+/// instead if there is one, and is otherwise (re)written on a best-effort
+/// basis.
+#[cfg(all(feature = "graph", feature = "fs"))]
+pub fn open_db_with(path: &Path, rules: Rules) -> Result<DB, Error> {
+    // A directory's own modification time says nothing about its files.
+    #[cfg(feature = "cache")]
+    if !path.is_dir() {
+        if let Some(db) = crate::cache::load(path, rules) {
+            return Ok(db);
+        }
+
+        // Taken before reading, so that a write in the meantime can only make
+        // the cache look stale, never fresh.
+        let stamp = crate::cache::stamp(path);
+        let db = build_db(path, rules)?;
+        if let Some(s) = stamp {
+            let _ = crate::cache::store(path, s, rules, &db);
+        }
+        return Ok(db);
+    }
+
+    build_db(path, rules)
+}
+
+/// Read a data file and build its `DB` from scratch.
+#[cfg(all(feature = "graph", feature = "fs"))]
+pub(crate) fn build_db(path: &Path, rules: Rules) -> Result<DB, Error> {
+    let hm = read_entries(path)?
+        .into_iter()
+        .map(|e| (e.kanji, e))
+        .collect();
+
+    DB::new_with(hm, rules)
+}
+
+/// Write a Kanji "database" into a file by order of its `Kanji`.
+#[cfg(all(feature = "graph", feature = "fs"))]
+pub fn write_db(path: &Path, db: DB) -> Result<(), Error> {
+    write_entries(path, db.entries.into_values().collect())
+}
+
+/// Like `write_db`, but to any destination, e.g. stdout.
+#[cfg(feature = "graph")]
+pub fn write_db_to_writer<W: Write>(writer: W, db: DB) -> Result<(), Error> {
+    write_entries_to(writer, db.entries.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Kanji, Onyomi};
+
+    fn entry(json: &str) -> Entry {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn written(entries: Vec<Entry>) -> String {
+        let mut out = Vec::new();
+        write_entries_to(&mut out, entries).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn one_entry_per_line() {
+        let text = written(vec![
+            entry(r#"{"kanji":"林","oya":["木"],"onyomi":["りん"]}"#),
+            entry(r#"{"kanji":"木","onyomi":["もく","ぼく"]}"#),
+        ]);
+        let expected = format!(
+            "{{\"version\":{},\"entries\":[\n{}\n{}\n]}}\n",
+            crate::migrate::CURRENT,
+            r#"{"kanji": "木", "onyomi": ["もく", "ぼく"]},"#,
+            r#"{"kanji": "林", "oya": ["木"], "onyomi": ["りん"]}"#,
+        );
+
+        assert_eq!(text, expected);
+        assert_eq!(read_entries_from(text.as_bytes()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn additions_touch_one_line() {
+        let before = written(vec![
+            entry(r#"{"kanji":"木","onyomi":["もく"]}"#),
+            entry(r#"{"kanji":"林","oya":["木"],"onyomi":["りん"]}"#),
+        ]);
+        let after = written(vec![
+            entry(r#"{"kanji":"木","onyomi":["もく"]}"#),
+            entry(r#"{"kanji":"本","onyomi":["ほん"]}"#),
+            entry(r#"{"kanji":"林","oya":["木"],"onyomi":["りん"]}"#),
+        ]);
+        let old: Vec<&str> = before.lines().collect();
+        let new: Vec<&str> = after.lines().collect();
+
+        assert_eq!(new.len(), old.len() + 1);
+        assert!(old.iter().all(|l| new.contains(l)));
+    }
+
+    /// More than a `u16` could index, over every block of the standard set
+    /// and its extensions.
+    const SOAK: usize = 70_000;
+
+    /// A database of every Kanji up to `SOAK`, each the child of the one
+    /// halfway before it.
+    fn soak_entries() -> Vec<Entry> {
+        let readings = ["こう", "ごう", "きょう", "よう", "かん", "か"];
+        let kanji: Vec<Kanji> = ('\u{3400}'..='\u{3134a}')
+            .filter_map(Kanji::new)
+            .take(SOAK)
+            .collect();
+        assert_eq!(kanji.len(), SOAK);
+
+        kanji
+            .iter()
+            .enumerate()
+            .map(|(i, k)| Entry {
+                oya: (i > 0).then(|| kanji[i / 2]).into_iter().collect(),
+                onyomi: vec![Onyomi::from(readings[i % readings.len()])],
+                ..Entry::new(*k)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn soak_round_trip() {
+        let entries = soak_entries();
+        let mut out = Vec::new();
+        write_entries_to(&mut out, entries.clone()).unwrap();
+
+        // One compact line per entry, plus the opening and closing ones, as
+        // the database is past `LARGE_DB`.
+        let text = std::str::from_utf8(&out).unwrap();
+        assert_eq!(text.lines().count(), SOAK + 2);
+        assert!(text
+            .lines()
+            .all(|l| !l.starts_with(' ') && !l.contains(": ")));
+
+        let back = read_entries_from(out.as_slice()).unwrap();
+        assert_eq!(back, entries);
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn soak_db() {
+        let db = DB::new(soak_entries().into_iter().map(|e| (e.kanji, e)).collect()).unwrap();
+        assert_eq!(db.graph.node_count(), SOAK);
+        assert_eq!(db.graph.edge_count(), SOAK - 1);
+        assert!(db.readings.len() <= 6);
+
+        let mut out = Vec::new();
+        write_db_to_writer(&mut out, db).unwrap();
+        let back = open_db_from_reader(out.as_slice()).unwrap();
+        assert_eq!(back.graph.edge_count(), SOAK - 1);
+    }
+}
@@ -0,0 +1,133 @@
+//! Reading data from the original, line-based KANJIDIC dictionary file.
+//!
+//! Each line holds a Kanji followed by space-separated fields. 音読み appear as
+//! Katakana fields, 訓読み as Hiragana ones, and anything after the `T1`/`T2` markers are name readings
+//! (名乗り) that we don't care about.
+
+use crate::{utils, Kanji};
+use std::collections::HashMap;
+
+#[cfg(feature = "graph")]
+use crate::DB;
+
+/// The 音読み of every Kanji in a UTF-8 KANJIDIC file, in Hiragana.
+pub fn onyomi_table(raw: &str) -> HashMap<Kanji, Vec<String>> {
+    raw.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let kanji = fields.next()?.chars().next().and_then(Kanji::new)?;
+            let onyomi = fields
+                .take_while(|f| *f != "T1" && *f != "T2")
+                .map(|f| f.trim_matches('-'))
+                .filter(|f| !f.is_empty() && f.chars().all(kanji::is_katakana))
+                .map(utils::to_hiragana)
+                .collect();
+
+            Some((kanji, onyomi))
+        })
+        .collect()
+}
+
+/// The 訓読み of every Kanji in a UTF-8 KANJIDIC file, as written there, e.g.
+/// `ほっ.する` with a `.` before the okurigana, or `-ほし` as a suffix.
+pub fn kunyomi_table(raw: &str) -> HashMap<Kanji, Vec<String>> {
+    raw.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let kanji = fields.next()?.chars().next().and_then(Kanji::new)?;
+            let kunyomi = fields
+                .take_while(|f| *f != "T1" && *f != "T2")
+                .filter(|f| f.chars().any(kanji::is_hiragana))
+                .filter(|f| {
+                    f.chars()
+                        .all(|c| kanji::is_hiragana(c) || c == '.' || c == '-')
+                })
+                .map(|f| f.to_string())
+                .collect();
+
+            Some((kanji, kunyomi))
+        })
+        .collect()
+}
+
+/// How common each Kanji is, as the usage rank in its `F` field, where 1 is the
+/// most common. Only some 2,500 Kanji are ranked.
+pub fn frequency_table(raw: &str) -> HashMap<Kanji, u32> {
+    raw.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let kanji = fields.next()?.chars().next().and_then(Kanji::new)?;
+            let rank = fields
+                .take_while(|f| *f != "T1" && *f != "T2")
+                .find_map(|f| f.strip_prefix('F')?.parse().ok())?;
+
+            Some((kanji, rank))
+        })
+        .collect()
+}
+
+/// The English meanings of every Kanji in a UTF-8 KANJIDIC file, each written
+/// there within braces, like `{water}`.
+pub fn meaning_table(raw: &str) -> HashMap<Kanji, Vec<String>> {
+    raw.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let kanji = line.chars().next().and_then(Kanji::new)?;
+            let meanings = line
+                .split('{')
+                .skip(1)
+                .filter_map(|f| f.split_once('}'))
+                .map(|(m, _)| m.to_string())
+                .collect();
+
+            Some((kanji, meanings))
+        })
+        .collect()
+}
+
+/// Disagreements between an `Entry`'s 音読み and those of a dictionary.
+pub struct ReadingAudit {
+    pub kanji: Kanji,
+    /// Readings the dictionary has that the `Entry` doesn't.
+    pub missing: Vec<String>,
+    /// Readings the `Entry` has that the dictionary doesn't.
+    pub extra: Vec<String>,
+}
+
+#[cfg(feature = "graph")]
+impl DB {
+    /// Compare every entry's 音読み against those of a dictionary, yielding only
+    /// those that disagree. Kanji unknown to the dictionary are skipped.
+    pub fn audit_readings(&self, table: &HashMap<Kanji, Vec<String>>) -> Vec<ReadingAudit> {
+        let mut audits: Vec<ReadingAudit> = self
+            .entries
+            .values()
+            .filter_map(|e| {
+                let known = table.get(&e.kanji)?;
+                let missing: Vec<String> = known
+                    .iter()
+                    .filter(|y| !e.onyomi.iter().any(|o| o == *y))
+                    .cloned()
+                    .collect();
+                let extra: Vec<String> = e
+                    .onyomi
+                    .iter()
+                    .filter(|y| !known.iter().any(|k| y.reading == *k))
+                    .map(|y| y.reading.to_string())
+                    .collect();
+
+                (!missing.is_empty() || !extra.is_empty()).then_some(ReadingAudit {
+                    kanji: e.kanji,
+                    missing,
+                    extra,
+                })
+            })
+            .collect();
+
+        audits.sort_by_key(|a| a.kanji);
+        audits
+    }
+}
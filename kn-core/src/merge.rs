@@ -0,0 +1,195 @@
+//! Combining two sets of entries, as when pulling additions from someone
+//! else's copy of the data into your own.
+
+use crate::{Entry, Kanji};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Which copy of the data to side with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The data being merged into.
+    Ours,
+    /// The data being merged in.
+    Theirs,
+}
+
+/// A `Kanji` whose two entries disagree on its readings or parents.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub ours: Entry,
+    pub theirs: Entry,
+}
+
+impl Conflict {
+    /// The Kanji the two entries are for.
+    pub fn kanji(&self) -> Kanji {
+        self.ours.kanji
+    }
+
+    /// Settle the conflict by taking one side's entry whole.
+    pub fn resolve(self, side: Side) -> Entry {
+        match side {
+            Side::Ours => self.ours,
+            Side::Theirs => self.theirs,
+        }
+    }
+}
+
+/// Two sets of entries brought together, but for the conflicts still to be
+/// resolved.
+#[derive(Debug, Clone)]
+pub struct Merge {
+    /// Every entry that needed no decision.
+    pub merged: Vec<Entry>,
+    /// Kanji that only `theirs` had.
+    pub added: Vec<Kanji>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Bring two sets of entries together. Kanji with an entry on only one side
+/// keep it. Where both sides agree on a Kanji's readings and parents, their
+/// entries are combined: lists like 代表 and notes are joined, and `ours` wins
+/// any pinned relationship they both have. Joined lists keep the order `ours`
+/// had, followed by anything new from `theirs` in the order `theirs` had it, so
+/// the same two lists in different orders come out in `ours`' order. Otherwise,
+/// it's a conflict.
+pub fn merge(ours: Vec<Entry>, theirs: Vec<Entry>) -> Merge {
+    let mut theirs: BTreeMap<Kanji, Entry> = theirs.into_iter().map(|e| (e.kanji, e)).collect();
+    let mut merged = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for e in ours {
+        match theirs.remove(&e.kanji) {
+            None => merged.push(e),
+            Some(t) if agree(&e, &t) => merged.push(combine(e, t)),
+            Some(t) => conflicts.push(Conflict { ours: e, theirs: t }),
+        }
+    }
+
+    let added = theirs.keys().copied().collect();
+    merged.extend(theirs.into_values());
+    conflicts.sort_by_key(|c| c.kanji());
+
+    Merge {
+        merged,
+        added,
+        conflicts,
+    }
+}
+
+/// Do two entries have the same readings and parents, in whatever order?
+fn agree(a: &Entry, b: &Entry) -> bool {
+    let readings =
+        |e: &Entry| -> BTreeSet<String> { e.onyomi.iter().map(|y| y.to_string()).collect() };
+    let parents = |e: &Entry| -> BTreeSet<Kanji> { e.oya.iter().copied().collect() };
+
+    readings(a) == readings(b) && parents(a) == parents(b)
+}
+
+/// One entry with everything from another that it doesn't already have.
+fn combine(mut ours: Entry, theirs: Entry) -> Entry {
+    join(&mut ours.kakushi_oya, theirs.kakushi_oya);
+    join(&mut ours.daihyou, theirs.daihyou);
+    join(&mut ours.notes, theirs.notes);
+    join(&mut ours.tags, theirs.tags);
+    join(&mut ours.variants, theirs.variants);
+    join(&mut ours.nite, theirs.nite);
+    theirs.oya_overrides.into_iter().for_each(|(k, i)| {
+        ours.oya_overrides.entry(k).or_insert(i);
+    });
+    ours.added = match (ours.added, theirs.added) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    ours
+}
+
+/// Append what `ours` lacks from `theirs`, keeping the order of both, `ours`
+/// first.
+fn join<T: PartialEq>(ours: &mut Vec<T>, theirs: Vec<T>) {
+    theirs.into_iter().for_each(|x| {
+        if !ours.contains(&x) {
+            ours.push(x);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Inherit, Onyomi};
+
+    fn k(c: char) -> Kanji {
+        Kanji::new(c).unwrap()
+    }
+
+    fn entry(c: char, oya: &str, onyomi: &[&str]) -> Entry {
+        Entry {
+            oya: oya.chars().map(k).collect(),
+            onyomi: onyomi.iter().map(|y| Onyomi::from(*y)).collect(),
+            ..Entry::new(k(c))
+        }
+    }
+
+    fn kanji(es: &[Entry]) -> Vec<Kanji> {
+        let mut ks: Vec<Kanji> = es.iter().map(|e| e.kanji).collect();
+        ks.sort();
+        ks
+    }
+
+    #[test]
+    fn one_side_only() {
+        let m = merge(
+            vec![entry('工', "", &["こう"])],
+            vec![entry('水', "", &["すい"]), entry('江', "工水", &["こう"])],
+        );
+
+        assert_eq!(kanji(&m.merged), vec![k('工'), k('水'), k('江')]);
+        assert_eq!(m.added, vec![k('水'), k('江')]);
+        assert!(m.conflicts.is_empty());
+    }
+
+    #[test]
+    fn agreeing_entries_combine() {
+        let ours = Entry {
+            daihyou: vec!["江戸".to_string(), "長江".to_string()],
+            tags: vec!["地名".to_string()],
+            oya_overrides: BTreeMap::from([(k('工'), Inherit::Same)]),
+            ..entry('江', "工水", &["こう", "ごう"])
+        };
+        let theirs = Entry {
+            daihyou: vec!["入江".to_string(), "長江".to_string(), "江戸".to_string()],
+            oya_overrides: BTreeMap::from([(k('工'), Inherit::None), (k('水'), Inherit::None)]),
+            ..entry('江', "水工", &["ごう", "こう"])
+        };
+        let m = merge(vec![ours], vec![theirs]);
+
+        assert!(m.conflicts.is_empty());
+        assert!(m.added.is_empty());
+        let e = &m.merged[0];
+        // Our order first, then whatever was new to us.
+        assert_eq!(e.daihyou, vec!["江戸", "長江", "入江"]);
+        assert_eq!(e.tags, vec!["地名"]);
+        assert_eq!(e.oya, vec![k('工'), k('水')]);
+        assert_eq!(e.oya_overrides[&k('工')], Inherit::Same);
+        assert_eq!(e.oya_overrides[&k('水')], Inherit::None);
+    }
+
+    #[test]
+    fn conflicts() {
+        let ours = vec![entry('江', "工水", &["こう"]), entry('工', "", &["こう"])];
+        let theirs = vec![entry('江', "工", &["こう"]), entry('工', "", &["く"])];
+        let m = merge(ours, theirs);
+
+        assert!(m.merged.is_empty());
+        assert_eq!(m.conflicts.len(), 2);
+        let mut cs = m.conflicts.into_iter();
+        let c = cs.next().unwrap();
+        assert_eq!(c.kanji(), k('工'));
+        assert_eq!(c.resolve(Side::Theirs).onyomi, vec![Onyomi::from("く")]);
+        let c = cs.next().unwrap();
+        assert_eq!(c.kanji(), k('江'));
+        assert_eq!(c.resolve(Side::Ours).oya, vec![k('工'), k('水')]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Versions of the data file's layout, and upgrading old ones.
+//!
+//! The first data files were a bare JSON array of entries, and are taken to be
+//! version 1. Since version 2 the array sits under an `entries` key beside the
+//! version number:
+//!
+//! ```json
+//! { "version": 2, "entries": [ ... ] }
+//! ```
+//!
+//! Since version 3 an 音読み may also be an object naming its stratum, as
+//! described on `Onyomi`. Version 2 files are already valid version 3 files,
+//! but not the other way around, so older programs must refuse the new ones.
+//!
+//! Files are upgraded one version at a time, in memory, as they're read, so any
+//! older file can always be loaded. Writing always produces the newest version.
+//! Only the header is looked at to tell the version, and files whose entries
+//! already have the current shape are read straight into `Entry` values, never
+//! held whole as JSON.
+//!
+//! Fields added to `Entry` after the fact, like `kakushi_oya`, are optional and
+//! need no step of their own. A step is only needed when existing data must be
+//! reshaped.
+
+use crate::{Entry, Error};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::BufRead;
+
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// The version that is written.
+pub const CURRENT: u64 = 3;
+
+/// Upgrades from one version to the next. The first lifts version 1 to 2, and
+/// so on.
+const STEPS: [fn(Value) -> Value; CURRENT as usize - 1] = [wrap, strata];
+
+/// 1 -> 2: Put the bare array under `entries`.
+fn wrap(v: Value) -> Value {
+    json!({ "version": 2, "entries": v })
+}
+
+/// 2 -> 3: Nothing to reshape, since plain readings are still allowed.
+fn strata(mut v: Value) -> Value {
+    v["version"] = json!(3);
+    v
+}
+
+/// The oldest version whose entries already have the current shape, and so
+/// need no upgrading. Raise it alongside `CURRENT` when a step reshapes them.
+const SAME_SHAPE: u64 = 1;
+
+/// A data file of version 2 or later, skipping over the version itself.
+#[derive(Deserialize)]
+struct Versioned {
+    entries: Vec<Entry>,
+}
+
+/// The layout version of a data file, judged from its first few bytes without
+/// consuming them. `None` if they don't say, say because `version` isn't the
+/// first key, as it always is in files written here.
+pub fn peek_version<R: BufRead>(reader: &mut R) -> Result<Option<u64>, Error> {
+    let buf = reader.fill_buf().map_err(Error::Io)?;
+    let text = String::from_utf8_lossy(buf);
+    let text = text.trim_start();
+
+    if text.starts_with('[') {
+        return Ok(Some(1));
+    }
+
+    let version = text
+        .strip_prefix('{')
+        .and_then(|t| t.trim_start().strip_prefix("\"version\""))
+        .and_then(|t| t.trim_start().strip_prefix(':'))
+        .map(|t| t.trim_start())
+        .and_then(|t| {
+            let digits = t.find(|c: char| !c.is_ascii_digit())?;
+            t[..digits].parse().ok()
+        });
+
+    Ok(version)
+}
+
+/// Read the entries of a data file of any known version, upgrading them as
+/// needed.
+pub fn read<R: BufRead>(mut reader: R) -> Result<Vec<Entry>, Error> {
+    match peek_version(&mut reader)? {
+        Some(v) if v == 0 || v > CURRENT => Err(Error::UnknownVersion(v)),
+        // Nothing to upgrade, so the entries can be read as they come.
+        Some(v) if v >= SAME_SHAPE => {
+            let entries = match v {
+                1 => serde_json::from_reader(reader),
+                _ => serde_json::from_reader(reader).map(|v: Versioned| v.entries),
+            };
+            entries.map_err(Error::Json)
+        }
+        _ => upgrade(serde_json::from_reader(reader).map_err(Error::Json)?),
+    }
+}
+
+/// The layout version of some parsed data file.
+pub fn version_of(v: &Value) -> u64 {
+    match v {
+        Value::Array(_) => 1,
+        Value::Object(m) => m.get("version").and_then(Value::as_u64).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// The layout version of a data file, or for a directory of shards, that of
+/// its oldest shard.
+#[cfg(feature = "fs")]
+pub fn file_version(path: &Path) -> Result<u64, Error> {
+    if path.is_dir() {
+        return crate::shard::shards(path)?
+            .iter()
+            .map(|s| file_version(s))
+            .try_fold(CURRENT, |acc, v| v.map(|v| acc.min(v)));
+    }
+
+    let file = std::fs::File::open(path).map_err(Error::Io)?;
+    let mut reader = std::io::BufReader::new(file);
+
+    match peek_version(&mut reader)? {
+        Some(v) => Ok(v),
+        None => {
+            let v = serde_json::from_reader(reader).map_err(Error::Json)?;
+            Ok(version_of(&v))
+        }
+    }
+}
+
+/// Bring parsed data of any known version up to the current one, and yield
+/// its entries.
+pub fn upgrade(mut v: Value) -> Result<Vec<Entry>, Error> {
+    let from = version_of(&v);
+
+    if from == 0 || from > CURRENT {
+        return Err(Error::UnknownVersion(from));
+    }
+
+    for step in &STEPS[from as usize - 1..] {
+        v = step(v);
+    }
+
+    match v {
+        Value::Object(mut m) => {
+            let entries = m.remove("entries").unwrap_or(Value::Array(Vec::new()));
+            serde_json::from_value(entries).map_err(Error::Json)
+        }
+        _ => Err(Error::UnknownVersion(from)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kanji::Kanji;
+
+    fn version(raw: &str) -> Option<u64> {
+        peek_version(&mut raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn peeking() {
+        assert_eq!(version("[{\"kanji\":\"工\"}]"), Some(1));
+        assert_eq!(version("  {\"version\":3,\"entries\":[\n"), Some(3));
+        assert_eq!(version("{ \"version\" : 2 , \"entries\": [] }"), Some(2));
+        assert_eq!(version("{\"entries\":[],\"version\":2}"), None);
+    }
+
+    #[test]
+    fn every_version_reads_the_same() {
+        let entry = r#"{"kanji":"江","oya":["工"],"onyomi":["こう"]}"#;
+        let files = [
+            format!("[{}]", entry),
+            format!("{{\"version\":2,\"entries\":[{}]}}", entry),
+            format!("{{\"version\":3,\"entries\":[\n{}\n]}}", entry),
+            // The version out of place, as a hand-edited file might have it.
+            format!("{{\"entries\":[{}],\"version\":3}}", entry),
+        ];
+
+        for f in files {
+            let entries = read(f.as_bytes()).unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].oya, vec![Kanji::new('工').unwrap()]);
+        }
+    }
+
+    #[test]
+    fn unknown_versions() {
+        let future = format!("{{\"version\":{},\"entries\":[]}}", CURRENT + 1);
+        assert!(matches!(
+            read(future.as_bytes()),
+            Err(Error::UnknownVersion(_))
+        ));
+        assert!(matches!(
+            read("{\"version\":0,\"entries\":[]}".as_bytes()),
+            Err(Error::UnknownVersion(0))
+        ));
+    }
+}
@@ -0,0 +1,341 @@
+//! 音読み, and the wave of borrowing from Chinese that each came with.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// The layer of Chinese a reading was borrowed from. Readings of different
+/// strata often look unrelated even when their characters are, so comparing
+/// them makes many edges seem more irregular than they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Stratum {
+    /// 呉音, the oldest layer, via the south (e.g. 行 ぎょう).
+    #[serde(rename = "go-on")]
+    Goon,
+    /// 漢音, from the Tang capital, via the envoys (e.g. 行 こう).
+    #[serde(rename = "kan-on")]
+    Kanon,
+    /// 唐音, the latest, often via Zen monks (e.g. 行 あん).
+    #[serde(rename = "tou-on")]
+    Touon,
+}
+
+impl Stratum {
+    /// The single Kanji that marks a stratum, e.g. 呉 for `Goon`.
+    pub fn mark(&self) -> char {
+        match self {
+            Stratum::Goon => '呉',
+            Stratum::Kanon => '漢',
+            Stratum::Touon => '唐',
+        }
+    }
+
+    fn from_mark(c: &str) -> Option<Stratum> {
+        match c {
+            "呉" => Some(Stratum::Goon),
+            "漢" => Some(Stratum::Kanon),
+            "唐" => Some(Stratum::Touon),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Stratum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "go-on" | "goon" | "go" => Ok(Stratum::Goon),
+            "kan-on" | "kanon" | "kan" => Ok(Stratum::Kanon),
+            "tou-on" | "touon" | "tou" => Ok(Stratum::Touon),
+            _ => Stratum::from_mark(s).ok_or_else(|| format!("Unknown stratum: {}", s)),
+        }
+    }
+}
+
+/// The text of a reading, shared rather than copied.
+///
+/// There are only a few hundred distinct readings, but thousands of entries
+/// that carry them. Once run through an `Interner`, as a `DB` does with its
+/// entries, every こう is the same allocation, cloning one is only a reference
+/// count, and two readings are usually equal by their pointers alone.
+#[derive(Debug, Clone, PartialOrd, Ord)]
+pub struct Reading(Arc<str>);
+
+impl Reading {
+    /// A reading of its own, not yet shared with any other.
+    pub fn new(s: &str) -> Reading {
+        Reading(Arc::from(s))
+    }
+
+    /// The reading as a plain `str`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Reading {
+    fn eq(&self, other: &Reading) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Reading {}
+
+// By content, like `str`, so that a `Reading` may be looked up by one.
+impl Hash for Reading {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl From<&str> for Reading {
+    fn from(s: &str) -> Self {
+        Reading::new(s)
+    }
+}
+
+impl From<String> for Reading {
+    fn from(s: String) -> Self {
+        Reading::new(&s)
+    }
+}
+
+impl Deref for Reading {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Reading {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Reading {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Reading {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Reading {
+    fn eq(&self, other: &String) -> bool {
+        &*self.0 == other.as_str()
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Reading {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Reading {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        // Borrowing would fail on escaped strings, so this costs one copy.
+        String::deserialize(d).map(Reading::from)
+    }
+}
+
+/// A pool of readings, so that equal ones share a single allocation.
+///
+/// Readings stay in the pool for as long as it lives, so it's best owned by
+/// whatever owns the entries, and dropped with them.
+#[derive(Debug, Default, Clone)]
+pub struct Interner(HashSet<Reading>);
+
+impl Interner {
+    /// The pooled copy of a reading, adding it to the pool if it's new.
+    pub fn intern(&mut self, r: &Reading) -> Reading {
+        match self.0.get(r.as_str()) {
+            Some(pooled) => pooled.clone(),
+            None => {
+                self.0.insert(r.clone());
+                r.clone()
+            }
+        }
+    }
+
+    /// How many distinct readings are in the pool.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A single 音読み, in Hiragana.
+///
+/// In the data file this is a plain string, unless its stratum is known:
+///
+/// ```json
+/// "onyomi": ["こう", { "reading": "ぎょう", "stratum": "go-on" }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "Raw", into = "Raw")]
+pub struct Onyomi {
+    pub reading: Reading,
+    pub stratum: Option<Stratum>,
+}
+
+/// How an `Onyomi` is written to disk.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Raw {
+    Plain(String),
+    Tagged { reading: String, stratum: Stratum },
+}
+
+impl From<Raw> for Onyomi {
+    fn from(raw: Raw) -> Self {
+        match raw {
+            Raw::Plain(reading) => Onyomi::from(reading),
+            Raw::Tagged { reading, stratum } => Onyomi {
+                reading: reading.into(),
+                stratum: Some(stratum),
+            },
+        }
+    }
+}
+
+impl From<Onyomi> for Raw {
+    fn from(y: Onyomi) -> Self {
+        match y.stratum {
+            None => Raw::Plain(y.reading.to_string()),
+            Some(stratum) => Raw::Tagged {
+                reading: y.reading.to_string(),
+                stratum,
+            },
+        }
+    }
+}
+
+impl Onyomi {
+    /// The reading itself, without its stratum.
+    pub fn as_str(&self) -> &str {
+        &self.reading
+    }
+
+    /// Read a reading as written by `Display`, e.g. `ぎょう` or `ぎょう(呉)`.
+    pub fn parse(s: &str) -> Onyomi {
+        let tagged = s
+            .strip_suffix(')')
+            .and_then(|s| s.split_once('('))
+            .and_then(|(y, m)| Some((y, Stratum::from_mark(m)?)));
+
+        match tagged {
+            Some((y, stratum)) => Onyomi {
+                reading: y.into(),
+                stratum: Some(stratum),
+            },
+            None => Onyomi::from(s),
+        }
+    }
+
+    /// Whether two readings may be compared when classifying an edge: only
+    /// if they're of the same stratum, or either one's is unknown.
+    pub fn comparable(&self, other: &Onyomi) -> bool {
+        match (self.stratum, other.stratum) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+impl From<String> for Onyomi {
+    fn from(reading: String) -> Self {
+        Onyomi {
+            reading: reading.into(),
+            stratum: None,
+        }
+    }
+}
+
+impl From<&str> for Onyomi {
+    fn from(reading: &str) -> Self {
+        Onyomi {
+            reading: reading.into(),
+            stratum: None,
+        }
+    }
+}
+
+impl Deref for Onyomi {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.reading
+    }
+}
+
+impl PartialEq<str> for Onyomi {
+    fn eq(&self, other: &str) -> bool {
+        self.reading == other
+    }
+}
+
+impl PartialEq<String> for Onyomi {
+    fn eq(&self, other: &String) -> bool {
+        self.reading == *other
+    }
+}
+
+impl fmt::Display for Onyomi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stratum {
+            None => write!(f, "{}", self.reading),
+            Some(s) => write!(f, "{}({})", self.reading, s.mark()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_round_trip() {
+        let y: Onyomi = serde_json::from_str("\"こう\"").unwrap();
+        assert_eq!(y, Onyomi::from("こう"));
+        assert_eq!(serde_json::to_string(&y).unwrap(), "\"こう\"");
+    }
+
+    #[test]
+    fn tagged_round_trip() {
+        let raw = r#"{"reading":"ぎょう","stratum":"go-on"}"#;
+        let y: Onyomi = serde_json::from_str(raw).unwrap();
+        assert_eq!(y.as_str(), "ぎょう");
+        assert_eq!(y.stratum, Some(Stratum::Goon));
+        assert_eq!(serde_json::to_string(&y).unwrap(), raw);
+    }
+
+    #[test]
+    fn mixed_round_trip() {
+        let raw = r#"["こう",{"reading":"あん","stratum":"tou-on"}]"#;
+        let ys: Vec<Onyomi> = serde_json::from_str(raw).unwrap();
+        assert_eq!(ys[0].stratum, None);
+        assert_eq!(ys[1].stratum, Some(Stratum::Touon));
+        assert_eq!(serde_json::to_string(&ys).unwrap(), raw);
+    }
+}
@@ -0,0 +1,504 @@
+//! The sound system of 音読み, as written in Hiragana: morae, voicing, vowels,
+//! and consonants, and the ways in which two readings can resemble each other.
+//!
+//! None of this depends on the rest of the crate, so it's available with no
+//! features enabled.
+
+/// Are two readings the same but for the voicing of their first kana? (e.g.
+/// こく→ごく)
+pub fn is_voiced_pair(a: &str, b: &str) -> bool {
+    let (ma, mb) = (morae(a), morae(b));
+
+    ma.len() == mb.len()
+        && ma
+            .first()
+            .zip(mb.first())
+            .map(|(x, y)| {
+                let (mut xs, mut ys) = (x.chars(), y.chars());
+                xs.next()
+                    .zip(ys.next())
+                    .and_then(|(x, y)| voiced_char(x).map(|c| c == y))
+                    .unwrap_or(false)
+                    && xs.eq(ys)
+            })
+            .unwrap_or(false)
+        && ma.iter().skip(1).eq(mb.iter().skip(1))
+}
+
+/// Like `is_voiced_pair`, but for the は行, where voicing yields either B or P.
+/// These are kept separate since は行 doesn't follow proper voicing rules, but
+/// between a parent and child reading (e.g. ふ→ぶ) it's still a real
+/// inheritance.
+pub fn is_rendaku_pair(a: &str, b: &str) -> bool {
+    let (ma, mb) = (morae(a), morae(b));
+
+    ma.len() == mb.len()
+        && ma
+            .first()
+            .zip(mb.first())
+            .map(|(x, y)| {
+                let (mut xs, mut ys) = (x.chars(), y.chars());
+                xs.next()
+                    .zip(ys.next())
+                    .map(|(x, y)| ha_gyou(x).is_some() && ha_gyou(x) == ha_gyou(y) && x != y)
+                    .unwrap_or(false)
+                    && xs.eq(ys)
+            })
+            .unwrap_or(false)
+        && ma.iter().skip(1).eq(mb.iter().skip(1))
+}
+
+/// The unvoiced は行 kana that a given H, B, or P kana belongs to.
+fn ha_gyou(c: char) -> Option<char> {
+    match c {
+        'は' | 'ば' | 'ぱ' => Some('は'),
+        'ひ' | 'び' | 'ぴ' => Some('ひ'),
+        'ふ' | 'ぶ' | 'ぷ' => Some('ふ'),
+        'へ' | 'べ' | 'ぺ' => Some('へ'),
+        'ほ' | 'ぼ' | 'ぽ' => Some('ほ'),
+        _ => None,
+    }
+}
+
+/// The other half of a voiced pair: the voiced form of an unvoiced kana, or the
+/// unvoiced form of a voiced one. (e.g. か→が, が→か)
+///
+/// は行 is excluded on purpose, since it doesn't follow proper voicing rules,
+/// and no 音読み start with P while on their own. Example: 一票 doesn't count
+/// since the P is "dynamic" from being paired with 一, and indeed dictionaries
+/// don't list ぴょう as a reading for 票. See `devoice` for a one-way mapping
+/// that includes them.
+pub fn voiced_char(c: char) -> Option<char> {
+    match c {
+        'か' => Some('が'),
+        'き' => Some('ぎ'),
+        'く' => Some('ぐ'),
+        'け' => Some('げ'),
+        'こ' => Some('ご'),
+        'が' => Some('か'),
+        'ぎ' => Some('き'),
+        'ぐ' => Some('く'),
+        'げ' => Some('け'),
+        'ご' => Some('こ'),
+        'さ' => Some('ざ'),
+        'し' => Some('じ'),
+        'す' => Some('ず'),
+        'せ' => Some('ぜ'),
+        'そ' => Some('ぞ'),
+        'ざ' => Some('さ'),
+        'じ' => Some('し'),
+        'ず' => Some('す'),
+        'ぜ' => Some('せ'),
+        'ぞ' => Some('そ'),
+        'た' => Some('だ'),
+        'ち' => Some('ぢ'),
+        'つ' => Some('づ'),
+        'て' => Some('で'),
+        'と' => Some('ど'),
+        'だ' => Some('た'),
+        'ぢ' => Some('ち'),
+        'づ' => Some('つ'),
+        'で' => Some('て'),
+        'ど' => Some('と'),
+        _ => None,
+    }
+}
+
+/// Do two readings differ only in the consonant of their first mora? (e.g.
+/// こく→よく)
+pub fn is_rhyme(a: &str, b: &str) -> bool {
+    let (ma, mb) = (morae(a), morae(b));
+
+    ma.len() == mb.len()
+        && ma
+            .first()
+            .zip(mb.first())
+            .map(|(x, y)| mora_vowel(x).is_some() && mora_vowel(x) == mora_vowel(y))
+            .unwrap_or(false)
+        && ma.iter().skip(1).eq(mb.iter().skip(1))
+}
+
+/// Is one reading the other with its final mora clipped, either mutated to っ
+/// or dropped entirely? (e.g. がく→がっ, かん→か)
+pub fn is_clipped(a: &str, b: &str) -> bool {
+    let (ma, mb) = (morae(a), morae(b));
+    let (long, short) = if ma.len() >= mb.len() {
+        (ma, mb)
+    } else {
+        (mb, ma)
+    };
+
+    match (long.split_last(), short.split_last()) {
+        (Some((l, lrest)), Some((s, srest))) if long.len() == short.len() => {
+            l != s && (*l == "っ" || *s == "っ") && lrest == srest
+        }
+        (Some((l, lrest)), Some(_)) if long.len() == short.len() + 1 => {
+            matches!(*l, "ん" | "く" | "き" | "つ" | "ち") && lrest == &short[..]
+        }
+        _ => false,
+    }
+}
+
+/// Split a reading into its morae, keeping any small kana (拗音) attached to
+/// the kana they modify. (e.g. きょく → きょ, く)
+pub fn morae(s: &str) -> Vec<&str> {
+    let mut morae: Vec<&str> = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        if i > 0 && !is_small_vowel(c) {
+            morae.push(&s[start..i]);
+            start = i;
+        }
+    }
+
+    if start < s.len() {
+        morae.push(&s[start..]);
+    }
+
+    morae
+}
+
+/// A reading broken into its phonological parts, written in Kunrei-style
+/// romaji so that each letter stands for one phoneme. The first mora gives the
+/// onset, glide, and vowel, and all later morae form the coda, with ん as `N`
+/// and っ as `Q`. (e.g. きょく → k, y, o, ku)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Syllable {
+    pub onset: &'static str,
+    pub glide: &'static str,
+    pub vowel: &'static str,
+    pub coda: String,
+}
+
+/// Break a Hiragana reading into onset, glide, vowel, and coda. Yields `None`
+/// for anything that isn't plain Hiragana.
+pub fn decompose(s: &str) -> Option<Syllable> {
+    let morae = morae(s);
+    let (first, rest) = morae.split_first()?;
+    let (onset, glide, vowel) = split_mora(first)?;
+
+    let coda = rest
+        .iter()
+        .map(|m| match *m {
+            "ん" => Some("N".to_string()),
+            "っ" => Some("Q".to_string()),
+            m => split_mora(m).map(|(o, g, v)| format!("{}{}{}", o, g, v)),
+        })
+        .collect::<Option<String>>()?;
+
+    Some(Syllable {
+        onset,
+        glide,
+        vowel,
+        coda,
+    })
+}
+
+/// The onset, glide, and vowel of a single mora.
+fn split_mora(m: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    let mut chars = m.chars();
+    let head = chars.next()?;
+    let glide = match chars.next() {
+        None => "",
+        Some('ゃ' | 'ゅ' | 'ょ') => "y",
+        Some('ゎ') => "w",
+        Some(c) if is_small_vowel(c) => "",
+        Some(_) => return None,
+    };
+    let vowel = match mora_vowel(m)? {
+        'あ' => "a",
+        'い' => "i",
+        'う' => "u",
+        'え' => "e",
+        _ => "o",
+    };
+
+    Some((onset(head)?, glide, vowel))
+}
+
+/// The consonant that begins a given Hiragana, in Kunrei-style romaji. Vowels
+/// have the empty onset, and kana that can't begin a mora have none at all.
+pub fn onset(c: char) -> Option<&'static str> {
+    let onset = match c {
+        'あ' | 'い' | 'う' | 'え' | 'お' => "",
+        'か' | 'き' | 'く' | 'け' | 'こ' => "k",
+        'が' | 'ぎ' | 'ぐ' | 'げ' | 'ご' => "g",
+        'さ' | 'し' | 'す' | 'せ' | 'そ' => "s",
+        'ざ' | 'じ' | 'ず' | 'ぜ' | 'ぞ' => "z",
+        'た' | 'ち' | 'つ' | 'て' | 'と' => "t",
+        'だ' | 'ぢ' | 'づ' | 'で' | 'ど' => "d",
+        'な' | 'に' | 'ぬ' | 'ね' | 'の' => "n",
+        'は' | 'ひ' | 'ふ' | 'へ' | 'ほ' => "h",
+        'ば' | 'び' | 'ぶ' | 'べ' | 'ぼ' => "b",
+        'ぱ' | 'ぴ' | 'ぷ' | 'ぺ' | 'ぽ' => "p",
+        'ま' | 'み' | 'む' | 'め' | 'も' => "m",
+        'や' | 'ゆ' | 'よ' => "y",
+        'ら' | 'り' | 'る' | 'れ' | 'ろ' => "r",
+        'わ' | 'を' => "w",
+        _ => return None,
+    };
+
+    Some(onset)
+}
+
+/// Small kana that modify the vowel of the mora before them.
+fn is_small_vowel(c: char) -> bool {
+    matches!(
+        c,
+        'ゃ' | 'ゅ' | 'ょ' | 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'ゎ'
+    )
+}
+
+/// What is the vowel of the given mora? For 拗音 this is decided by the small
+/// kana, so the vowel of きょ is お.
+pub fn mora_vowel(m: &str) -> Option<char> {
+    match m.chars().last()? {
+        'ゃ' | 'ぁ' | 'ゎ' => Some('あ'),
+        'ぃ' => Some('い'),
+        'ゅ' | 'ぅ' => Some('う'),
+        'ぇ' => Some('え'),
+        'ょ' | 'ぉ' => Some('お'),
+        c => vowel(c),
+    }
+}
+
+/// What is the vowel of the given Hiragana?
+pub fn vowel(c: char) -> Option<char> {
+    match c {
+        'あ' | 'か' | 'さ' | 'た' | 'な' | 'は' | 'ま' | 'や' | 'ら' | 'わ' => Some('あ'),
+        'が' | 'ざ' | 'だ' | 'ば' | 'ぱ' => Some('あ'),
+        'い' | 'き' | 'し' | 'ち' | 'に' | 'ひ' | 'み' | 'り' => Some('い'),
+        'ぎ' | 'じ' | 'ぢ' | 'び' | 'ぴ' => Some('い'),
+        'う' | 'く' | 'す' | 'つ' | 'ぬ' | 'ふ' | 'む' | 'ゆ' | 'る' => Some('う'),
+        'ぐ' | 'ず' | 'づ' | 'ぶ' | 'ぷ' => Some('う'),
+        'え' | 'け' | 'せ' | 'て' | 'ね' | 'へ' | 'め' | 'れ' => Some('え'),
+        'げ' | 'ぜ' | 'で' | 'べ' | 'ぺ' => Some('え'),
+        'お' | 'こ' | 'そ' | 'と' | 'の' | 'ほ' | 'も' | 'よ' | 'ろ' | 'を' => Some('お'),
+        'ご' | 'ぞ' | 'ど' | 'ぼ' | 'ぽ' => Some('お'),
+        _ => None,
+    }
+}
+
+/// The unvoiced form of a kana, with は行 B and P both going to H. Kana that
+/// aren't voiced have none. (e.g. が→か, ぷ→ふ)
+pub fn devoice(c: char) -> Option<char> {
+    match c {
+        'ば' | 'ぱ' | 'び' | 'ぴ' | 'ぶ' | 'ぷ' | 'べ' | 'ぺ' | 'ぼ' | 'ぽ' => ha_gyou(c),
+        _ => voiced_char(c).filter(|_| onset(c).is_some_and(|o| matches!(o, "g" | "z" | "d"))),
+    }
+}
+
+/// A reading with every voiced kana devoiced. (e.g. ぎゃく→きゃく)
+pub fn devoiced(s: &str) -> String {
+    s.chars().map(|c| devoice(c).unwrap_or(c)).collect()
+}
+
+/// Broad classes of consonants, by where in the mouth they're made. Voiced
+/// and unvoiced consonants share a class, as do the three of the は行.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Class {
+    /// No consonant at all. (e.g. あ)
+    Vowel,
+    /// K and G.
+    Velar,
+    /// S and Z.
+    Sibilant,
+    /// T and D.
+    Dental,
+    /// H, B, and P.
+    Labial,
+    /// N and M.
+    Nasal,
+    /// R.
+    Liquid,
+    /// Y and W.
+    Glide,
+}
+
+/// The class of the consonant that begins a given Hiragana.
+pub fn consonant_class(c: char) -> Option<Class> {
+    let class = match onset(c)? {
+        "" => Class::Vowel,
+        "k" | "g" => Class::Velar,
+        "s" | "z" => Class::Sibilant,
+        "t" | "d" => Class::Dental,
+        "h" | "b" | "p" => Class::Labial,
+        "n" | "m" => Class::Nasal,
+        "r" => Class::Liquid,
+        _ => Class::Glide,
+    };
+
+    Some(class)
+}
+
+/// Do two readings begin with consonants of the same `Class`? Readings that
+/// begin with a bare vowel don't count. (e.g. こく→けい, こく→ぎん)
+pub fn is_same_consonant(a: &str, b: &str) -> bool {
+    let class = |s: &str| s.chars().next().and_then(consonant_class);
+
+    match (class(a), class(b)) {
+        (Some(x), Some(y)) => x == y && x != Class::Vowel,
+        _ => false,
+    }
+}
+
+/// Does the reading of a word contain the given 音読み, allowing for the voicing
+/// and clipping it can undergo in compounds? (e.g. がく in がっこう, こう in
+/// ぶっこう)
+pub fn is_read_in(yomi: &str, reading: &str) -> bool {
+    let (yomi, reading) = (devoiced(yomi), devoiced(reading));
+    let clipped = yomi
+        .strip_suffix(['つ', 'ち', 'く', 'き'])
+        .filter(|stem| !stem.is_empty())
+        .map(|stem| format!("{}っ", stem));
+
+    reading.contains(&yomi) || clipped.is_some_and(|c| reading.contains(&c))
+}
+
+/// How many morae a reading has. (e.g. きょく has two)
+pub fn mora_count(s: &str) -> usize {
+    morae(s).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every kana that can begin a mora.
+    fn kana() -> impl Iterator<Item = char> {
+        ('ぁ'..='ゖ').filter(|c| onset(*c).is_some())
+    }
+
+    /// Every reading of one or two morae, with and without 拗音, ん, and っ.
+    fn readings() -> Vec<String> {
+        let firsts: Vec<String> = kana()
+            .flat_map(|c| {
+                let yoon = match (onset(c), vowel(c)) {
+                    (Some(o), Some('い')) if !o.is_empty() => vec!['ゃ', 'ゅ', 'ょ'],
+                    _ => vec![],
+                };
+                std::iter::once(c.to_string())
+                    .chain(yoon.into_iter().map(move |s| format!("{}{}", c, s)))
+            })
+            .collect();
+        let codas = ["", "ん", "く", "き", "つ", "ち", "っ", "う", "い"];
+
+        firsts
+            .iter()
+            .flat_map(|f| codas.iter().map(move |c| format!("{}{}", f, c)))
+            .collect()
+    }
+
+    #[test]
+    fn voicing_is_an_involution() {
+        kana().for_each(|c| {
+            if let Some(v) = voiced_char(c) {
+                assert_eq!(voiced_char(v), Some(c), "{}", c);
+                assert_ne!(v, c);
+            }
+        });
+    }
+
+    #[test]
+    fn devoicing_undoes_voicing() {
+        kana().for_each(|c| match devoice(c) {
+            Some(d) => {
+                assert_ne!(d, c);
+                assert_eq!(devoice(d), None, "{}", c);
+                assert_eq!(consonant_class(d), consonant_class(c), "{}", c);
+            }
+            None => assert!(voiced_char(c).is_none_or(|v| devoice(v) == Some(c))),
+        });
+        assert_eq!(devoiced("ぎゃく"), "きゃく");
+        assert_eq!(devoiced("ぶん"), "ふん");
+    }
+
+    #[test]
+    fn morae_partition_the_reading() {
+        readings().iter().for_each(|y| {
+            let morae = morae(y);
+            assert_eq!(morae.concat(), *y);
+            assert!(morae.iter().all(|m| !m.is_empty()));
+            assert_eq!(mora_count(y), morae.len());
+        });
+        assert_eq!(morae("きょく"), vec!["きょ", "く"]);
+        assert_eq!(morae("がっ"), vec!["が", "っ"]);
+    }
+
+    #[test]
+    fn every_mora_has_a_vowel_and_class() {
+        readings().iter().for_each(|y| {
+            let first = morae(y)[0];
+            assert!(mora_vowel(first).is_some(), "{}", y);
+            assert!(decompose(y).is_some(), "{}", y);
+        });
+        kana().for_each(|c| assert!(consonant_class(c).is_some(), "{}", c));
+    }
+
+    #[test]
+    fn relations_are_symmetric() {
+        let ys = readings();
+        ys.iter().step_by(7).for_each(|a| {
+            ys.iter().step_by(11).for_each(|b| {
+                assert_eq!(is_voiced_pair(a, b), is_voiced_pair(b, a), "{} {}", a, b);
+                assert_eq!(is_rendaku_pair(a, b), is_rendaku_pair(b, a), "{} {}", a, b);
+                assert_eq!(is_rhyme(a, b), is_rhyme(b, a), "{} {}", a, b);
+                assert_eq!(is_clipped(a, b), is_clipped(b, a), "{} {}", a, b);
+            });
+        });
+    }
+
+    #[test]
+    fn voicing_and_rhyme() {
+        readings().iter().for_each(|y| {
+            assert!(is_rhyme(y, y), "{}", y);
+            assert!(!is_voiced_pair(y, y), "{}", y);
+
+            let mut chars = y.chars();
+            if let Some(v) = chars.next().and_then(voiced_char) {
+                let other: String = std::iter::once(v).chain(chars).collect();
+                assert!(is_voiced_pair(y, &other), "{} {}", y, other);
+                assert!(is_rhyme(y, &other), "{} {}", y, other);
+            }
+        });
+        assert!(is_rendaku_pair("ふ", "ぶ"));
+        assert!(is_clipped("がく", "がっ"));
+        assert!(is_clipped("かん", "か"));
+    }
+
+    #[test]
+    fn yoon_pairs() {
+        // The small kana decides the vowel, so these all rhyme.
+        assert!(is_rhyme("きょく", "こく"));
+        assert!(is_rhyme("しゅう", "ちゅう"));
+        assert!(is_rhyme("りょう", "よう"));
+        assert!(is_rhyme("きゃく", "かく"));
+        assert!(is_rhyme("じょう", "そう"));
+        assert!(!is_rhyme("きょく", "きく"));
+        assert!(!is_rhyme("しゃ", "し"));
+
+        // Voicing applies to the whole mora, small kana and all.
+        assert!(is_voiced_pair("きょう", "ぎょう"));
+        assert!(is_voiced_pair("しゅ", "じゅ"));
+        assert!(!is_voiced_pair("きょう", "ごう"));
+        assert!(!is_voiced_pair("きょう", "ぎゅう"));
+
+        assert!(is_clipped("りゃく", "りゃっ"));
+        assert!(!is_clipped("きょ", "き"));
+        assert_eq!(morae("しゅっ"), vec!["しゅ", "っ"]);
+    }
+
+    #[test]
+    fn readings_within_words() {
+        readings().iter().for_each(|y| {
+            assert!(is_read_in(y, y), "{}", y);
+            assert!(is_read_in(y, &format!("あ{}い", y)), "{}", y);
+        });
+        assert!(is_read_in("がく", "がっこう"));
+        assert!(is_read_in("こう", "ぶつごう"));
+        assert!(is_read_in("はつ", "しゅっぱつ"));
+        assert!(!is_read_in("ご", "がくしゃ"));
+        assert!(!is_read_in("つ", "かっ"));
+    }
+}
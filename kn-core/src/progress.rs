@@ -0,0 +1,161 @@
+//! A learner's study state, kept separately from the main database.
+//!
+//! Everything a learner accumulates while studying — which Kanji they know,
+//! their review schedule, and how they've done in quizzes — lives in a single
+//! `Progress` value that can be moved between machines independently of the
+//! data file itself.
+
+use crate::Kanji;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "fs")]
+use crate::Error;
+#[cfg(feature = "fs")]
+use std::fs;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+/// The name of the progress file, kept next to the data file.
+pub const PROGRESS_FILE: &str = "progress.json";
+
+/// All study state for one learner.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Kanji the learner considers known.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub learned: BTreeSet<Kanji>,
+    /// Spaced-repetition scheduling, per Kanji.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub srs: BTreeMap<Kanji, Card>,
+    /// Quiz results, per Kanji.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quiz: BTreeMap<Kanji, QuizStats>,
+    /// SRS stages from WaniKani, from 0 (lesson not yet done) to 9 (burned).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub wanikani: BTreeMap<Kanji, u8>,
+}
+
+/// The review schedule of a single Kanji.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Card {
+    /// When the next review is due, in seconds since the Unix epoch.
+    pub due: u64,
+    /// The current gap between reviews, in days.
+    pub interval: u32,
+    /// How many times this card has been reviewed.
+    pub reviews: u32,
+}
+
+/// Accumulated quiz results for a single Kanji.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct QuizStats {
+    pub correct: u32,
+    pub wrong: u32,
+    /// Total time spent answering, in milliseconds.
+    pub millis: u64,
+}
+
+/// Seconds in a day, for scheduling.
+const DAY: u64 = 60 * 60 * 24;
+
+impl Card {
+    /// A fresh card, due immediately.
+    pub fn new(now: u64) -> Card {
+        Card {
+            due: now,
+            interval: 0,
+            reviews: 0,
+        }
+    }
+
+    /// Reschedule after an answer: correct answers double the interval, while
+    /// wrong ones send the card back to daily review.
+    pub fn answer(&mut self, correct: bool, now: u64) {
+        self.interval = if correct {
+            (self.interval * 2).max(1)
+        } else {
+            1
+        };
+        self.due = now + DAY * u64::from(self.interval);
+        self.reviews += 1;
+    }
+}
+
+impl QuizStats {
+    /// Record the result of a single answer.
+    pub fn record(&mut self, correct: bool, millis: u64) {
+        if correct {
+            self.correct += 1;
+        } else {
+            self.wrong += 1;
+        }
+        self.millis += millis;
+    }
+
+    /// The average time taken per answer, if any answers have been given.
+    pub fn average_millis(&self) -> Option<u64> {
+        let answers = u64::from(self.correct + self.wrong);
+        (answers > 0).then(|| self.millis / answers)
+    }
+}
+
+impl Progress {
+    /// Kanji whose cards are due for review, most overdue first.
+    pub fn due(&self, now: u64) -> Vec<Kanji> {
+        let mut due: Vec<_> = self.srs.iter().filter(|(_, c)| c.due <= now).collect();
+        due.sort_by_key(|(k, c)| (c.due, **k));
+        due.into_iter().map(|(k, _)| *k).collect()
+    }
+
+    /// The expected time to answer for a given Kanji, based on past answers for
+    /// it, then on all past answers.
+    pub fn expected_millis(&self, k: &Kanji) -> Option<u64> {
+        self.quiz
+            .get(k)
+            .and_then(|q| q.average_millis())
+            .or_else(|| {
+                let total = self
+                    .quiz
+                    .values()
+                    .fold(QuizStats::default(), |acc, q| QuizStats {
+                        correct: acc.correct + q.correct,
+                        wrong: acc.wrong + q.wrong,
+                        millis: acc.millis + q.millis,
+                    });
+                total.average_millis()
+            })
+    }
+
+    /// Merge another `Progress` into this one. Where both have data for the same
+    /// Kanji, the other side wins.
+    pub fn merge(&mut self, other: Progress) {
+        self.learned.extend(other.learned);
+        self.srs.extend(other.srs);
+        self.quiz.extend(other.quiz);
+        self.wanikani.extend(other.wanikani);
+    }
+}
+
+/// The location of the progress file that accompanies a data file.
+#[cfg(feature = "fs")]
+pub fn progress_path(data: &Path) -> PathBuf {
+    data.with_file_name(PROGRESS_FILE)
+}
+
+/// Read a progress file, yielding an empty `Progress` if none exists yet.
+#[cfg(feature = "fs")]
+pub fn read_progress(path: &Path) -> Result<Progress, Error> {
+    match fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).map_err(Error::Json),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Progress::default()),
+        Err(e) => Err(Error::Io(e)),
+    }
+}
+
+/// Write a progress file.
+#[cfg(feature = "fs")]
+pub fn write_progress(path: &Path, progress: &Progress) -> Result<(), Error> {
+    let raw = serde_json::to_string_pretty(progress).map_err(Error::Json)?;
+    fs::write(path, raw).map_err(Error::Io)
+}
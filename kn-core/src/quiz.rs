@@ -0,0 +1,58 @@
+//! Helpers for building reading quizzes on top of the Kanji graph.
+
+use crate::{phonology, Direction, Kanji, DB};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Up to `n` wrong answers for the readings of `k` that are plausible enough to
+/// make a learner think.
+///
+/// Readings of `k`'s siblings (the other children of its parents) come first,
+/// since a learner who half-remembers a family will reach for them. After that
+/// come readings that rhyme with, voice, or clip the first reading of `k`,
+/// most common first. The result never contains a correct reading, and is
+/// deterministic; shuffling it is up to the caller.
+pub fn distractors(db: &DB, k: Kanji, n: usize) -> Vec<String> {
+    let entry = match db.entries.get(&k) {
+        Some(e) => e,
+        None => return Vec::new(),
+    };
+    let answer = match entry.onyomi.first() {
+        Some(y) => y,
+        None => return Vec::new(),
+    };
+
+    let siblings = db.index.get(&k).into_iter().flat_map(|kix| {
+        db.neighbours(*kix, Direction::Incoming)
+            .flat_map(|(oix, _)| db.neighbours(oix, Direction::Outgoing))
+            .filter(move |(six, _)| six != kix)
+            .filter_map(|(six, _)| db.entry(six))
+            .sorted_by_key(|e| e.kanji)
+            .flat_map(|e| e.onyomi.iter().map(|y| y.as_str()))
+    });
+
+    // How many Kanji share each reading.
+    let counts: HashMap<&str, usize> = db
+        .entries
+        .values()
+        .flat_map(|e| e.onyomi.iter().map(|y| y.as_str()))
+        .counts();
+    let similar = counts
+        .iter()
+        .filter(|(y, _)| {
+            phonology::is_rhyme(y, answer)
+                || phonology::is_voiced_pair(y, answer)
+                || phonology::is_clipped(y, answer)
+        })
+        .sorted_by_key(|(y, c)| (Reverse(**c), **y))
+        .map(|(y, _)| *y);
+
+    siblings
+        .chain(similar)
+        .filter(|y| !entry.onyomi.iter().any(|o| o == *y))
+        .unique()
+        .take(n)
+        .map(|y| y.to_string())
+        .collect()
+}
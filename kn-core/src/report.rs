@@ -0,0 +1,1047 @@
+//! Serializable summaries of the database, for people and programs alike.
+
+use crate::{phonology, utils, Inherit, Kanji, Level, Onyomi, Rules, DB};
+use kanji::exam_lists::*;
+use petgraph::visit::{Dfs, EdgeRef};
+use petgraph::Direction;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A `Kanji` related to another, and how their readings relate.
+#[derive(Debug, Clone, Serialize)]
+pub struct Relative {
+    pub kanji: Kanji,
+    /// Missing for parents without entries of their own, since there's no edge
+    /// to classify.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherit: Option<Inherit>,
+}
+
+/// Everything known about a single `Kanji`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryReport {
+    pub kanji: Kanji,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    pub onyomi: Vec<Onyomi>,
+    /// Not kept in the database, so only present when filled in from a
+    /// dictionary by the caller.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kunyomi: Vec<String>,
+    pub oya: Vec<Relative>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub kakushi_oya: Vec<Kanji>,
+    pub children: Vec<Relative>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<Kanji>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nite: Vec<Kanji>,
+    pub daihyou: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// How much of one group of Kanji has been entered.
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub entered: usize,
+    pub total: usize,
+}
+
+/// How much of a single exam level has been entered.
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelProgress {
+    #[serde(serialize_with = "display")]
+    pub level: Level,
+    #[serde(flatten)]
+    pub progress: Progress,
+}
+
+/// An overview of the whole database.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub entries: usize,
+    /// From 10級 up to 1級.
+    pub levels: Vec<LevelProgress>,
+    pub jouyou: Progress,
+    /// Entries added in each recent week, oldest first. Empty if no entry
+    /// records when it was added.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub weekly: Vec<usize>,
+    /// How much the learner knows, if they've marked anything as learned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub learned: Option<LearnedStats>,
+}
+
+/// How much of one group of Kanji the learner knows.
+#[derive(Debug, Clone, Serialize)]
+pub struct Known {
+    pub learned: usize,
+    pub total: usize,
+}
+
+impl Known {
+    /// How much has been learned, out of 100. Nothing to learn counts as 0.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            100.0 * (self.learned as f64) / (self.total as f64)
+        }
+    }
+}
+
+/// How much of a single exam level the learner knows.
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelKnown {
+    #[serde(serialize_with = "display")]
+    pub level: Level,
+    #[serde(flatten)]
+    pub known: Known,
+}
+
+/// How much of a family the learner knows, counting its root and every
+/// descendant.
+#[derive(Debug, Clone, Serialize)]
+pub struct FamilyKnown {
+    pub kanji: Kanji,
+    #[serde(flatten)]
+    pub known: Known,
+}
+
+/// How much the learner knows, overall and in parts.
+#[derive(Debug, Clone, Serialize)]
+pub struct LearnedStats {
+    /// Of all entries.
+    pub entries: Known,
+    /// From 10級 up to 1級.
+    pub levels: Vec<LevelKnown>,
+    /// The largest families, largest first.
+    pub families: Vec<FamilyKnown>,
+}
+
+/// A `Kanji` and when its entry was added.
+#[derive(Debug, Clone, Serialize)]
+pub struct Added {
+    pub kanji: Kanji,
+    /// Seconds since the Unix epoch.
+    pub added: u64,
+}
+
+/// How often one kind of relationship occurs.
+#[derive(Debug, Clone, Serialize)]
+pub struct InheritCount {
+    pub inherit: Inherit,
+    pub count: usize,
+    /// Of all edges.
+    pub percent: f64,
+}
+
+/// A parent, and how many of its children's readings differ from its own.
+#[derive(Debug, Clone, Serialize)]
+pub struct IrregularFamily {
+    pub kanji: Kanji,
+    pub differ: usize,
+    pub children: usize,
+}
+
+/// How regular the readings of families are, across the whole graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeStats {
+    pub edges: usize,
+    /// In the order of `Inherit::ALL`.
+    pub kinds: Vec<InheritCount>,
+    /// The families with the greatest share of `Differ` edges, worst first.
+    pub irregular: Vec<IrregularFamily>,
+}
+
+/// How much of the graph descends from a single `Kanji`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Centrality {
+    pub kanji: Kanji,
+    pub children: usize,
+    /// Children, grandchildren, and so on, each counted once.
+    pub descendants: usize,
+}
+
+/// A reading that a new child of some parent might have.
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub reading: String,
+    pub inherit: Inherit,
+    /// The parent's reading that this one follows from.
+    pub from: String,
+    /// Whether the Kanji is already known to have this reading.
+    pub recorded: bool,
+}
+
+/// What a new child of some parent is likely to be read as, judged by the
+/// parent's readings and how its existing children relate to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Prediction {
+    pub kanji: Kanji,
+    pub oya: Kanji,
+    /// The 音読み already recorded for the Kanji, if it has an entry.
+    pub recorded: Vec<Onyomi>,
+    pub children: usize,
+    /// How the existing children relate to the parent, most common first.
+    pub patterns: Vec<InheritCount>,
+    /// Most likely first.
+    pub candidates: Vec<Candidate>,
+}
+
+/// Kanji of one reading that are linked to each other through any of their
+/// parents.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingFamily {
+    /// The members without a parent in the family, which the rest descend
+    /// from. A family that is only a cycle is rooted at its first member.
+    pub roots: Vec<Kanji>,
+    /// In order.
+    pub kanji: Vec<Kanji>,
+}
+
+/// A 音読み shared by many Kanji.
+#[derive(Debug, Clone, Serialize)]
+pub struct Homophones {
+    pub reading: String,
+    pub count: usize,
+    /// Largest first.
+    pub families: Vec<ReadingFamily>,
+}
+
+/// Some text of an entry that contains a search term.
+#[derive(Debug, Clone, Serialize)]
+pub struct MeaningMatch {
+    pub kanji: Kanji,
+    /// Where the text came from: `daihyou`, `notes`, or `meaning`.
+    pub field: &'static str,
+    pub text: String,
+    /// The byte range of the term within the text.
+    pub range: (usize, usize),
+}
+
+/// A `Kanji` and the exam level it belongs to.
+#[derive(Debug, Clone, Serialize)]
+pub struct KanjiLevel {
+    pub kanji: Kanji,
+    #[serde(serialize_with = "display")]
+    pub level: Level,
+}
+
+/// Write a `Level` the way it's written on the exam, e.g. 準二.
+fn display<S: serde::Serializer>(level: &Level, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(level)
+}
+
+/// A parent with no entry of its own, and the children waiting on it.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingParent {
+    pub kanji: Kanji,
+    pub children: Vec<Kanji>,
+}
+
+/// The Kanji that have a given 音読み.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadingMatch {
+    pub reading: String,
+    pub kanji: Vec<Kanji>,
+}
+
+/// The Kanji that might be mistaken for a given one.
+#[derive(Debug, Clone, Serialize)]
+pub struct Lookalikes {
+    pub kanji: Kanji,
+    pub lookalikes: Vec<Kanji>,
+}
+
+/// How a 代表 word has drifted out of step with its entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Drift {
+    /// The word doesn't contain the Kanji at all.
+    NoKanji,
+    /// The word's given reading contains none of the Kanji's 音読み.
+    NoReading,
+}
+
+/// A 代表 word that doesn't fit the entry it represents.
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftedWord {
+    pub kanji: Kanji,
+    pub word: String,
+    pub drift: Drift,
+}
+
+/// Why two entries might really be one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Likeness {
+    /// One Kanji is a CJK compatibility form of the other, the same character
+    /// as far as Unicode is concerned. (e.g. U+FA5B and 者)
+    Compatibility,
+    /// The two have the same 音読み and the same parents, and either more than
+    /// one parent or a record of being variants of each other.
+    SameReadingsAndParents,
+}
+
+/// Two entries that may well be duplicates, and which of them to keep if
+/// they're merged.
+#[derive(Debug, Clone, Serialize)]
+pub struct NearDuplicate {
+    pub keep: Kanji,
+    pub merge: Kanji,
+    pub likeness: Likeness,
+}
+
+/// The unified form of a CJK compatibility ideograph, if it is one.
+pub fn unified(k: Kanji) -> Option<Kanji> {
+    let mut decomposed = Vec::new();
+    unicode_normalization::char::decompose_canonical(k.get(), |c| decomposed.push(c));
+
+    match decomposed[..] {
+        [c] if c != k.get() => Kanji::new(c),
+        _ => None,
+    }
+}
+
+/// Where an already lowercase term appears in some text, ignoring case, as a
+/// byte range of the original text. Lowercasing can change a character's
+/// length, so offsets into the lowercase copy are mapped back character by
+/// character.
+fn find_lowercase(text: &str, term: &str) -> Option<(usize, usize)> {
+    let mut lower = String::with_capacity(text.len());
+    // For each byte of `lower`, the original character it came from.
+    let mut origin: Vec<(usize, char)> = Vec::with_capacity(text.len());
+
+    text.char_indices().for_each(|(i, c)| {
+        c.to_lowercase().for_each(|l| {
+            lower.push(l);
+            origin.extend(std::iter::repeat_n((i, c), l.len_utf8()));
+        })
+    });
+
+    let start = lower.find(term)?;
+    let end = start + term.len();
+    let (a, _) = *origin.get(start)?;
+    let b = if term.is_empty() {
+        a
+    } else {
+        let (i, c) = origin[end - 1];
+        i + c.len_utf8()
+    };
+
+    Some((a, b))
+}
+
+/// A 代表 word and the reading written after it, if any. (e.g. 学校(がっこう))
+pub fn furigana(daihyou: &str) -> (&str, Option<&str>) {
+    daihyou
+        .strip_suffix([')', '）'])
+        .and_then(|d| d.split_once(['(', '（']))
+        .map(|(word, reading)| (word, Some(reading)))
+        .unwrap_or((daihyou, None))
+}
+
+/// Every exam level with the Kanji in it, from 10級 up to 1級.
+fn exam_levels() -> [(Level, &'static str); 12] {
+    [
+        (Level::Ten, LEVEL_10),
+        (Level::Nine, LEVEL_09),
+        (Level::Eight, LEVEL_08),
+        (Level::Seven, LEVEL_07),
+        (Level::Six, LEVEL_06),
+        (Level::Five, LEVEL_05),
+        (Level::Four, LEVEL_04),
+        (Level::Three, LEVEL_03),
+        (Level::PreTwo, LEVEL_02_PRE),
+        (Level::Two, LEVEL_02),
+        (Level::PreOne, LEVEL_01_PRE),
+        (Level::One, LEVEL_01),
+    ]
+}
+
+/// The level of each given `Kanji` that has one.
+pub fn levels_of(ks: &[Kanji]) -> Vec<KanjiLevel> {
+    let table = kanji::level_table();
+
+    ks.iter()
+        .filter_map(|k| {
+            table.get(k).map(|l| KanjiLevel {
+                kanji: *k,
+                level: *l,
+            })
+        })
+        .collect()
+}
+
+impl DB {
+    /// How far along the database is, level by level.
+    pub fn stats(&self) -> Stats {
+        let levels = self.levels();
+
+        let per_level: Vec<(Level, Progress)> = exam_levels()
+            .into_iter()
+            .map(|(level, ks)| {
+                let progress = Progress {
+                    entered: levels.values().filter(|l| **l == level).count(),
+                    total: ks.chars().count(),
+                };
+                (level, progress)
+            })
+            .collect();
+
+        // 常用 is everything below 準1級.
+        let jouyou = per_level.iter().filter(|(l, _)| *l < Level::PreOne).fold(
+            Progress {
+                entered: 0,
+                total: 0,
+            },
+            |acc, (_, p)| Progress {
+                entered: acc.entered + p.entered,
+                total: acc.total + p.total,
+            },
+        );
+
+        Stats {
+            entries: self.entries.len(),
+            levels: per_level
+                .into_iter()
+                .map(|(level, progress)| LevelProgress { level, progress })
+                .collect(),
+            jouyou,
+            weekly: Vec::new(),
+            learned: None,
+        }
+    }
+
+    /// How much of the database, its levels, and its `families` largest
+    /// families the learner knows.
+    pub fn learned_stats(&self, learned: &BTreeSet<Kanji>, families: usize) -> LearnedStats {
+        let entries = Known {
+            learned: self.entries.keys().filter(|k| learned.contains(k)).count(),
+            total: self.entries.len(),
+        };
+
+        let levels = exam_levels()
+            .into_iter()
+            .map(|(level, ks)| LevelKnown {
+                level,
+                known: Known {
+                    learned: ks
+                        .chars()
+                        .filter_map(Kanji::new)
+                        .filter(|k| learned.contains(k))
+                        .count(),
+                    total: ks.chars().count(),
+                },
+            })
+            .collect();
+
+        let mut largest = self.centrality();
+        largest.sort_by_key(|c| std::cmp::Reverse(c.descendants));
+        let families = largest
+            .into_iter()
+            .take(families)
+            .filter_map(|c| {
+                let mut dfs = Dfs::new(&self.graph, *self.index.get(&c.kanji)?);
+                let mut known = Known {
+                    learned: 0,
+                    total: 0,
+                };
+                while let Some(nix) = dfs.next(&self.graph) {
+                    known.total += 1;
+                    if self
+                        .graph
+                        .node_weight(nix)
+                        .is_some_and(|k| learned.contains(k))
+                    {
+                        known.learned += 1;
+                    }
+                }
+                Some(FamilyKnown {
+                    kanji: c.kanji,
+                    known,
+                })
+            })
+            .collect();
+
+        LearnedStats {
+            entries,
+            levels,
+            families,
+        }
+    }
+
+    /// Entries added in each of the `weeks` weeks up to `now`, oldest first.
+    /// Empty if no entry records when it was added.
+    pub fn added_per_week(&self, now: u64, weeks: usize) -> Vec<usize> {
+        const WEEK: u64 = 7 * 24 * 60 * 60;
+
+        let times: Vec<u64> = self.entries.values().filter_map(|e| e.added).collect();
+        if times.is_empty() {
+            return Vec::new();
+        }
+
+        let mut counts = vec![0; weeks];
+        times
+            .into_iter()
+            .filter_map(|t| usize::try_from(now.checked_sub(t)? / WEEK).ok())
+            .filter(|ago| *ago < weeks)
+            .for_each(|ago| counts[weeks - 1 - ago] += 1);
+        counts
+    }
+
+    /// Entries added at or after the given time, oldest first.
+    pub fn added_since(&self, since: u64) -> Vec<Added> {
+        let mut found: Vec<Added> = self
+            .entries
+            .values()
+            .filter_map(|e| {
+                let added = e.added.filter(|t| *t >= since)?;
+                Some(Added {
+                    kanji: e.kanji,
+                    added,
+                })
+            })
+            .collect();
+        found.sort_by_key(|a| (a.added, a.kanji));
+        found
+    }
+
+    /// Counts of each kind of edge, and the families least regular in their
+    /// readings. Families smaller than `min_children` are too small to judge,
+    /// and are left out of the latter.
+    pub fn edge_stats(&self, min_children: usize, top: usize) -> EdgeStats {
+        let edges = self.graph.raw_edges();
+        let mut counts: HashMap<Inherit, usize> = HashMap::new();
+        let mut families: HashMap<Kanji, (usize, usize)> = HashMap::new();
+
+        edges.iter().for_each(|e| {
+            *counts.entry(e.weight).or_default() += 1;
+
+            if let Some(o) = self.graph.node_weight(e.source()) {
+                let family = families.entry(*o).or_default();
+                family.1 += 1;
+                if e.weight == Inherit::Differ {
+                    family.0 += 1;
+                }
+            }
+        });
+
+        let kinds = Inherit::ALL
+            .iter()
+            .map(|i| {
+                let count = counts.get(i).copied().unwrap_or_default();
+                InheritCount {
+                    inherit: *i,
+                    count,
+                    percent: match edges.len() {
+                        0 => 0.0,
+                        n => 100.0 * count as f64 / n as f64,
+                    },
+                }
+            })
+            .collect();
+
+        let mut irregular: Vec<IrregularFamily> = families
+            .into_iter()
+            .filter(|(_, (differ, children))| *differ > 0 && *children >= min_children)
+            .map(|(kanji, (differ, children))| IrregularFamily {
+                kanji,
+                differ,
+                children,
+            })
+            .collect();
+        // Compare shares by cross-multiplying, to stay in whole numbers.
+        irregular.sort_by(|a, b| {
+            (b.differ * a.children)
+                .cmp(&(a.differ * b.children))
+                .then(b.children.cmp(&a.children))
+                .then(a.kanji.cmp(&b.kanji))
+        });
+        irregular.truncate(top);
+
+        EdgeStats {
+            edges: edges.len(),
+            kinds,
+            irregular,
+        }
+    }
+
+    /// The children and descendant counts of every `Kanji` with children, in
+    /// order.
+    pub fn centrality(&self) -> Vec<Centrality> {
+        let mut found: Vec<Centrality> = self
+            .graph
+            .node_indices()
+            .filter_map(|nix| {
+                let kanji = *self.graph.node_weight(nix)?;
+                let children = self
+                    .graph
+                    .neighbors_directed(nix, Direction::Outgoing)
+                    .count();
+
+                let mut dfs = Dfs::new(&self.graph, nix);
+                let mut descendants = 0;
+                while let Some(d) = dfs.next(&self.graph) {
+                    if d != nix {
+                        descendants += 1;
+                    }
+                }
+
+                (children > 0).then_some(Centrality {
+                    kanji,
+                    children,
+                    descendants,
+                })
+            })
+            .collect();
+
+        found.sort_by_key(|c| c.kanji);
+        found
+    }
+
+    /// The `top` readings shared by the most Kanji, largest first, their
+    /// members grouped into families.
+    pub fn homophones(&self, top: usize) -> Vec<Homophones> {
+        let mut index: Vec<(&str, Vec<Kanji>)> = self.reading_index().into_iter().collect();
+        index.sort_by_key(|(_, ks)| std::cmp::Reverse(ks.len()));
+        index.truncate(top);
+
+        index
+            .into_iter()
+            .map(|(reading, ks)| {
+                let members: BTreeSet<Kanji> = ks.iter().copied().collect();
+                let parents = |k: &Kanji| -> Vec<Kanji> {
+                    self.entries
+                        .get(k)
+                        .into_iter()
+                        .flat_map(|e| e.oya.iter())
+                        .filter(|o| *o != k && members.contains(o))
+                        .copied()
+                        .collect()
+                };
+
+                // Members are linked to their parents both ways, so that
+                // siblings end up in the same family.
+                let mut links: BTreeMap<Kanji, Vec<Kanji>> = BTreeMap::new();
+                ks.iter().for_each(|k| {
+                    parents(k).into_iter().for_each(|o| {
+                        links.entry(*k).or_default().push(o);
+                        links.entry(o).or_default().push(*k);
+                    })
+                });
+
+                let mut seen: BTreeSet<Kanji> = BTreeSet::new();
+                let mut families: Vec<ReadingFamily> = ks
+                    .iter()
+                    .filter_map(|k| {
+                        seen.insert(*k).then_some(())?;
+                        let mut kanji = vec![*k];
+                        let mut todo = vec![*k];
+                        while let Some(k) = todo.pop() {
+                            links.get(&k).into_iter().flatten().for_each(|n| {
+                                if seen.insert(*n) {
+                                    kanji.push(*n);
+                                    todo.push(*n);
+                                }
+                            });
+                        }
+                        kanji.sort();
+
+                        let mut roots: Vec<Kanji> = kanji
+                            .iter()
+                            .filter(|k| parents(k).is_empty())
+                            .copied()
+                            .collect();
+                        if roots.is_empty() {
+                            roots.push(kanji[0]);
+                        }
+
+                        Some(ReadingFamily { roots, kanji })
+                    })
+                    .collect();
+                families.sort_by_key(|f| std::cmp::Reverse(f.kanji.len()));
+
+                Homophones {
+                    reading: reading.to_string(),
+                    count: ks.len(),
+                    families,
+                }
+            })
+            .collect()
+    }
+
+    /// Every 代表 word, note, and dictionary meaning that contains the given
+    /// term, ignoring case, in order of their `Kanji`.
+    pub fn search_meaning(
+        &self,
+        term: &str,
+        meanings: &HashMap<Kanji, Vec<String>>,
+    ) -> Vec<MeaningMatch> {
+        let term: String = term.chars().flat_map(char::to_lowercase).collect();
+        let none: Vec<String> = Vec::new();
+
+        let mut found: Vec<MeaningMatch> = self
+            .entries
+            .values()
+            .flat_map(|e| {
+                let fields = [
+                    ("daihyou", &e.daihyou),
+                    ("notes", &e.notes),
+                    ("meaning", meanings.get(&e.kanji).unwrap_or(&none)),
+                ];
+                fields
+                    .into_iter()
+                    .flat_map(move |(field, texts)| texts.iter().map(move |t| (e.kanji, field, t)))
+            })
+            .filter_map(|(kanji, field, text)| {
+                let range = find_lowercase(text, &term)?;
+                Some(MeaningMatch {
+                    kanji,
+                    field,
+                    text: text.clone(),
+                    range,
+                })
+            })
+            .collect();
+
+        found.sort_by_key(|m| m.kanji);
+        found
+    }
+
+    /// Likely 音読み for a Kanji as a new child of the given parent, drawn from
+    /// every reading already in the database. If the Kanji has an entry of its
+    /// own, the readings it really has are marked. Yields nothing if the parent
+    /// has no entry.
+    pub fn predict(&self, kanji: Kanji, oya: Kanji, rules: Rules) -> Option<Prediction> {
+        let e = self.entries.get(&oya)?;
+        let recorded: Vec<Onyomi> = self
+            .entries
+            .get(&kanji)
+            .map(|e| e.onyomi.clone())
+            .unwrap_or_default();
+        let oix = *self.index.get(&oya)?;
+
+        let mut counts: HashMap<Inherit, usize> = HashMap::new();
+        self.graph
+            .edges_directed(oix, Direction::Outgoing)
+            .for_each(|edge| *counts.entry(*edge.weight()).or_default() += 1);
+        let children: usize = counts.values().sum();
+
+        let mut patterns: Vec<InheritCount> = Inherit::ALL
+            .iter()
+            .filter_map(|i| {
+                let count = *counts.get(i)?;
+                Some(InheritCount {
+                    inherit: *i,
+                    count,
+                    percent: 100.0 * count as f64 / children as f64,
+                })
+            })
+            .collect();
+        patterns.sort_by_key(|p| std::cmp::Reverse(p.count));
+
+        let readings: BTreeSet<&str> = self
+            .entries
+            .values()
+            .flat_map(|e| e.onyomi.iter().map(|y| y.as_str()))
+            .collect();
+
+        // Matches are ranked against the parent's readings as when classifying
+        // edges, then by how often the parent's children follow each pattern.
+        let mut found: Vec<((usize, u8), Candidate)> = readings
+            .into_iter()
+            .filter_map(|y| {
+                let ((strength, _), inherit, b) = Inherit::follow(y, &e.onyomi, rules)?;
+                let seen = counts.get(&inherit).copied().unwrap_or_default();
+                let candidate = Candidate {
+                    reading: y.to_string(),
+                    inherit,
+                    from: b.reading.to_string(),
+                    recorded: recorded.iter().any(|r| r.as_str() == y),
+                };
+                Some(((seen, strength), candidate))
+            })
+            .collect();
+        found.sort_by_key(|((seen, strength), _)| (std::cmp::Reverse(*seen), *strength));
+
+        Some(Prediction {
+            kanji,
+            oya,
+            recorded,
+            children,
+            patterns,
+            candidates: found.into_iter().map(|(_, c)| c).collect(),
+        })
+    }
+
+    /// 代表 words that don't contain their Kanji, or whose reading, when one is
+    /// given, doesn't use any of the Kanji's 音読み.
+    pub fn drifted_daihyou(&self) -> Vec<DriftedWord> {
+        let mut drifted: Vec<DriftedWord> = self
+            .entries
+            .values()
+            .flat_map(|e| {
+                e.daihyou.iter().filter_map(move |d| {
+                    let (word, reading) = furigana(d);
+                    let unread = |r: &str| {
+                        let r = utils::to_hiragana(r);
+                        !e.onyomi.is_empty()
+                            && !e.onyomi.iter().any(|y| phonology::is_read_in(y, &r))
+                    };
+                    let drift = match reading {
+                        _ if !word.contains(e.kanji.get()) => Some(Drift::NoKanji),
+                        Some(r) if unread(r) => Some(Drift::NoReading),
+                        _ => None,
+                    };
+
+                    drift.map(|drift| DriftedWord {
+                        kanji: e.kanji,
+                        word: d.clone(),
+                        drift,
+                    })
+                })
+            })
+            .collect();
+
+        drifted.sort_by(|a, b| (a.kanji, &a.word).cmp(&(b.kanji, &b.word)));
+        drifted
+    }
+
+    /// Entries that look like accidental duplicates of others: compatibility
+    /// forms of Kanji that have entries of their own, and Kanji with the same
+    /// readings and parents. Of the latter, the one with more children is
+    /// suggested to keep.
+    ///
+    /// Siblings in a phonetic series share a reading and a lone parent as a
+    /// matter of course, so those only count if they're also variants.
+    pub fn near_duplicates(&self) -> Vec<NearDuplicate> {
+        let compatible = self.entries.keys().filter_map(|k| {
+            unified(*k)
+                .filter(|u| self.entries.contains_key(u))
+                .map(|u| NearDuplicate {
+                    keep: u,
+                    merge: *k,
+                    likeness: Likeness::Compatibility,
+                })
+        });
+
+        // Kanji without parents are too commonly alike to be worth reporting.
+        let mut alike: HashMap<(BTreeSet<&str>, BTreeSet<Kanji>), Vec<Kanji>> = HashMap::new();
+        self.entries
+            .values()
+            .filter(|e| !e.oya.is_empty() && !e.onyomi.is_empty())
+            .for_each(|e| {
+                let readings = e.onyomi.iter().map(|y| y.as_str()).collect();
+                let parents = e.oya.iter().copied().collect();
+                alike.entry((readings, parents)).or_default().push(e.kanji);
+            });
+
+        let children = |k: &Kanji| {
+            self.index
+                .get(k)
+                .map(|ix| {
+                    self.graph
+                        .neighbors_directed(*ix, Direction::Outgoing)
+                        .count()
+                })
+                .unwrap_or(0)
+        };
+        let variants = |a: &Kanji, b: &Kanji| {
+            let of =
+                |x: &Kanji, y: &Kanji| self.entries.get(x).is_some_and(|e| e.variants.contains(y));
+            of(a, b) || of(b, a)
+        };
+        let same = alike.into_iter().flat_map(|((_, parents), mut ks)| {
+            ks.sort_by_key(|k| (std::cmp::Reverse(children(k)), *k));
+            ks.iter()
+                .enumerate()
+                .flat_map(|(i, keep)| ks[i + 1..].iter().map(move |merge| (*keep, *merge)))
+                .filter(|(keep, merge)| parents.len() > 1 || variants(keep, merge))
+                .map(|(keep, merge)| NearDuplicate {
+                    keep,
+                    merge,
+                    likeness: Likeness::SameReadingsAndParents,
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // A compatibility form usually has the same readings and parents too,
+        // but needs reporting only once.
+        let mut dups: Vec<NearDuplicate> = compatible.chain(same).collect();
+        dups.sort_by_key(|d| (d.keep, d.merge));
+        dups.dedup_by_key(|d| (d.keep, d.merge));
+        dups
+    }
+
+    /// Like `missing_parents`, but with the children of each.
+    pub fn missing_report(&self) -> Vec<MissingParent> {
+        self.missing_parents()
+            .into_iter()
+            .map(|o| {
+                let mut children: Vec<Kanji> = self
+                    .entries
+                    .values()
+                    .filter(|e| e.oya.contains(&o))
+                    .map(|e| e.kanji)
+                    .collect();
+                children.sort();
+                MissingParent { kanji: o, children }
+            })
+            .collect()
+    }
+
+    /// All there is to say about a `Kanji`, if it has an entry.
+    pub fn report(&self, k: Kanji) -> Option<EntryReport> {
+        let e = self.entries.get(&k)?;
+        let nix = *self.index.get(&k)?;
+
+        let oya = e
+            .oya
+            .iter()
+            .map(|o| Relative {
+                kanji: *o,
+                inherit: self
+                    .index
+                    .get(o)
+                    .and_then(|oix| self.graph.find_edge(*oix, nix))
+                    .and_then(|eix| self.graph.edge_weight(eix))
+                    .copied(),
+            })
+            .collect();
+
+        let mut children: Vec<Relative> = self
+            .graph
+            .edges_directed(nix, Direction::Outgoing)
+            .filter_map(|edge| {
+                self.graph.node_weight(edge.target()).map(|c| Relative {
+                    kanji: *c,
+                    inherit: Some(*edge.weight()),
+                })
+            })
+            .collect();
+        children.sort_by_key(|r| r.kanji);
+
+        let report = EntryReport {
+            kanji: k,
+            level: kanji::level_table().get(&k).map(|l| l.to_string()),
+            onyomi: e.onyomi.clone(),
+            kunyomi: Vec::new(),
+            oya,
+            kakushi_oya: e.kakushi_oya.clone(),
+            children,
+            variants: e.variants.clone(),
+            nite: e.nite.clone(),
+            daihyou: e.daihyou.clone(),
+            notes: e.notes.clone(),
+            tags: e.tags.clone(),
+        };
+
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Entry;
+
+    fn k(c: char) -> Kanji {
+        Kanji::new(c).unwrap()
+    }
+
+    fn entry(c: char, oya: &str, onyomi: &[&str]) -> (Kanji, Entry) {
+        let e = Entry {
+            oya: oya.chars().map(k).collect(),
+            onyomi: onyomi.iter().map(|y| Onyomi::from(*y)).collect(),
+            ..Entry::new(k(c))
+        };
+        (e.kanji, e)
+    }
+
+    #[test]
+    fn predictions() {
+        let db = DB::new(HashMap::from([
+            entry('工', "", &["こう", "く"]),
+            entry('江', "工", &["こう"]),
+            entry('功', "工", &["こう"]),
+            entry('貢', "工", &["ごう"]),
+            entry('紅', "", &["こう", "く"]),
+        ]))
+        .unwrap();
+        let rules = Rules::default();
+        let p = db.predict(k('紅'), k('工'), rules).unwrap();
+
+        assert_eq!(p.children, 3);
+        let patterns: Vec<(Inherit, usize)> =
+            p.patterns.iter().map(|p| (p.inherit, p.count)).collect();
+        assert_eq!(patterns, vec![(Inherit::Same, 2), (Inherit::Voicing, 1)]);
+
+        // Patterns the children follow most come first.
+        let candidates: Vec<(&str, Inherit, bool)> = p
+            .candidates
+            .iter()
+            .map(|c| (c.reading.as_str(), c.inherit, c.recorded))
+            .collect();
+        assert_eq!(
+            candidates,
+            vec![
+                ("こう", Inherit::Same, true),
+                ("ごう", Inherit::Voicing, false),
+                ("く", Inherit::Second, true),
+            ]
+        );
+        assert_eq!(p.recorded, vec![Onyomi::from("こう"), Onyomi::from("く")]);
+
+        // Nothing is marked for a Kanji with no entry.
+        let p = db.predict(k('攻'), k('工'), rules).unwrap();
+        assert!(p.recorded.is_empty());
+        assert!(p.candidates.iter().all(|c| !c.recorded));
+        assert!(db.predict(k('攻'), k('水'), rules).is_none());
+    }
+
+    #[test]
+    fn homophone_families() {
+        // 江 and 紅 reach 工 only through their second parents.
+        let db = DB::new(HashMap::from([
+            entry('工', "", &["こう"]),
+            entry('水', "", &["すい"]),
+            entry('江', "水工", &["こう"]),
+            entry('紅', "糸工", &["こう", "く"]),
+            entry('鴻', "江鳥", &["こう"]),
+            entry('甲', "", &["こう"]),
+        ]))
+        .unwrap();
+        let found = db.homophones(1);
+
+        assert_eq!(found[0].reading, "こう");
+        assert_eq!(found[0].count, 5);
+        let families: Vec<(Vec<Kanji>, Vec<Kanji>)> = found[0]
+            .families
+            .iter()
+            .map(|f| (f.roots.clone(), f.kanji.clone()))
+            .collect();
+        assert_eq!(
+            families,
+            vec![
+                (vec![k('工')], vec![k('工'), k('江'), k('紅'), k('鴻')]),
+                (vec![k('甲')], vec![k('甲')]),
+            ]
+        );
+    }
+
+    #[test]
+    fn lowercase_ranges() {
+        assert_eq!(find_lowercase("Water", "water"), Some((0, 5)));
+        assert_eq!(find_lowercase("Deep WATER", "water"), Some((5, 10)));
+        assert_eq!(find_lowercase("Water", "fire"), None);
+        // İ is two bytes, but three once lowercased.
+        let text = "İstanbul water";
+        let (a, b) = find_lowercase(text, "water").unwrap();
+        assert_eq!(&text[a..b], "water");
+        let (a, b) = find_lowercase(text, "stan").unwrap();
+        assert_eq!(&text[a..b], "stan");
+    }
+}
@@ -0,0 +1,245 @@
+//! One search over everything: Kanji themselves, their readings, and their
+//! glosses.
+//!
+//! An `Index` is built along with every `DB`, and `DB::find` answers queries
+//! with hits ranked from most to least certain: the Kanji itself, then an
+//! exact reading, a reading prefix, a gloss containing the query, and finally a
+//! gloss that merely contains the query's letters in order. Glosses containing
+//! the query are those found by `DB::search_meaning`.
+
+use crate::{utils, Entry, Kanji, Reading, DB};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How a hit matched the query, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Match {
+    /// The query contains the Kanji itself.
+    Literal,
+    /// The query is one of the Kanji's 音読み.
+    Reading,
+    /// The query begins one of the Kanji's 音読み.
+    ReadingPrefix,
+    /// A gloss contains the query.
+    Gloss,
+    /// A gloss contains the letters of the query, in order.
+    Fuzzy,
+}
+
+/// A `Kanji` found by a query, and its best match.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hit {
+    pub kanji: Kanji,
+    #[serde(rename = "match")]
+    pub matched: Match,
+    /// The reading or gloss that matched. Empty for a `Match::Literal`.
+    pub text: String,
+    /// For fuzzy matches, how many letters lie between those of the query.
+    /// Lower is better.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub gap: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Everything searchable about each `Kanji`.
+pub struct Index {
+    readings: Vec<(Kanji, Reading)>,
+    /// 代表 words and notes, as written and in lowercase for matching.
+    glosses: Vec<(Kanji, String, String)>,
+    kanji: Vec<Kanji>,
+}
+
+impl Index {
+    /// Index the given entries. Readings are shared with the entries.
+    pub fn new(entries: &HashMap<Kanji, Entry>) -> Index {
+        let readings = entries
+            .values()
+            .flat_map(|e| e.onyomi.iter().map(|y| (e.kanji, y.reading.clone())))
+            .collect();
+        let glosses = entries
+            .values()
+            .flat_map(|e| {
+                e.daihyou
+                    .iter()
+                    .chain(e.notes.iter())
+                    .map(|g| (e.kanji, g.clone(), g.to_lowercase()))
+            })
+            .collect();
+
+        Index {
+            readings,
+            glosses,
+            kanji: entries.keys().copied().collect(),
+        }
+    }
+}
+
+impl DB {
+    /// Every `Kanji` matching the query, best first, each with only its best
+    /// match. Dictionary meanings (see `kanjidic::meaning_table`) count as
+    /// glosses, alongside 代表 words and notes.
+    pub fn find(&self, query: &str, meanings: &HashMap<Kanji, Vec<String>>) -> Vec<Hit> {
+        let index = &self.search;
+        let query = query.trim();
+        let lower = query.to_lowercase();
+        let kana = utils::to_hiragana(&utils::romaji_to_hiragana(query));
+        let is_kana = !kana.is_empty() && kana.chars().all(kanji::is_hiragana);
+
+        let literal = index
+            .kanji
+            .iter()
+            .filter(|k| query.contains(k.get()))
+            .map(|k| Hit {
+                kanji: *k,
+                matched: Match::Literal,
+                text: String::new(),
+                gap: 0,
+            });
+
+        let readings = index
+            .readings
+            .iter()
+            .filter(|_| is_kana)
+            .filter_map(|(k, y)| {
+                let matched = match y {
+                    _ if *y == kana => Match::Reading,
+                    _ if y.starts_with(&kana) => Match::ReadingPrefix,
+                    _ => return None,
+                };
+                Some(Hit {
+                    kanji: *k,
+                    matched,
+                    text: y.to_string(),
+                    gap: 0,
+                })
+            });
+
+        let glosses = self
+            .search_meaning(query, meanings)
+            .into_iter()
+            .filter(|_| !lower.is_empty())
+            .map(|m| Hit {
+                kanji: m.kanji,
+                matched: Match::Gloss,
+                text: m.text,
+                gap: 0,
+            });
+
+        // Too short a query matches nearly anything.
+        let fuzzy_ok = lower.chars().count() >= 3;
+        let fuzzy = |k: Kanji, g: &str, low: &str| {
+            let gap = gap(&lower, low).filter(|_| fuzzy_ok && !low.contains(&lower))?;
+            Some(Hit {
+                kanji: k,
+                matched: Match::Fuzzy,
+                text: g.to_string(),
+                gap,
+            })
+        };
+        let indexed = index
+            .glosses
+            .iter()
+            .filter_map(|(k, g, low)| fuzzy(*k, g, low));
+        let dictionary = meanings
+            .iter()
+            .flat_map(|(k, ms)| ms.iter().filter_map(|m| fuzzy(*k, m, &m.to_lowercase())));
+
+        let mut best: HashMap<Kanji, Hit> = HashMap::new();
+        literal
+            .chain(readings)
+            .chain(glosses)
+            .chain(indexed)
+            .chain(dictionary)
+            .for_each(|hit| match best.get(&hit.kanji) {
+                Some(b) if (b.matched, b.gap) <= (hit.matched, hit.gap) => {}
+                _ => {
+                    best.insert(hit.kanji, hit);
+                }
+            });
+
+        let mut hits: Vec<Hit> = best.into_values().collect();
+        hits.sort_by_key(|h| (h.matched, h.gap, h.kanji));
+        hits
+    }
+}
+
+/// If every letter of the query appears in the text in order, how many other
+/// letters lie between the first and last of them.
+fn gap(query: &str, text: &str) -> Option<usize> {
+    let mut letters = query.chars().peekable();
+    let mut span = 0;
+    let mut started = false;
+
+    for c in text.chars() {
+        match letters.peek() {
+            None => break,
+            Some(q) if *q == c => {
+                letters.next();
+                started = true;
+            }
+            Some(_) if started => span += 1,
+            Some(_) => {}
+        }
+    }
+
+    letters.peek().is_none().then_some(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Onyomi;
+
+    fn k(c: char) -> Kanji {
+        Kanji::new(c).unwrap()
+    }
+
+    fn entry(c: char, onyomi: &[&str], notes: &[&str]) -> (Kanji, Entry) {
+        let e = Entry {
+            onyomi: onyomi.iter().map(|y| Onyomi::from(*y)).collect(),
+            notes: notes.iter().map(|n| n.to_string()).collect(),
+            ..Entry::new(k(c))
+        };
+        (e.kanji, e)
+    }
+
+    fn find(db: &DB, query: &str) -> Vec<(Kanji, Match)> {
+        db.find(query, &HashMap::new())
+            .into_iter()
+            .map(|h| (h.kanji, h.matched))
+            .collect()
+    }
+
+    #[test]
+    fn ranking() {
+        let db = DB::new(HashMap::from([
+            entry('工', &["こう", "く"], &["工作"]),
+            entry('江', &["こう"], &["工 and 水"]),
+            entry('光', &["こうい"], &[]),
+            entry('鉱', &["せき"], &["kouzan"]),
+            entry('幸', &["さち"], &["kind of unusual"]),
+            entry('木', &["もく"], &["tree"]),
+        ]))
+        .unwrap();
+
+        // Exact readings, then prefixes, then glosses, whole and fuzzy.
+        assert_eq!(
+            find(&db, "kou"),
+            vec![
+                (k('工'), Match::Reading),
+                (k('江'), Match::Reading),
+                (k('光'), Match::ReadingPrefix),
+                (k('鉱'), Match::Gloss),
+                (k('幸'), Match::Fuzzy),
+            ]
+        );
+        // The Kanji itself beats a note mentioning it, even its own.
+        assert_eq!(
+            find(&db, "工"),
+            vec![(k('工'), Match::Literal), (k('江'), Match::Gloss)]
+        );
+    }
+}
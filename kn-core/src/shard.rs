@@ -0,0 +1,87 @@
+//! Databases split across several JSON files in one directory.
+//!
+//! Each `.json` file in the directory is a "shard" holding some of the
+//! entries, e.g. one per level. Loading merges them all, refusing any `Kanji`
+//! that appears in more than one. Writing puts each entry back into whichever
+//! shard holds it on disk, and anything new into `new.json`.
+//!
+//! Any data path that is a directory is treated this way by `read_entries`,
+//! `write_entries`, and `open_db`.
+
+use crate::{Entry, Error, Kanji};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// The shard that entries with no home yet are written to.
+pub const NEW_SHARD: &str = "new.json";
+
+/// Every shard in a directory, in filename order.
+pub fn shards(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+
+    for entry in std::fs::read_dir(dir).map_err(Error::Io)? {
+        let path = entry.map_err(Error::Io)?.path();
+        if path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("json") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// The shard that each `Kanji` currently lives in.
+fn homes(dir: &Path) -> Result<HashMap<Kanji, PathBuf>, Error> {
+    let mut homes: HashMap<Kanji, PathBuf> = HashMap::new();
+
+    for shard in shards(dir)? {
+        for e in crate::read_entries(&shard)? {
+            if let Some(other) = homes.insert(e.kanji, shard.clone()) {
+                return Err(Error::Duplicate(e.kanji, other, shard));
+            }
+        }
+    }
+
+    Ok(homes)
+}
+
+/// Every entry from every shard in a directory.
+pub fn read_entries_dir(dir: &Path) -> Result<Vec<Entry>, Error> {
+    let mut seen: HashMap<Kanji, PathBuf> = HashMap::new();
+    let mut entries = Vec::new();
+
+    for shard in shards(dir)? {
+        for e in crate::read_entries(&shard)? {
+            if let Some(other) = seen.insert(e.kanji, shard.clone()) {
+                return Err(Error::Duplicate(e.kanji, other, shard));
+            }
+            entries.push(e);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Write each entry back to the shard it lives in. Shards whose entries have
+/// all gone are left empty rather than deleted.
+pub fn write_entries_dir(dir: &Path, entries: Vec<Entry>) -> Result<(), Error> {
+    let homes = homes(dir)?;
+
+    // Every existing shard is rewritten, even if it has nothing left.
+    let mut groups: BTreeMap<PathBuf, Vec<Entry>> = homes
+        .values()
+        .map(|shard| (shard.clone(), Vec::new()))
+        .collect();
+
+    for e in entries {
+        let shard = homes
+            .get(&e.kanji)
+            .cloned()
+            .unwrap_or_else(|| dir.join(NEW_SHARD));
+        groups.entry(shard).or_default().push(e);
+    }
+
+    groups
+        .into_iter()
+        .try_for_each(|(shard, es)| crate::write_entries(&shard, es))
+}
@@ -0,0 +1,144 @@
+//! Keeping entries in SQLite, as an alternative to the JSON data file.
+//!
+//! Every entry is its own row, so that a single entry can be added or changed
+//! without rewriting all the others. Any data path ending in `.sqlite` or `.db`
+//! is treated as such a database by `read_entries`, `write_entries`, and
+//! `open_db`, so the rest of the crate needn't care which is in use.
+
+use crate::{Entry, Error, Kanji, Rules, DB};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::path::Path;
+
+/// Does this data path refer to a SQLite database?
+pub fn is_sqlite(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("sqlite") | Some("db")
+    )
+}
+
+/// A connection to a SQLite database of entries.
+pub struct Sqlite {
+    conn: Connection,
+}
+
+impl Sqlite {
+    /// Open an existing database. A missing one is an error, just as a
+    /// missing JSON data file would be, rather than quietly an empty database.
+    pub fn open(path: &Path) -> Result<Sqlite, Error> {
+        std::fs::metadata(path).map_err(Error::Io)?;
+        let flags = OpenFlags::default() - OpenFlags::SQLITE_OPEN_CREATE;
+        let conn = Connection::open_with_flags(path, flags).map_err(Error::Sqlite)?;
+        Sqlite::init(conn)
+    }
+
+    /// Open a database, creating it if it doesn't exist yet.
+    pub fn create(path: &Path) -> Result<Sqlite, Error> {
+        let conn = Connection::open(path).map_err(Error::Sqlite)?;
+        Sqlite::init(conn)
+    }
+
+    fn init(conn: Connection) -> Result<Sqlite, Error> {
+        // Entries are stored as the same JSON that the data file would hold,
+        // so that the schema never needs to follow changes to `Entry`.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS entries (kanji TEXT PRIMARY KEY, entry TEXT NOT NULL)",
+            [],
+        )
+        .map_err(Error::Sqlite)?;
+
+        Ok(Sqlite { conn })
+    }
+
+    /// Every entry, in `Kanji` order, with readings normalized to Hiragana.
+    pub fn read_entries(&self) -> Result<Vec<Entry>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT entry FROM entries ORDER BY kanji")
+            .map_err(Error::Sqlite)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(Error::Sqlite)?;
+
+        rows.map(|raw| {
+            let raw = raw.map_err(Error::Sqlite)?;
+            let mut e: Entry = serde_json::from_str(&raw).map_err(Error::Json)?;
+            e.normalize();
+            Ok(e)
+        })
+        .collect()
+    }
+
+    /// A single entry, if it exists.
+    pub fn entry(&self, k: Kanji) -> Result<Option<Entry>, Error> {
+        let raw: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT entry FROM entries WHERE kanji = ?1",
+                params![k.to_string()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Error::Sqlite)?;
+
+        raw.map(|r| serde_json::from_str(&r).map_err(Error::Json))
+            .transpose()
+    }
+
+    /// Replace the entire contents of the database, all at once.
+    pub fn write_entries(&mut self, entries: Vec<Entry>) -> Result<(), Error> {
+        let tx = self.conn.transaction().map_err(Error::Sqlite)?;
+        tx.execute("DELETE FROM entries", [])
+            .map_err(Error::Sqlite)?;
+        entries.iter().try_for_each(|e| put(&tx, e))?;
+        tx.commit().map_err(Error::Sqlite)
+    }
+
+    /// Add or replace a single entry, leaving all others untouched.
+    pub fn put(&self, entry: &Entry) -> Result<(), Error> {
+        put(&self.conn, entry)
+    }
+
+    /// Remove a single entry, yielding whether there was one to remove.
+    pub fn remove(&self, k: Kanji) -> Result<bool, Error> {
+        let n = self
+            .conn
+            .execute(
+                "DELETE FROM entries WHERE kanji = ?1",
+                params![k.to_string()],
+            )
+            .map_err(Error::Sqlite)?;
+        Ok(n > 0)
+    }
+
+    /// Bring the whole "database" into memory.
+    pub fn open_db(&self, rules: Rules) -> Result<DB, Error> {
+        let hm = self
+            .read_entries()?
+            .into_iter()
+            .map(|e| (e.kanji, e))
+            .collect();
+
+        DB::new_with(hm, rules)
+    }
+
+    /// Replace the contents of the database with those of a `DB`.
+    pub fn write_db(&mut self, db: DB) -> Result<(), Error> {
+        self.write_entries(db.entries.into_values().collect())
+    }
+}
+
+fn put(conn: &Connection, entry: &Entry) -> Result<(), Error> {
+    let mut entry = entry.clone();
+    entry.oya.sort();
+    entry.normalize();
+    let raw = serde_json::to_string(&entry).map_err(Error::Json)?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO entries (kanji, entry) VALUES (?1, ?2)",
+        params![entry.kanji.to_string(), raw],
+    )
+    .map_err(Error::Sqlite)?;
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Reading variant data from the Unihan database's `Unihan_Variants.txt`.
+//!
+//! Each tab-separated line holds a code point, a field name, and one or more
+//! code points of variants, like `U+570B kSimplifiedVariant U+56FD`, the latter
+//! sometimes followed by a `<` and the sources that attest them. Only the
+//! fields that link whole forms of a character are read, which covers 旧字体
+//! and 新字体 pairs like 國/国 and 眞/真.
+
+use crate::Kanji;
+use std::collections::{BTreeSet, HashMap};
+
+/// The fields that count as variants.
+const FIELDS: [&str; 4] = [
+    "kSemanticVariant",
+    "kSimplifiedVariant",
+    "kTraditionalVariant",
+    "kZVariant",
+];
+
+/// The variants of every Kanji in a Unihan variants file. Links go both ways,
+/// even where the file only gives one direction.
+pub fn variant_table(raw: &str) -> HashMap<Kanji, BTreeSet<Kanji>> {
+    let mut table: HashMap<Kanji, BTreeSet<Kanji>> = HashMap::new();
+
+    raw.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let kanji = code_point(fields.next()?)?;
+            let field = fields.next()?;
+            let variants: Vec<Kanji> = fields
+                .next()?
+                .split_whitespace()
+                .filter_map(|v| code_point(v.split('<').next()?))
+                .filter(|v| *v != kanji)
+                .collect();
+
+            FIELDS.contains(&field).then_some((kanji, variants))
+        })
+        .for_each(|(kanji, variants)| {
+            variants.into_iter().for_each(|v| {
+                table.entry(kanji).or_default().insert(v);
+                table.entry(v).or_default().insert(kanji);
+            })
+        });
+
+    table
+}
+
+/// A Kanji written as `U+570B`.
+fn code_point(s: &str) -> Option<Kanji> {
+    let hex = s.strip_prefix("U+")?;
+    let c = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)?;
+    Kanji::new(c)
+}
@@ -1,78 +1,296 @@
 //! Utility functions for handling Japanese text that isn't appropriate to
 //! upstream into the `kanji` library.
 
-pub fn is_voiced_pair(a: &str, b: &str) -> bool {
-    let mut chars = a.chars().zip(b.chars());
-    chars
-        .next()
-        .and_then(|(x, y)| voiced_char(x).map(|c| c == y))
-        .unwrap_or(false)
-        && chars.all(|(x, y)| x == y)
+/// Convert any Katakana in a string to its Hiragana equivalent, leaving
+/// everything else untouched.
+pub fn to_hiragana(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
 }
 
-// は行 is excluded on purpose, since it doesn't follow proper voicing rules,
-// and no 音読み start with P while on their own. Example: 一票 doesn't count
-// since the P is "dynamic" from being paired with 一, and indeed dictionaries
-// don't list ぴょう as a reading for 票.
-fn voiced_char(c: char) -> Option<char> {
-    match c {
-        'か' => Some('が'),
-        'き' => Some('ぎ'),
-        'く' => Some('ぐ'),
-        'け' => Some('げ'),
-        'こ' => Some('ご'),
-        'が' => Some('か'),
-        'ぎ' => Some('き'),
-        'ぐ' => Some('く'),
-        'げ' => Some('け'),
-        'ご' => Some('こ'),
-        'さ' => Some('ざ'),
-        'し' => Some('じ'),
-        'す' => Some('ず'),
-        'せ' => Some('ぜ'),
-        'そ' => Some('ぞ'),
-        'ざ' => Some('さ'),
-        'じ' => Some('し'),
-        'ず' => Some('す'),
-        'ぜ' => Some('せ'),
-        'ぞ' => Some('そ'),
-        'た' => Some('だ'),
-        'ち' => Some('ぢ'),
-        'つ' => Some('づ'),
-        'て' => Some('で'),
-        'と' => Some('ど'),
-        'だ' => Some('た'),
-        'ぢ' => Some('ち'),
-        'づ' => Some('つ'),
-        'で' => Some('て'),
-        'ど' => Some('と'),
-        _ => None,
+/// Convert romaji (Hepburn or Kunrei) into Hiragana. Characters that aren't
+/// recognized as romaji, including any existing kana, pass through untouched.
+///
+/// As with an IME, a doubled `nn` is always ん, so `konnya` is こんや, the
+/// same as `kon'ya`. Before a vowel it's ん followed by a な行 syllable, as in
+/// `annai`. Unlike an IME, an `ny` after a vowel is ん followed by a や行
+/// syllable, so `kinyou` is きんよう: no 音読み has にゃ, にゅ, or にょ
+/// anywhere but at its start.
+pub fn romaji_to_hiragana(s: &str) -> String {
+    // Long vowels written with macrons or circumflexes are spelled out first.
+    let chars: Vec<char> = s
+        .to_lowercase()
+        .chars()
+        .flat_map(|c| match c {
+            'ā' | 'â' => vec!['a', 'a'],
+            'ī' | 'î' => vec!['i', 'i'],
+            'ū' | 'û' => vec!['u', 'u'],
+            'ē' | 'ê' => vec!['e', 'e'],
+            'ō' | 'ô' => vec!['o', 'u'],
+            _ => vec![c],
+        })
+        .collect();
+
+    let is_vowel = |c: Option<&char>| matches!(c, Some('a' | 'i' | 'u' | 'e' | 'o'));
+    let is_glide = |c: Option<&char>| is_vowel(c) || c == Some(&'y');
+    let mut out = String::new();
+    let mut i = 0;
+
+    while let Some(c) = chars.get(i) {
+        let next = chars.get(i + 1);
+        let after_vowel = i > 0 && is_vowel(chars.get(i - 1));
+
+        // 撥音: an `n` not starting a syllable of its own.
+        if *c == 'n' && (!is_glide(next) || (next == Some(&'y') && after_vowel)) {
+            out.push('ん');
+            i += match next {
+                Some('\'') => 2,
+                Some('n') if !is_vowel(chars.get(i + 2)) => 2,
+                _ => 1,
+            };
+            continue;
+        }
+
+        // 促音: a doubled consonant, or the `tch` of Hepburn.
+        if c.is_ascii_alphabetic()
+            && !is_glide(Some(c))
+            && (next == Some(c) || (*c == 't' && next == Some(&'c')))
+        {
+            out.push('っ');
+            i += 1;
+            continue;
+        }
+
+        let found = (1..=4).rev().find_map(|len| {
+            let token: String = chars.get(i..i + len)?.iter().collect();
+            syllable(&token).map(|kana| (kana, len))
+        });
+
+        match found {
+            Some((kana, len)) => {
+                out.push_str(kana);
+                i += len;
+            }
+            None => {
+                out.push(*c);
+                i += 1;
+            }
+        }
     }
+
+    out
+}
+
+/// Is a reading written wholly in Hiragana, as every 音読み should be?
+pub fn is_hiragana(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| matches!(c, 'ぁ'..='ゖ'))
+}
+
+/// Bring a reading as typed into Hiragana as best we can: whitespace is
+/// dropped, full-width letters narrowed, and romaji and Katakana converted.
+/// What's left over, like a stray Kanji, is kept for someone to notice.
+pub fn normalize_kana(s: &str) -> String {
+    let narrow: String = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            'Ａ'..='Ｚ' | 'ａ'..='ｚ' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            _ => c,
+        })
+        .collect();
+
+    to_hiragana(&romaji_to_hiragana(&narrow))
 }
 
-pub fn is_rhyme(a: &str, b: &str) -> bool {
-    let mut chars = a.chars().zip(b.chars());
-    chars
-        .next()
-        .map(|(x, y)| vowel(x) == vowel(y))
-        .unwrap_or(false)
-        && chars.all(|(x, y)| x == y)
+/// The Hiragana for a single romaji syllable.
+fn syllable(s: &str) -> Option<&'static str> {
+    let kana = match s {
+        "a" => "あ",
+        "i" => "い",
+        "u" => "う",
+        "e" => "え",
+        "o" => "お",
+        "ka" => "か",
+        "ki" => "き",
+        "ku" => "く",
+        "ke" => "け",
+        "ko" => "こ",
+        "kya" => "きゃ",
+        "kyu" => "きゅ",
+        "kyo" => "きょ",
+        "ga" => "が",
+        "gi" => "ぎ",
+        "gu" => "ぐ",
+        "ge" => "げ",
+        "go" => "ご",
+        "gya" => "ぎゃ",
+        "gyu" => "ぎゅ",
+        "gyo" => "ぎょ",
+        "sa" => "さ",
+        "shi" | "si" => "し",
+        "su" => "す",
+        "se" => "せ",
+        "so" => "そ",
+        "sha" | "sya" => "しゃ",
+        "shu" | "syu" => "しゅ",
+        "sho" | "syo" => "しょ",
+        "za" => "ざ",
+        "ji" | "zi" => "じ",
+        "zu" => "ず",
+        "ze" => "ぜ",
+        "zo" => "ぞ",
+        "ja" | "jya" | "zya" => "じゃ",
+        "ju" | "jyu" | "zyu" => "じゅ",
+        "jo" | "jyo" | "zyo" => "じょ",
+        "ta" => "た",
+        "chi" | "ti" => "ち",
+        "tsu" | "tu" => "つ",
+        "te" => "て",
+        "to" => "と",
+        "cha" | "tya" => "ちゃ",
+        "chu" | "tyu" => "ちゅ",
+        "cho" | "tyo" => "ちょ",
+        "da" => "だ",
+        "di" => "ぢ",
+        "du" => "づ",
+        "de" => "で",
+        "do" => "ど",
+        "dya" => "ぢゃ",
+        "dyu" => "ぢゅ",
+        "dyo" => "ぢょ",
+        "na" => "な",
+        "ni" => "に",
+        "nu" => "ぬ",
+        "ne" => "ね",
+        "no" => "の",
+        "nya" => "にゃ",
+        "nyu" => "にゅ",
+        "nyo" => "にょ",
+        "ha" => "は",
+        "hi" => "ひ",
+        "fu" | "hu" => "ふ",
+        "he" => "へ",
+        "ho" => "ほ",
+        "hya" => "ひゃ",
+        "hyu" => "ひゅ",
+        "hyo" => "ひょ",
+        "ba" => "ば",
+        "bi" => "び",
+        "bu" => "ぶ",
+        "be" => "べ",
+        "bo" => "ぼ",
+        "bya" => "びゃ",
+        "byu" => "びゅ",
+        "byo" => "びょ",
+        "pa" => "ぱ",
+        "pi" => "ぴ",
+        "pu" => "ぷ",
+        "pe" => "ぺ",
+        "po" => "ぽ",
+        "pya" => "ぴゃ",
+        "pyu" => "ぴゅ",
+        "pyo" => "ぴょ",
+        "ma" => "ま",
+        "mi" => "み",
+        "mu" => "む",
+        "me" => "め",
+        "mo" => "も",
+        "mya" => "みゃ",
+        "myu" => "みゅ",
+        "myo" => "みょ",
+        "ya" => "や",
+        "yu" => "ゆ",
+        "yo" => "よ",
+        "ra" => "ら",
+        "ri" => "り",
+        "ru" => "る",
+        "re" => "れ",
+        "ro" => "ろ",
+        "rya" => "りゃ",
+        "ryu" => "りゅ",
+        "ryo" => "りょ",
+        "wa" => "わ",
+        "wi" => "うぃ",
+        "we" => "うぇ",
+        "wo" => "を",
+        "fa" => "ふぁ",
+        "fi" => "ふぃ",
+        "fe" => "ふぇ",
+        "fo" => "ふぉ",
+        "xa" | "la" => "ぁ",
+        "xi" | "li" => "ぃ",
+        "xu" | "lu" => "ぅ",
+        "xe" | "le" => "ぇ",
+        "xo" | "lo" => "ぉ",
+        "xya" | "lya" => "ゃ",
+        "xyu" | "lyu" => "ゅ",
+        "xyo" | "lyo" => "ょ",
+        "xtsu" | "xtu" | "ltu" => "っ",
+        _ => return None,
+    };
+
+    Some(kana)
 }
 
-// TODO Account for small よ, etc.
-/// What is the vowel of the given Hiragana?
-fn vowel(c: char) -> Option<char> {
-    match c {
-        'あ' | 'か' | 'さ' | 'た' | 'な' | 'は' | 'ま' | 'や' | 'ら' | 'わ' => Some('あ'),
-        'が' | 'ざ' | 'だ' | 'ば' | 'ぱ' => Some('あ'),
-        'い' | 'き' | 'し' | 'ち' | 'に' | 'ひ' | 'み' | 'り' => Some('い'),
-        'ぎ' | 'じ' | 'ぢ' | 'び' | 'ぴ' => Some('い'),
-        'う' | 'く' | 'す' | 'つ' | 'ぬ' | 'ふ' | 'む' | 'ゆ' | 'る' => Some('う'),
-        'ぐ' | 'ず' | 'づ' | 'ぶ' | 'ぷ' => Some('う'),
-        'え' | 'け' | 'せ' | 'て' | 'ね' | 'へ' | 'め' | 'れ' => Some('え'),
-        'げ' | 'ぜ' | 'で' | 'べ' | 'ぺ' => Some('え'),
-        'お' | 'こ' | 'そ' | 'と' | 'の' | 'ほ' | 'も' | 'よ' | 'ろ' => Some('お'),
-        'ご' | 'ぞ' | 'ど' | 'ぼ' | 'ぽ' => Some('お'),
-        _ => None,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn youon() {
+        assert_eq!(romaji_to_hiragana("kyou"), "きょう");
+        assert_eq!(romaji_to_hiragana("ryuu"), "りゅう");
+        assert_eq!(romaji_to_hiragana("sho"), "しょ");
+        assert_eq!(romaji_to_hiragana("syo"), "しょ");
+        assert_eq!(romaji_to_hiragana("ja"), "じゃ");
+    }
+
+    #[test]
+    fn sokuon() {
+        assert_eq!(romaji_to_hiragana("gakkou"), "がっこう");
+        assert_eq!(romaji_to_hiragana("hassei"), "はっせい");
+        assert_eq!(romaji_to_hiragana("matcha"), "まっちゃ");
+    }
+
+    #[test]
+    fn hatsuon() {
+        assert_eq!(romaji_to_hiragana("kan"), "かん");
+        assert_eq!(romaji_to_hiragana("kann"), "かん");
+        assert_eq!(romaji_to_hiragana("kon'ya"), "こんや");
+        assert_eq!(romaji_to_hiragana("konnya"), "こんや");
+        assert_eq!(romaji_to_hiragana("annai"), "あんない");
+        assert_eq!(romaji_to_hiragana("shinbun"), "しんぶん");
+        assert_eq!(romaji_to_hiragana("kinyou"), "きんよう");
+        assert_eq!(romaji_to_hiragana("kin'you"), "きんよう");
+        assert_eq!(romaji_to_hiragana("nyou"), "にょう");
+    }
+
+    #[test]
+    fn long_vowels() {
+        assert_eq!(romaji_to_hiragana("ou"), "おう");
+        assert_eq!(romaji_to_hiragana("ō"), "おう");
+        assert_eq!(romaji_to_hiragana("tôkyô"), "とうきょう");
+        assert_eq!(romaji_to_hiragana("ū"), "うう");
+        assert_eq!(romaji_to_hiragana("ē"), "ええ");
+        assert_eq!(romaji_to_hiragana("sei"), "せい");
+    }
+
+    #[test]
+    fn foreign_syllables() {
+        assert_eq!(romaji_to_hiragana("wi"), "うぃ");
+        assert_eq!(romaji_to_hiragana("we"), "うぇ");
+        assert_eq!(romaji_to_hiragana("fa"), "ふぁ");
+    }
+
+    #[test]
+    fn passthrough() {
+        assert_eq!(romaji_to_hiragana("こう"), "こう");
+        assert_eq!(romaji_to_hiragana("KOU"), "こう");
+        assert_eq!(normalize_kana("コウ"), "こう");
+        assert_eq!(normalize_kana(" ｋｏｕ "), "こう");
     }
 }
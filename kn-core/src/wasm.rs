@@ -0,0 +1,66 @@
+//! Bindings for JavaScript, so that the database can power an explorer in the
+//! browser.
+//!
+//! The crate is only a library by default, so ask for a `cdylib` when building,
+//! then generate the JavaScript glue with `wasm-bindgen`:
+//!
+//! ```sh
+//! cargo rustc -p kn-core --lib --release --crate-type cdylib \
+//!   --target wasm32-unknown-unknown --no-default-features --features wasm
+//! wasm-bindgen --target web --out-dir pkg \
+//!   target/wasm32-unknown-unknown/release/kn_core.wasm
+//! ```
+//!
+//! Nothing here touches the filesystem: the data file's contents are handed
+//! over as a string.
+
+use crate::{DotMode, DotOptions, Kanji, DB};
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+/// A loaded database.
+#[wasm_bindgen]
+pub struct KanjiNet {
+    db: DB,
+}
+
+#[wasm_bindgen]
+impl KanjiNet {
+    /// Build the database from the contents of a data file, of any version.
+    #[wasm_bindgen(constructor)]
+    pub fn new(json: &str) -> Result<KanjiNet, JsError> {
+        let db = crate::open_db_from_reader(json.as_bytes())?;
+        Ok(KanjiNet { db })
+    }
+
+    /// The family of a Kanji, all of its ancestors and descendants, as JSON
+    /// lists of nodes and edges (see `export::JsonGraph`).
+    pub fn family(&self, kanji: &str) -> Result<String, JsError> {
+        let k = self.kanji(kanji)?;
+        let graph = self.db.filtered_graph(vec![k], None);
+        let json = serde_json::to_string(&self.db.to_json_graph_of(&graph))?;
+        Ok(json)
+    }
+
+    /// The family of a Kanji as DOT text, ready for a Graphviz renderer such as
+    /// `@viz-js/viz`.
+    pub fn dot(&self, kanji: &str) -> Result<String, JsError> {
+        let k = self.kanji(kanji)?;
+        let graph = self.db.filtered_graph(vec![k], None);
+        let opts = DotOptions {
+            mode: DotMode::Groups,
+            ..DotOptions::default()
+        };
+        Ok(self.db.dot_custom(HashSet::from([k]), &graph, &opts))
+    }
+
+    /// A Kanji known to the database.
+    fn kanji(&self, s: &str) -> Result<Kanji, JsError> {
+        let mut chars = s.chars();
+        match (chars.next().and_then(Kanji::new), chars.next()) {
+            (Some(k), None) if self.db.entries.contains_key(&k) => Ok(k),
+            (Some(k), None) => Err(JsError::new(&format!("No entry for {}.", k))),
+            _ => Err(JsError::new(&format!("Not a single Kanji: {}", s))),
+        }
+    }
+}
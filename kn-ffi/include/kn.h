@@ -0,0 +1,43 @@
+/* A C ABI over the KanjiNet graph. See kn-ffi/src/lib.rs for details.
+ *
+ * Functions that can fail return NULL, after which kn_last_error() says why.
+ * Strings returned by kn_entry, kn_family, and kn_dot belong to the caller and
+ * must be released with kn_string_free. All strings are UTF-8.
+ */
+
+#ifndef KN_H
+#define KN_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct KnDb KnDb;
+
+/* Open a data file, a directory of shards, or a SQLite database. */
+KnDb *kn_open(const char *path);
+
+/* Close a database. NULL is ignored. */
+void kn_close(KnDb *db);
+
+/* Everything known about a Kanji, as JSON. */
+char *kn_entry(const KnDb *db, const char *kanji);
+
+/* A Kanji's ancestors and descendants, as JSON lists of nodes and edges. */
+char *kn_family(const KnDb *db, const char *kanji);
+
+/* A Kanji's ancestors and descendants, as Graphviz DOT. */
+char *kn_dot(const KnDb *db, const char *kanji);
+
+/* Free a string returned by this library. NULL is ignored. */
+void kn_string_free(char *s);
+
+/* Why the last failing call on this thread failed, or NULL. Owned by the
+ * library. */
+const char *kn_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,209 @@
+//! A C ABI over the Kanji graph, for embedding it in other applications.
+//!
+//! The declarations live in `include/kn.h`. Every function that can fail
+//! yields `NULL`, after which `kn_last_error` says why. A panic is caught at
+//! the boundary and reported the same way, rather than unwinding into C.
+//! Strings given out by the library belong to the caller, and must be handed
+//! back to `kn_string_free`.
+
+use kanji::Kanji;
+use kn_core::{DotMode, DotOptions, DB};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+thread_local! {
+    /// Why the most recent call on this thread failed.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An opened database, opaque to C.
+pub struct KnDb {
+    db: DB,
+}
+
+/// Note why a call failed, and yield the `NULL` it should return.
+fn fail<T>(msg: String) -> *mut T {
+    let msg = CString::new(msg).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+    ptr::null_mut()
+}
+
+/// Run the body of an entry point, turning a panic into a failure.
+fn guard<T, F>(f: F) -> *mut T
+where
+    F: FnOnce() -> *mut T,
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|e| {
+        let msg = e
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown error.".to_string());
+        fail(format!("Internal error: {}", msg))
+    })
+}
+
+/// A borrowed C string as UTF-8.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or point to a NUL-terminated string.
+unsafe fn text<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("Unexpected NULL string.".to_string());
+    }
+
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| "The string isn't UTF-8.".to_string())
+}
+
+/// A single Kanji with an entry in the database.
+///
+/// # Safety
+///
+/// As for `text`.
+unsafe fn kanji(db: &KnDb, s: *const c_char) -> Result<Kanji, String> {
+    let s = text(s)?;
+    let mut chars = s.chars();
+
+    match (chars.next().and_then(Kanji::new), chars.next()) {
+        (Some(k), None) if db.db.entries.contains_key(&k) => Ok(k),
+        (Some(k), None) => Err(format!("No entry for {}.", k)),
+        _ => Err(format!("Not a single Kanji: {}", s)),
+    }
+}
+
+/// Give a Rust string over to C.
+fn give(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(_) => fail("The result contained a NUL byte.".to_string()),
+    }
+}
+
+/// Run a query against an open database, giving its result over to C.
+///
+/// # Safety
+///
+/// `db` must be `NULL` or come from `kn_open`, and `s` as for `text`.
+unsafe fn query<F>(db: *const KnDb, s: *const c_char, f: F) -> *mut c_char
+where
+    F: FnOnce(&KnDb, Kanji) -> Result<String, String>,
+{
+    guard(|| {
+        let db = match db.as_ref() {
+            Some(db) => db,
+            None => return fail("Unexpected NULL database.".to_string()),
+        };
+
+        match kanji(db, s).and_then(|k| f(db, k)) {
+            Ok(s) => give(s),
+            Err(e) => fail(e),
+        }
+    })
+}
+
+/// Open a data file (or a directory of shards, or a SQLite database), the
+/// same as `kin` would.
+///
+/// # Safety
+///
+/// `path` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kn_open(path: *const c_char) -> *mut KnDb {
+    guard(|| {
+        let path = match text(path) {
+            Ok(p) => p,
+            Err(e) => return fail(e),
+        };
+
+        match kn_core::open_db(Path::new(path)) {
+            Ok(db) => Box::into_raw(Box::new(KnDb { db })),
+            Err(e) => fail(e.to_string()),
+        }
+    })
+}
+
+/// Close a database. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `db` must be `NULL` or come from `kn_open`, and not have been closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn kn_close(db: *mut KnDb) {
+    if !db.is_null() {
+        // There's nothing to return a failure through.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(db))));
+    }
+}
+
+/// Everything known about a Kanji, as the JSON of `kin show`.
+///
+/// # Safety
+///
+/// `db` must come from `kn_open`, and `kanji` point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kn_entry(db: *const KnDb, kanji: *const c_char) -> *mut c_char {
+    query(db, kanji, |db, k| {
+        let report = db.db.report(k).ok_or(format!("No entry for {}.", k))?;
+        serde_json::to_string(&report).map_err(|e| e.to_string())
+    })
+}
+
+/// The family of a Kanji, all of its ancestors and descendants, as JSON lists
+/// of nodes and edges.
+///
+/// # Safety
+///
+/// As for `kn_entry`.
+#[no_mangle]
+pub unsafe extern "C" fn kn_family(db: *const KnDb, kanji: *const c_char) -> *mut c_char {
+    query(db, kanji, |db, k| {
+        let graph = db.db.filtered_graph(vec![k], None);
+        serde_json::to_string(&db.db.to_json_graph_of(&graph)).map_err(|e| e.to_string())
+    })
+}
+
+/// The family of a Kanji as DOT text, ready for Graphviz.
+///
+/// # Safety
+///
+/// As for `kn_entry`.
+#[no_mangle]
+pub unsafe extern "C" fn kn_dot(db: *const KnDb, kanji: *const c_char) -> *mut c_char {
+    query(db, kanji, |db, k| {
+        let graph = db.db.filtered_graph(vec![k], None);
+        let opts = DotOptions {
+            mode: DotMode::Groups,
+            ..DotOptions::default()
+        };
+        Ok(db.db.dot_custom(HashSet::from([k]), &graph, &opts))
+    })
+}
+
+/// Free a string given out by this library. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or come from this library, and not have been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn kn_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(CString::from_raw(s))));
+    }
+}
+
+/// Why the most recent failed call on this thread failed, or `NULL` if none
+/// has. The string is owned by the library, and lives until the next failure.
+#[no_mangle]
+pub extern "C" fn kn_last_error() -> *const c_char {
+    panic::catch_unwind(|| {
+        LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+    })
+    .unwrap_or(ptr::null())
+}